@@ -0,0 +1,113 @@
+//! Host-side Merkle tree over an allowlist of enclave measurements.
+//!
+//! The tree has a fixed depth of `ALLOWLIST_DEPTH` so that the circuit shape does not depend on
+//! the allowlist. Leaves are the 32-byte measurements themselves, padded by repeating the first
+//! one so that padding never allows anything else, and inner nodes are SHA256(left || right).
+use anyhow::{anyhow, ensure, Result};
+
+pub use crate::merkle::MerkleProof;
+use crate::merkle::MerkleTree;
+use crate::quote::{EnclaveReportBody, MR_ENCLAVE_OFFSET, MR_SIGNER_OFFSET};
+use crate::sgx_dcap_verifier::{MR_ENCLAVE_INSTANCE, MR_SIGNER_INSTANCE};
+
+pub const ALLOWLIST_DEPTH: usize = 8;
+
+/// The measurement of the ISV enclave report an allowlist applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllowlistTarget {
+    MrEnclave,
+    MrSigner,
+}
+
+impl AllowlistTarget {
+    pub fn measurement(self, report: &EnclaveReportBody) -> [u8; 32] {
+        match self {
+            Self::MrEnclave => report.mr_enclave,
+            Self::MrSigner => report.mr_signer,
+        }
+    }
+
+    pub(crate) fn report_offset(self) -> usize {
+        match self {
+            Self::MrEnclave => MR_ENCLAVE_OFFSET,
+            Self::MrSigner => MR_SIGNER_OFFSET,
+        }
+    }
+
+    /// First of the two public instances that hold the allowlist root instead of the measurement
+    pub(crate) fn instance(self) -> usize {
+        match self {
+            Self::MrEnclave => MR_ENCLAVE_INSTANCE,
+            Self::MrSigner => MR_SIGNER_INSTANCE,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Allowlist {
+    tree: MerkleTree,
+}
+
+impl Allowlist {
+    pub fn new(measurements: &[[u8; 32]]) -> Result<Self> {
+        ensure!(!measurements.is_empty(), "allowlist is empty");
+        ensure!(
+            measurements.len() <= 1 << ALLOWLIST_DEPTH,
+            "allowlist holds at most {} measurements",
+            1 << ALLOWLIST_DEPTH
+        );
+        let mut leaves = measurements.to_vec();
+        leaves.resize(1 << ALLOWLIST_DEPTH, measurements[0]);
+        Ok(Self {
+            tree: MerkleTree::new(leaves, ALLOWLIST_DEPTH),
+        })
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    /// Merkle proof of the first occurrence of `measurement`
+    pub fn proof(&self, measurement: &[u8; 32]) -> Result<MerkleProof> {
+        let index = self
+            .tree
+            .leaves()
+            .iter()
+            .position(|leaf| leaf == measurement)
+            .ok_or_else(|| anyhow!("measurement is not allowlisted"))?;
+        Ok(self.tree.proof(index))
+    }
+}
+
+/// An allowlist proof for the MRENCLAVE or MRSIGNER of the verified quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AllowlistPolicy {
+    pub target: AllowlistTarget,
+    pub proof: MerkleProof,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allowlist_proof() {
+        let measurements: Vec<[u8; 32]> = (1..=5).map(|i| [i; 32]).collect();
+        let allowlist = Allowlist::new(&measurements).unwrap();
+
+        for measurement in &measurements {
+            let proof = allowlist.proof(measurement).unwrap();
+            assert_eq!(proof.siblings.len(), ALLOWLIST_DEPTH);
+            assert_eq!(proof.root(measurement), allowlist.root());
+            assert_ne!(proof.root(&[9; 32]), allowlist.root());
+        }
+        assert!(allowlist.proof(&[9; 32]).is_err());
+
+        // padding does not allow anything else
+        assert!(allowlist.proof(&[0; 32]).is_err());
+
+        let too_many = vec![[1; 32]; (1 << ALLOWLIST_DEPTH) + 1];
+        assert!(Allowlist::new(&too_many).is_err());
+        assert!(Allowlist::new(&[]).is_err());
+    }
+}
@@ -0,0 +1,720 @@
+//! Base64 decoding chip, usable by any circuit built on a halo2-lib context.
+//!
+//! Each quartet of characters is looked up against the bit decompositions of its 6-bit values
+//! and of the 3 bytes it decodes to, see `BitDecompositionTableConfig`. The bytes are then
+//! re-assigned in the context, where the encoding is constrained to be canonical. The lookups
+//! relate characters and bytes both ways, so the same chip proves that a byte string encodes to
+//! a base64 string.
+use base64::{
+    engine::{general_purpose, GeneralPurpose},
+    Engine,
+};
+use halo2_base::utils::PrimeField;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    halo2_proofs::{
+        circuit::{Layouter, Value},
+        plonk::{Advice, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    },
+    AssignedValue, Context, QuantumCell,
+};
+use std::marker::PhantomData;
+
+use crate::table::{Base64Alphabet, BitDecompositionTableConfig};
+
+/// Default max number of base64 characters of a decoded string, which covers PCK certificates
+/// of up to 1536 DER bytes
+pub const BASE64_MAX_ENCODED_LEN: usize = 2048;
+// character the rows past an encoded string are padded with, which decodes to zero bytes
+pub(crate) const BASE64_PADDING_CHAR: u8 = b'A';
+const BIT_DECOMPOSITION_ADVICE_COL_COUNT: usize = 12;
+
+// Here we decompose a transition into 3-value lookups.
+#[derive(Debug, Clone)]
+pub struct Base64DecodeConfig<F: PrimeField> {
+    encoded_chars: Column<Advice>, // This is the raw ASCII character value -- like 'a' would be 97
+    bit_decompositions: [Column<Advice>; BIT_DECOMPOSITION_ADVICE_COL_COUNT],
+    decoded_chars: Column<Advice>, // This has a 1 char gap between each group of 3 chars
+    decoded_chars_without_gap: Column<Advice>,
+    bit_decomposition_table: BitDecompositionTableConfig<F>,
+    q_decode_selector: Selector,
+    // rows of each decoded string, whatever its length
+    max_encoded_len: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: PrimeField> Base64DecodeConfig<F> {
+    /// Every decoded string takes `max_encoded_len` rows, a multiple of 4, so that a single
+    /// verifying key covers strings of any length up to it
+    pub fn configure(meta: &mut ConstraintSystem<F>, max_encoded_len: usize) -> Self {
+        Self::configure_with_alphabet(meta, max_encoded_len, Base64Alphabet::Standard)
+    }
+
+    /// Same as `configure`, for strings of the given alphabet
+    pub fn configure_with_alphabet(
+        meta: &mut ConstraintSystem<F>,
+        max_encoded_len: usize,
+        alphabet: Base64Alphabet,
+    ) -> Self {
+        assert_eq!(
+            max_encoded_len % 4,
+            0,
+            "base64 is decoded by groups of 4 characters"
+        );
+        let mut bit_decompositions = vec![];
+        for _ in 0..BIT_DECOMPOSITION_ADVICE_COL_COUNT {
+            bit_decompositions.push(meta.advice_column());
+        }
+        let encoded_chars = meta.advice_column();
+        let decoded_chars = meta.advice_column();
+        let decoded_chars_without_gap = meta.advice_column();
+        let bit_decomposition_table = BitDecompositionTableConfig::configure(meta, alphabet);
+        let q_decode_selector = meta.complex_selector();
+
+        meta.enable_equality(encoded_chars);
+        meta.enable_equality(decoded_chars);
+        meta.enable_equality(decoded_chars_without_gap);
+
+        // Create bit lookup for each bit
+        const ENCODED_LOOKUP_COLS: [usize; 4] = [0, 1, 2, 3];
+        const ENCODED_BIT_LOOKUP_COLS: [[usize; 3]; 4] =
+            [[0, 1, 2], [3, 4, 5], [6, 7, 8], [9, 10, 11]];
+        const DECODED_LOOKUP_COLS: [usize; 3] = [0, 1, 2];
+        const DECODED_BIT_LOOKUP_COLS: [[usize; 4]; 3] =
+            [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]];
+
+        let config = Self {
+            encoded_chars,
+            bit_decompositions: bit_decompositions.try_into().unwrap(),
+            decoded_chars,
+            decoded_chars_without_gap,
+            bit_decomposition_table,
+            q_decode_selector,
+            max_encoded_len,
+            _marker: PhantomData,
+        };
+        // Create bit lookup for each 6-bit encoded value
+        for i in 0..ENCODED_LOOKUP_COLS.len() {
+            config.create_bit_lookup(
+                meta,
+                i,
+                true,
+                ENCODED_BIT_LOOKUP_COLS[i].to_vec(),
+                [2, 1, 0].to_vec(),
+                config.q_decode_selector,
+            );
+        }
+        // Create bit lookup for each 8-bit decoded value
+        for i in 0..DECODED_LOOKUP_COLS.len() {
+            config.create_bit_lookup(
+                meta,
+                i,
+                false,
+                DECODED_BIT_LOOKUP_COLS[i].to_vec(),
+                [3, 2, 1, 0].to_vec(),
+                config.q_decode_selector,
+            );
+        }
+        config
+    }
+
+    #[inline]
+    fn create_bit_lookup(
+        &self,
+        meta: &mut ConstraintSystem<F>,
+        encoded_or_decoded_index_offset: usize,
+        encoded_if_true_and_decoded_if_false: bool,
+        bit_query_cols: Vec<usize>,
+        bit_lookup_cols: Vec<usize>,
+        selector_col: Selector,
+    ) {
+        meta.lookup("lookup base64 encode/decode", |meta| {
+            assert!(bit_query_cols.len() == bit_lookup_cols.len());
+            let q = meta.query_selector(selector_col);
+
+            // One minus q defaults to the 'a' value and '0' bit values
+            let one_minus_q = Expression::Constant(F::from(1)) - q.clone();
+            let zero = Expression::Constant(F::from(0));
+            let zero_char = Expression::Constant(F::from(65));
+
+            let mut lookup_vec = vec![];
+            if encoded_if_true_and_decoded_if_false {
+                let encoded_char = meta.query_advice(
+                    self.encoded_chars,
+                    Rotation(encoded_or_decoded_index_offset as i32),
+                );
+                lookup_vec.push((
+                    q.clone() * encoded_char + one_minus_q.clone() * zero_char.clone(),
+                    self.bit_decomposition_table.character,
+                ));
+            } else {
+                let decoded_char = meta.query_advice(
+                    self.decoded_chars,
+                    Rotation(encoded_or_decoded_index_offset as i32),
+                );
+                lookup_vec.push((
+                    q.clone() * decoded_char + one_minus_q.clone() * zero.clone(),
+                    self.bit_decomposition_table.value_decoded,
+                ));
+            }
+            for i in 0..bit_query_cols.len() {
+                let bit =
+                    meta.query_advice(self.bit_decompositions[bit_query_cols[i]], Rotation::cur());
+                lookup_vec.push((
+                    q.clone() * bit + one_minus_q.clone() * zero.clone(),
+                    self.bit_decomposition_table.bit_decompositions[bit_lookup_cols[i]],
+                ));
+            }
+            lookup_vec
+        });
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.bit_decomposition_table.load(layouter)
+    }
+
+    pub fn max_encoded_len(&self) -> usize {
+        self.max_encoded_len
+    }
+
+    pub fn alphabet(&self) -> Base64Alphabet {
+        self.bit_decomposition_table.alphabet
+    }
+
+    fn engine(&self) -> &'static GeneralPurpose {
+        match self.alphabet() {
+            Base64Alphabet::Standard => &general_purpose::STANDARD,
+            Base64Alphabet::UrlSafe => &general_purpose::URL_SAFE_NO_PAD,
+        }
+    }
+}
+
+/// Characters and bytes of a base64 string assigned by `Base64DecodeChip`, both padded to the
+/// configured max length
+#[derive(Debug, Clone)]
+pub struct AssignedBase64<'v, F: PrimeField> {
+    pub encoded: Vec<AssignedValue<'v, F>>,
+    pub decoded: Vec<AssignedValue<'v, F>>,
+    /// Number of bytes encoded by the string, without the bytes of the padding
+    pub decoded_len: AssignedValue<'v, F>,
+    /// Number of characters of the string, without the padding rows
+    pub encoded_len: AssignedValue<'v, F>,
+}
+
+/// Decodes and encodes base64 strings in the region of a halo2-lib context, one after the other
+#[derive(Debug)]
+pub struct Base64DecodeChip<'a, F: PrimeField> {
+    config: &'a Base64DecodeConfig<F>,
+    gate: &'a FlexGateConfig<F>,
+    // first row of the next string in the base64 columns
+    row: usize,
+}
+
+impl<'a, F: PrimeField> Base64DecodeChip<'a, F> {
+    /// A chip is created per context, since it assigns the base64 columns of its region
+    pub fn new(config: &'a Base64DecodeConfig<F>, gate: &'a FlexGateConfig<F>) -> Self {
+        Self {
+            config,
+            gate,
+            row: 0,
+        }
+    }
+
+    pub fn config(&self) -> &'a Base64DecodeConfig<F> {
+        self.config
+    }
+
+    /// Decodes `encoded`, of up to `max_encoded_len` characters. The returned bytes are followed
+    /// by zero bytes up to `max_encoded_len / 4 * 3`.
+    pub fn decode<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        encoded: &[u8],
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        self.decode_with_len(ctx, encoded)
+            .map(|(decoded, _)| decoded)
+    }
+
+    /// Same as `decode`, also returning the true decoded length, i.e. without the bytes of the
+    /// padding
+    pub fn decode_with_len<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        encoded: &[u8],
+    ) -> Result<(Vec<AssignedValue<'v, F>>, AssignedValue<'v, F>), Error> {
+        self.decode_string(ctx, encoded)
+            .map(|assigned| (assigned.decoded, assigned.decoded_len))
+    }
+
+    /// Same as `decode_with_len`, also returning the assigned characters and length of the
+    /// string, e.g. to constrain them to characters taken from a larger witness
+    pub fn decode_string<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        encoded: &[u8],
+    ) -> Result<AssignedBase64<'v, F>, Error> {
+        let decoded = self
+            .config
+            .engine()
+            .decode(encoded)
+            .map_err(|_| Error::Synthesis)?;
+        self.assign(ctx, encoded, decoded)
+    }
+
+    /// Encodes `bytes` in the alphabet of the config. The returned characters are proven to
+    /// encode the returned bytes, which the caller constrains to the byte string to encode, and
+    /// the encoded string to the expected one.
+    pub fn encode<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[u8],
+    ) -> Result<AssignedBase64<'v, F>, Error> {
+        let encoded = self.config.engine().encode(bytes);
+        self.assign(ctx, encoded.as_bytes(), bytes.to_vec())
+    }
+
+    fn assign<'v>(
+        &mut self,
+        ctx: &mut Context<'v, F>,
+        encoded: &[u8],
+        mut decoded: Vec<u8>,
+    ) -> Result<AssignedBase64<'v, F>, Error> {
+        let max_encoded_len = self.config.max_encoded_len;
+        let valid_len = match self.config.alphabet() {
+            Base64Alphabet::Standard => encoded.len() % 4 == 0,
+            Base64Alphabet::UrlSafe => encoded.len() % 4 != 1,
+        };
+        if encoded.len() > max_encoded_len || !valid_len {
+            return Err(Error::Synthesis);
+        }
+        // the padding rows decode to zero bytes, as do the '=' of the string
+        decoded.resize(max_encoded_len / 4 * 3, 0);
+        let mut characters = encoded.to_vec();
+        characters.resize(max_encoded_len, BASE64_PADDING_CHAR);
+
+        let characters = self.assign_characters(ctx, &characters)?;
+        let decoded = self.assign_decoded(ctx, &decoded)?;
+        let (decoded_len, encoded_len) =
+            self.constrain_padding(ctx, &characters, &decoded, encoded.len());
+        self.row += max_encoded_len;
+        Ok(AssignedBase64 {
+            encoded: characters,
+            decoded,
+            decoded_len,
+            encoded_len,
+        })
+    }
+
+    /// Assigns the characters and the bit decompositions of their 6-bit values in the base64
+    /// columns, and re-assigns the characters in the context
+    fn assign_characters<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        characters: &[u8],
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let config = self.config;
+        let mut assigned = vec![];
+        for (i, character) in characters.iter().enumerate() {
+            let bit_val: u8 = config
+                .bit_decomposition_table
+                .map_character_to_encoded_value(*character as char);
+            let cell = ctx.region.assign_advice(
+                || "encoded character",
+                config.encoded_chars,
+                self.row + i,
+                || Value::known(F::from(*character as u64)),
+            )?;
+            let value = self
+                .gate
+                .load_witness(ctx, Value::known(F::from(*character as u64)));
+            ctx.region.constrain_equal(cell.cell(), value.cell())?;
+            assigned.push(value);
+
+            // Set bit values by decomposing the encoded character
+            for j in 0..3 {
+                ctx.region.assign_advice(
+                    || "bit assignment",
+                    config.bit_decompositions[(i % 4) * 3 + j],
+                    self.row + i - (i % 4),
+                    || Value::known(F::from(((bit_val >> ((2 - j) * 2)) % 4) as u64)),
+                )?;
+            }
+        }
+
+        // Enable q_decomposed on every 4 rows
+        for i in (0..characters.len()).step_by(4) {
+            config
+                .q_decode_selector
+                .enable(&mut ctx.region, self.row + i)?;
+        }
+        Ok(assigned)
+    }
+
+    /// Assigns the decoded bytes in the base64 columns, with a 1 row gap after each group of 3,
+    /// and re-assigns them in the context
+    fn assign_decoded<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        decoded: &[u8],
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let config = self.config;
+        let mut assigned = vec![];
+        for (i, byte) in decoded.iter().enumerate() {
+            let cell = ctx.region.assign_advice(
+                || "decoded character",
+                config.decoded_chars_without_gap,
+                self.row + i,
+                || Value::known(F::from(*byte as u64)),
+            )?;
+            cell.copy_advice(
+                || "copying to add offset",
+                &mut ctx.region,
+                config.decoded_chars,
+                self.row + i + (i / 3),
+            )?;
+            let value = self
+                .gate
+                .load_witness(ctx, Value::known(F::from(*byte as u64)));
+            ctx.region.constrain_equal(cell.cell(), value.cell())?;
+            assigned.push(value);
+        }
+        Ok(assigned)
+    }
+
+    /// Constrains the base64 encoding of `decoded` to be canonical, and returns the true decoded
+    /// and encoded lengths. The string is split in a witnessed number of quartets, the last of
+    /// which may end with one or two padding positions:
+    /// - the characters past the quartets of the string are the padding character, so that the
+    ///   padding rows decode to zero bytes whatever the length
+    /// - in the standard alphabet, the padding positions are the '=' of the string, which only
+    ///   appear in the last one or two positions of the last quartet
+    /// - in the URL-safe alphabet, the string has no '=' and the witnessed number of padding
+    ///   positions are omitted from the string, i.e. they hold the padding character
+    /// - the unused low bits of the character before the padding are zero, i.e. the decoded byte
+    ///   at each padding position is zero
+    fn constrain_padding<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        encoded: &[AssignedValue<'v, F>],
+        decoded: &[AssignedValue<'v, F>],
+        encoded_len: usize,
+    ) -> (AssignedValue<'v, F>, AssignedValue<'v, F>) {
+        let gate = self.gate;
+        let alphabet = self.config.alphabet();
+        let groups = gate.load_witness(ctx, Value::known(F::from(((encoded_len + 3) / 4) as u64)));
+        let indicator =
+            gate.idx_to_indicator(ctx, QuantumCell::Existing(&groups), encoded.len() / 4 + 1);
+
+        // number of omitted padding positions of the URL-safe alphabet: 0, 1 or 2
+        let omitted = gate.load_witness(
+            ctx,
+            Value::known(F::from(((4 - encoded_len % 4) % 4) as u64)),
+        );
+        let omitted_is: Vec<AssignedValue<'v, F>> = (0..3u64)
+            .map(|n| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(&omitted),
+                    QuantumCell::Constant(F::from(n)),
+                )
+            })
+            .collect();
+        let omitted_is_valid = gate.sum(ctx, omitted_is.iter().map(QuantumCell::Existing));
+        gate.assert_is_const(ctx, &omitted_is_valid, F::one());
+        if alphabet == Base64Alphabet::Standard {
+            gate.assert_is_const(ctx, &omitted_is[0], F::one());
+        }
+        let omits_last = gate.not(ctx, QuantumCell::Existing(&omitted_is[0]));
+
+        // a group is in the string until the indicator of the number of groups is met, and the
+        // last group of the string is the one before it
+        let mut in_string = gate.load_constant(ctx, F::one());
+        let mut padding_positions = vec![];
+        for (g, (group, bytes)) in encoded.chunks(4).zip(decoded.chunks(3)).enumerate() {
+            in_string = gate.sub(
+                ctx,
+                QuantumCell::Existing(&in_string),
+                QuantumCell::Existing(&indicator[g]),
+            );
+            let is_last = &indicator[g + 1];
+            let not_last = gate.not(ctx, QuantumCell::Existing(is_last));
+
+            let is_padding: Vec<AssignedValue<'v, F>> = match alphabet {
+                Base64Alphabet::Standard => group
+                    .iter()
+                    .map(|character| {
+                        gate.is_equal(
+                            ctx,
+                            QuantumCell::Existing(character),
+                            QuantumCell::Constant(F::from(b'=' as u64)),
+                        )
+                    })
+                    .collect(),
+                Base64Alphabet::UrlSafe => {
+                    let zero = gate.load_zero(ctx);
+                    let third = gate.and(
+                        ctx,
+                        QuantumCell::Existing(is_last),
+                        QuantumCell::Existing(&omitted_is[2]),
+                    );
+                    let fourth = gate.and(
+                        ctx,
+                        QuantumCell::Existing(is_last),
+                        QuantumCell::Existing(&omits_last),
+                    );
+                    vec![zero.clone(), zero, third, fourth]
+                }
+            };
+
+            for (character, is_padding) in group.iter().zip(is_padding.iter()) {
+                // characters of the string, but the omitted padding positions
+                let keep = match alphabet {
+                    Base64Alphabet::Standard => in_string.clone(),
+                    Base64Alphabet::UrlSafe => {
+                        let is_kept = gate.not(ctx, QuantumCell::Existing(is_padding));
+                        gate.and(
+                            ctx,
+                            QuantumCell::Existing(&in_string),
+                            QuantumCell::Existing(&is_kept),
+                        )
+                    }
+                };
+                let expected = gate.select(
+                    ctx,
+                    QuantumCell::Existing(character),
+                    QuantumCell::Constant(F::from(BASE64_PADDING_CHAR as u64)),
+                    QuantumCell::Existing(&keep),
+                );
+                gate.assert_equal(
+                    ctx,
+                    QuantumCell::Existing(character),
+                    QuantumCell::Existing(&expected),
+                );
+            }
+
+            // no padding in the first two positions, nor outside of the last group of the string
+            for is_padding in &is_padding[..2] {
+                gate.assert_is_const(ctx, is_padding, F::zero());
+            }
+            for (position, byte) in [(2, &bytes[1]), (3, &bytes[2])] {
+                for must_be_zero in [&not_last, byte] {
+                    let invalid = gate.mul(
+                        ctx,
+                        QuantumCell::Existing(&is_padding[position]),
+                        QuantumCell::Existing(must_be_zero),
+                    );
+                    gate.assert_is_const(ctx, &invalid, F::zero());
+                }
+            }
+            // padding in the third position is followed by another one
+            let not_padded = gate.not(ctx, QuantumCell::Existing(&is_padding[3]));
+            let invalid = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_padding[2]),
+                QuantumCell::Existing(&not_padded),
+            );
+            gate.assert_is_const(ctx, &invalid, F::zero());
+            padding_positions.extend(is_padding.into_iter().skip(2));
+        }
+
+        // 3 bytes per group of the string, but one per padding position
+        let decoded_len = gate.mul(
+            ctx,
+            QuantumCell::Existing(&groups),
+            QuantumCell::Constant(F::from(3)),
+        );
+        let padding_len = gate.sum(ctx, padding_positions.iter().map(QuantumCell::Existing));
+        let decoded_len = gate.sub(
+            ctx,
+            QuantumCell::Existing(&decoded_len),
+            QuantumCell::Existing(&padding_len),
+        );
+        // 4 characters per group, but the omitted padding positions
+        let encoded_len = gate.mul(
+            ctx,
+            QuantumCell::Existing(&groups),
+            QuantumCell::Constant(F::from(4)),
+        );
+        let encoded_len = gate.sub(
+            ctx,
+            QuantumCell::Existing(&encoded_len),
+            QuantumCell::Existing(&omitted),
+        );
+        (decoded_len, encoded_len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sgx_dcap_verifier::{CircuitParams, SgxDcapVerifierConfig};
+    use halo2_base::halo2_proofs::{
+        circuit::SimpleFloorPlanner, dev::MockProver, halo2curves::bn256::Fr, plonk::Circuit,
+    };
+    use halo2_base::SKIP_FIRST_PASS;
+
+    const MAX_ENCODED_LEN: usize = 16;
+
+    // decodes `encoded`, or encodes `decoded`, and exposes the decoded length
+    #[derive(Default)]
+    struct Base64TestCircuit {
+        alphabet: Base64Alphabet,
+        encode: bool,
+        encoded: Vec<u8>,
+        decoded: Vec<u8>,
+    }
+
+    impl Circuit<Fr> for Base64TestCircuit {
+        // the base64 chip of the config decodes the standard alphabet, the other one the
+        // URL-safe alphabet
+        type Config = (SgxDcapVerifierConfig<Fr>, Base64DecodeConfig<Fr>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            (
+                SgxDcapVerifierConfig::configure_with_params(
+                    meta,
+                    &CircuitParams::default(),
+                    vec![64],
+                    MAX_ENCODED_LEN,
+                )
+                .unwrap(),
+                Base64DecodeConfig::configure_with_alphabet(
+                    meta,
+                    MAX_ENCODED_LEN,
+                    Base64Alphabet::UrlSafe,
+                ),
+            )
+        }
+
+        fn synthesize(
+            &self,
+            (config, url_safe): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            url_safe.load(&mut layouter)?;
+            let mut outputs = vec![];
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "base64",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let mut aux = config.new_context(region);
+                    let ctx = &mut aux;
+
+                    let mut base64 = match self.alphabet {
+                        Base64Alphabet::Standard => config.base64_chip(),
+                        Base64Alphabet::UrlSafe => Base64DecodeChip::new(&url_safe, config.gate()),
+                    };
+                    let (encoded, decoded, decoded_len) = if self.encode {
+                        let assigned = base64.encode(ctx, &self.decoded)?;
+                        (assigned.encoded, assigned.decoded, assigned.decoded_len)
+                    } else {
+                        let (decoded, decoded_len) = base64.decode_with_len(ctx, &self.encoded)?;
+                        (vec![], decoded, decoded_len)
+                    };
+                    let mut expected = self.decoded.clone();
+                    expected.resize(MAX_ENCODED_LEN / 4 * 3, 0);
+                    config.constrain_bytes(ctx, &decoded, &expected);
+                    if self.encode {
+                        let mut expected = self.encoded.clone();
+                        expected.resize(MAX_ENCODED_LEN, BASE64_PADDING_CHAR);
+                        config.constrain_bytes(ctx, &encoded, &expected);
+                    }
+                    outputs = vec![decoded_len.cell()];
+
+                    config.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            config.constrain_instances(&mut layouter, outputs)
+        }
+    }
+
+    #[test]
+    fn test_base64_decode() {
+        let k = 17;
+        for (encoded, decoded) in [
+            (&b"SGVsbG8="[..], &b"Hello"[..]),
+            (&b"SGk="[..], &b"Hi"[..]),
+            (&b"SA=="[..], &b"H"[..]),
+            (&b""[..], &b""[..]),
+        ] {
+            let circuit = Base64TestCircuit {
+                encoded: encoded.to_vec(),
+                decoded: decoded.to_vec(),
+                ..Default::default()
+            };
+            let instances = vec![Fr::from(decoded.len() as u64)];
+            let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+            prover.assert_satisfied();
+
+            // the '=' padding is not counted in the decoded length
+            if decoded.len() % 3 != 0 {
+                let instances = vec![Fr::from((encoded.len() / 4 * 3) as u64)];
+                let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+                assert!(prover.verify().is_err());
+            }
+        }
+
+        // strings longer than the configured maximum do not fit
+        let circuit = Base64TestCircuit {
+            encoded: b"SGVsbG8sIHdvcmxkIQ==".to_vec(),
+            decoded: b"Hello, world!".to_vec(),
+            ..Default::default()
+        };
+        assert!(MockProver::run(k, &circuit, vec![vec![Fr::zero()]]).is_err());
+    }
+
+    #[test]
+    fn test_base64_url_safe_and_encode() {
+        let k = 17;
+        for (alphabet, encoded, decoded) in [
+            (Base64Alphabet::UrlSafe, &b"-_8"[..], &[0xfb, 0xff][..]),
+            (Base64Alphabet::UrlSafe, &b"SGVsbG8"[..], &b"Hello"[..]),
+            (Base64Alphabet::UrlSafe, &b"SA"[..], &b"H"[..]),
+            (Base64Alphabet::Standard, &b"+/8="[..], &[0xfb, 0xff][..]),
+        ] {
+            for encode in [false, true] {
+                let circuit = Base64TestCircuit {
+                    alphabet,
+                    encode,
+                    encoded: encoded.to_vec(),
+                    decoded: decoded.to_vec(),
+                };
+                let instances = vec![Fr::from(decoded.len() as u64)];
+                let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+                prover.assert_satisfied();
+            }
+        }
+
+        // the URL-safe alphabet has no padding, nor '+' and '/'
+        for encoded in [&b"SA=="[..], &b"+/8"[..]] {
+            let circuit = Base64TestCircuit {
+                alphabet: Base64Alphabet::UrlSafe,
+                encoded: encoded.to_vec(),
+                ..Default::default()
+            };
+            assert!(MockProver::run(k, &circuit, vec![vec![Fr::zero()]]).is_err());
+        }
+
+        // the encoding of the bytes is not another string
+        let circuit = Base64TestCircuit {
+            alphabet: Base64Alphabet::UrlSafe,
+            encode: true,
+            encoded: b"SGVsbG9".to_vec(),
+            decoded: b"Hello".to_vec(),
+        };
+        let prover = MockProver::run(k, &circuit, vec![vec![Fr::from(5)]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
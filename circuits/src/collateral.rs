@@ -0,0 +1,1122 @@
+//! Host-side parsing and verification of Intel's PCS collateral: the TCBInfo JSON of a platform
+//! and the QE Identity JSON, both signed by the TCB Signing certificate.
+//!
+//! Intel signs the exact bytes of the `tcbInfo` or `enclaveIdentity` value of the JSON document,
+//! as served by the PCS without any insignificant whitespace. The signed body is taken from the
+//! document as is, with the whitespace outside of strings stripped so that a reformatted document
+//! still verifies.
+use anyhow::{anyhow, ensure, Result};
+use halo2_base::halo2_proofs::halo2curves::{
+    group::{ff::Field, prime::PrimeCurveAffine, Curve, Group},
+    secp256r1::{Fp, Fq, Secp256r1Affine},
+    CurveAffine,
+};
+use halo2_base::utils::{biguint_to_fe, fe_to_biguint, modulus};
+use num_bigint::BigUint;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+use crate::sgx_dcap_verifier::{INTEL_SGX_ROOT_CA_PUBKEY, JSON_FIELD_SEARCH};
+use crate::tcb::{QeIdentity, QeTcbLevel, TcbLevel, TcbLevels, TcbStatus};
+use crate::x509::{CertLayout, CrlLayout, SGX_TCB_COMPONENTS};
+
+const TCB_INFO_KEY: &str = "tcbInfo";
+const ENCLAVE_IDENTITY_KEY: &str = "enclaveIdentity";
+
+// keys of the values of the signed bodies located in-circuit. The keys of strings end with the
+// opening quote of the value.
+pub(crate) const FMSPC_KEY: &str = "\"fmspc\":\"";
+pub(crate) const PCE_ID_KEY: &str = "\"pceId\":\"";
+pub(crate) const TCB_LEVELS_KEY: &str = "\"tcbLevels\":";
+pub(crate) const TCB_STATUS_KEY: &str = "\"tcbStatus\":\"";
+pub(crate) const PCE_SVN_KEY: &str = "\"pcesvn\":";
+pub(crate) const ID_KEY: &str = "\"id\":\"";
+pub(crate) const MISC_SELECT_KEY: &str = "\"miscselect\":\"";
+pub(crate) const MISC_SELECT_MASK_KEY: &str = "\"miscselectMask\":\"";
+pub(crate) const ATTRIBUTES_KEY: &str = "\"attributes\":\"";
+pub(crate) const ATTRIBUTES_MASK_KEY: &str = "\"attributesMask\":\"";
+pub(crate) const MR_SIGNER_KEY: &str = "\"mrsigner\":\"";
+pub(crate) const ISV_PROD_ID_KEY: &str = "\"isvprodid\":";
+pub(crate) const ISV_SVN_KEY: &str = "\"isvsvn\":";
+/// End of the key of every number of a TCB level, e.g. `"sgxtcbcomp01svn":`, `{"svn":` or
+/// `"pcesvn":`
+pub(crate) const SVN_KEY_SUFFIX: &str = "svn\":";
+/// Min length of a TCB level object in the signed bodies, which have a `tcbDate` each
+pub(crate) const TCB_LEVEL_MIN_LEN: usize = 64;
+/// `id` of the QE Identity, as opposed to the QVE or TDX QE identities
+pub const QE_IDENTITY_ID: &str = "QE";
+
+/// Verifies a secp256r1 ECDSA signature over SHA256(`message`). `pubkey` holds the big-endian
+/// `x || y` public key and `signature` the big-endian `r || s`.
+pub fn verify_signature(pubkey: &[u8], message: &[u8], signature: &[u8]) -> Result<()> {
+    ensure!(
+        pubkey.len() == 64 && signature.len() == 64,
+        "invalid public key or signature length"
+    );
+    let le = |be: &[u8]| {
+        let mut le: [u8; 32] = be.try_into().unwrap();
+        le.reverse();
+        le
+    };
+
+    let x: Option<Fp> = Fp::from_bytes(&le(&pubkey[..32])).into();
+    let y: Option<Fp> = Fp::from_bytes(&le(&pubkey[32..])).into();
+    let pubkey: Secp256r1Affine = x
+        .zip(y)
+        .and_then(|(x, y)| Secp256r1Affine::from_xy(x, y).into())
+        .ok_or_else(|| anyhow!("public key is not a secp256r1 point"))?;
+    let r: Option<Fq> = Fq::from_bytes(&le(&signature[..32])).into();
+    let s: Option<Fq> = Fq::from_bytes(&le(&signature[32..])).into();
+    let (r, s) = r
+        .zip(s)
+        .filter(|(r, s)| !bool::from(r.is_zero()) && !bool::from(s.is_zero()))
+        .ok_or_else(|| anyhow!("invalid signature"))?;
+
+    let msghash = BigUint::from_bytes_be(&Sha256::digest(message)) % modulus::<Fq>();
+    let s_inv = s.invert().unwrap();
+    let u1 = biguint_to_fe::<Fq>(&msghash) * s_inv;
+    let u2 = r * s_inv;
+    let point = Secp256r1Affine::generator() * u1 + pubkey * u2;
+    ensure!(!bool::from(point.is_identity()), "invalid signature");
+    ensure!(
+        fe_to_biguint(&point.to_affine().x) % modulus::<Fq>() == fe_to_biguint(&r),
+        "signature verification failed"
+    );
+    Ok(())
+}
+
+/// Verifies the issuer's signature of a DER encoded certificate, returning the big-endian
+/// `x || y` public key of the certificate
+pub fn verify_cert(der: &[u8], issuer_pubkey: &[u8]) -> Result<Vec<u8>> {
+    let layout = CertLayout::parse(der)?;
+    verify_der_signature(
+        der,
+        &layout.tbs,
+        [&layout.signature_r, &layout.signature_s],
+        issuer_pubkey,
+    )?;
+    Ok(der[layout.pubkey].to_vec())
+}
+
+/// Verifies a DER encoded CRL with the big-endian `x || y` public key of its issuer, returning its
+/// layout
+pub fn verify_crl(der: &[u8], issuer_pubkey: &[u8]) -> Result<CrlLayout> {
+    let layout = CrlLayout::parse(der)?;
+    verify_der_signature(
+        der,
+        &layout.tbs,
+        [&layout.signature_r, &layout.signature_s],
+        issuer_pubkey,
+    )?;
+    Ok(layout)
+}
+
+/// Verifies the issuer's signature of the `tbs` part of a certificate or CRL, left-padding `r`
+/// and `s` to 32 bytes
+fn verify_der_signature(
+    der: &[u8],
+    tbs: &Range<usize>,
+    signature_integers: [&Range<usize>; 2],
+    issuer_pubkey: &[u8],
+) -> Result<()> {
+    let mut signature = vec![];
+    for integer in signature_integers {
+        signature.extend(vec![0; 32 - integer.len()]);
+        signature.extend_from_slice(&der[integer.clone()]);
+    }
+    verify_signature(issuer_pubkey, &der[tbs.clone()], &signature)
+}
+
+/// Strips the whitespace outside of JSON strings
+fn strip_whitespace(json: &[u8]) -> Vec<u8> {
+    let mut stripped = vec![];
+    let (mut in_string, mut escaped) = (false, false);
+    for &c in json {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == b'\\' {
+                escaped = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+        } else if c == b'"' {
+            in_string = true;
+        } else if c.is_ascii_whitespace() {
+            continue;
+        }
+        stripped.push(c);
+    }
+    stripped
+}
+
+/// Range of the object following `"key":` at the top level of a whitespace-free JSON document
+fn object_range(json: &[u8], key: &str) -> Result<Range<usize>> {
+    let needle = format!("\"{}\":{{", key);
+    let (mut depth, mut in_string, mut escaped) = (0, false, false);
+    let mut start = None;
+    for (i, &c) in json.iter().enumerate() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == b'\\' {
+                escaped = true;
+            } else if c == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+        match c {
+            b'"' => {
+                if depth == 1 && start.is_none() && json[i..].starts_with(needle.as_bytes()) {
+                    start = Some(i + needle.len() - 1);
+                }
+                in_string = true;
+            }
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if let Some(start) = start.filter(|_| depth == 1) {
+                    return Ok(start..i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(anyhow!("JSON document has no {} object", key))
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    ensure!(hex.len() % 2 == 0, "odd length hex string");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| Ok(u8::from_str_radix(&hex[i..i + 2], 16)?))
+        .collect()
+}
+
+fn hex_array<const N: usize>(hex: &str, name: &str) -> Result<[u8; N]> {
+    decode_hex(hex)?
+        .try_into()
+        .map_err(|_| anyhow!("invalid {}", name))
+}
+
+/// The whitespace-free body of the `key` object, i.e. the message signed by Intel
+fn signed_body(json: &[u8], key: &str) -> Result<Vec<u8>> {
+    let stripped = strip_whitespace(json);
+    Ok(stripped[object_range(&stripped, key)?].to_vec())
+}
+
+/// A value in a whitespace-free JSON body, located after its key
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JsonField {
+    /// The bytes in front of the value, i.e. its quoted key, the colon and the opening quote of
+    /// string values
+    pub key: Vec<u8>,
+    /// The digits of a number, the contents of a string or an array, brackets included
+    pub value: Range<usize>,
+}
+
+impl JsonField {
+    /// Finds the first value after `key` from `cursor` on, moving the cursor past it
+    fn find(body: &[u8], cursor: &mut usize, key: &str) -> Result<Self> {
+        let key = key.as_bytes().to_vec();
+        let start = body[*cursor..]
+            .windows(key.len())
+            .position(|window| window == key)
+            .map(|position| *cursor + position + key.len())
+            .ok_or_else(|| anyhow!("{} not found", String::from_utf8_lossy(&key)))?;
+        let is_string = key.ends_with(b"\"");
+        let len = body[start..]
+            .iter()
+            .position(|c| {
+                if is_string {
+                    *c == b'"'
+                } else {
+                    !c.is_ascii_digit()
+                }
+            })
+            .ok_or_else(|| anyhow!("unterminated JSON value"))?;
+        ensure!(len > 0, "empty JSON value");
+        *cursor = start + len;
+        Ok(Self {
+            key,
+            value: start..start + len,
+        })
+    }
+
+    /// Finds the first value after `key` in the body
+    fn find_first(body: &[u8], key: &str) -> Result<Self> {
+        Self::find(body, &mut 0, key)
+    }
+
+    /// Finds the first array after `key` in the body, brackets included
+    fn find_array(body: &[u8], key: &str) -> Result<Self> {
+        let key = key.as_bytes().to_vec();
+        let start = body
+            .windows(key.len())
+            .position(|window| window == key)
+            .map(|position| position + key.len())
+            .filter(|start| body.get(*start) == Some(&b'['))
+            .ok_or_else(|| anyhow!("{} array not found", String::from_utf8_lossy(&key)))?;
+        let (mut depth, mut in_string) = (0, false);
+        for (i, &c) in body.iter().enumerate().skip(start) {
+            if in_string {
+                in_string = c != b'"';
+                continue;
+            }
+            match c {
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(Self {
+                            key,
+                            value: start..i + 1,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(anyhow!("unterminated JSON array"))
+    }
+
+    /// Position of the key in the body
+    pub fn offset(&self) -> usize {
+        self.value.start.saturating_sub(self.key.len())
+    }
+
+    fn number(&self, body: &[u8]) -> Result<u64> {
+        Ok(std::str::from_utf8(&body[self.value.clone()])?.parse()?)
+    }
+
+    fn hex(&self, body: &[u8]) -> Result<Vec<u8>> {
+        decode_hex(std::str::from_utf8(&body[self.value.clone()])?)
+    }
+}
+
+/// Location of the values verified in-circuit in the signed `tcbInfo` body
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcbInfoLayout {
+    pub fmspc: JsonField,
+    pub pce_id: JsonField,
+    /// The `tcbLevels` array, walked in-circuit
+    pub tcb_levels: JsonField,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfoDocument {
+    tcb_info: TcbInfoJson,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfoJson {
+    // "SGX" or "TDX" from v3 on
+    id: Option<String>,
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    fmspc: String,
+    pce_id: String,
+    tcb_levels: Vec<TcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbLevelJson {
+    tcb: TcbJson,
+    tcb_status: String,
+}
+
+#[derive(Deserialize)]
+struct TcbComponentJson {
+    svn: u8,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TcbJson {
+    // v3: `{"sgxtcbcomponents":[{"svn":7,...},...],"pcesvn":13}`
+    V3 {
+        sgxtcbcomponents: Vec<TcbComponentJson>,
+        pcesvn: u16,
+    },
+    // v2: `{"sgxtcbcomp01svn":7,...,"sgxtcbcomp16svn":0,"pcesvn":13}`
+    V2(BTreeMap<String, u16>),
+}
+
+impl TcbJson {
+    fn to_level(&self, status: TcbStatus) -> Result<TcbLevel> {
+        let mut sgx_tcb_components = [0; SGX_TCB_COMPONENTS];
+        let pce_svn = match self {
+            Self::V3 {
+                sgxtcbcomponents,
+                pcesvn,
+            } => {
+                ensure!(
+                    sgxtcbcomponents.len() == SGX_TCB_COMPONENTS,
+                    "expected {} SGX TCB components",
+                    SGX_TCB_COMPONENTS
+                );
+                for (component, json) in sgx_tcb_components.iter_mut().zip(sgxtcbcomponents) {
+                    *component = json.svn;
+                }
+                *pcesvn
+            }
+            Self::V2(tcb) => {
+                for (i, component) in sgx_tcb_components.iter_mut().enumerate() {
+                    let key = format!("sgxtcbcomp{:02}svn", i + 1);
+                    let svn = *tcb.get(&key).ok_or_else(|| anyhow!("{} not found", key))?;
+                    *component = u8::try_from(svn)?;
+                }
+                *tcb.get("pcesvn")
+                    .ok_or_else(|| anyhow!("pcesvn not found"))?
+            }
+        };
+        Ok(TcbLevel {
+            sgx_tcb_components,
+            pce_svn,
+            status,
+        })
+    }
+}
+
+/// Intel's TCBInfo of a platform, v2 or v3
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcbInfo {
+    pub version: u32,
+    /// "SGX" or "TDX", v2 TCBInfo is always SGX
+    pub id: String,
+    pub issue_date: String,
+    pub next_update: String,
+    pub fmspc: [u8; 6],
+    pub pce_id: [u8; 2],
+    pub tcb_levels: TcbLevels,
+    /// The whitespace-free `tcbInfo` body, i.e. the message signed by the TCB Signing certificate
+    pub body: Vec<u8>,
+    /// Big-endian `r || s` signature of the body
+    pub signature: [u8; 64],
+}
+
+impl TcbInfo {
+    /// Parses a TCBInfo JSON document as served by the PCS, i.e.
+    /// `{"tcbInfo":{...},"signature":"..."}`
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let document: TcbInfoDocument = serde_json::from_slice(json)?;
+        let tcb_info = document.tcb_info;
+        ensure!(
+            tcb_info.version == 2 || tcb_info.version == 3,
+            "unsupported TCBInfo version {}",
+            tcb_info.version
+        );
+
+        let levels = tcb_info
+            .tcb_levels
+            .iter()
+            .map(|level| level.tcb.to_level(level.tcb_status.parse()?))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version: tcb_info.version,
+            id: tcb_info.id.unwrap_or_else(|| "SGX".to_string()),
+            issue_date: tcb_info.issue_date,
+            next_update: tcb_info.next_update,
+            fmspc: hex_array(&tcb_info.fmspc, "FMSPC")?,
+            pce_id: hex_array(&tcb_info.pce_id, "PCEID")?,
+            tcb_levels: TcbLevels::new(levels)?,
+            body: signed_body(json, TCB_INFO_KEY)?,
+            signature: hex_array(&document.signature, "TCBInfo signature")?,
+        })
+    }
+
+    /// Verifies the signature of the body with the big-endian `x || y` public key of the TCB
+    /// Signing certificate
+    pub fn verify_signature(&self, tcb_signing_pubkey: &[u8]) -> Result<()> {
+        verify_signature(tcb_signing_pubkey, &self.body, &self.signature)
+    }
+
+    /// Verifies the DER encoded TCB Signing certificate with Intel's root CA, then the signature
+    /// of the body with the TCB Signing certificate
+    pub fn verify(&self, tcb_signing_cert: &[u8]) -> Result<()> {
+        let tcb_signing_pubkey = verify_cert(tcb_signing_cert, &INTEL_SGX_ROOT_CA_PUBKEY)?;
+        self.verify_signature(&tcb_signing_pubkey)
+    }
+
+    /// Locates FMSPC, PCEID and the TCB levels array in the body. The located values and the
+    /// levels walked like in-circuit are checked against the parsed ones.
+    pub fn layout(&self) -> Result<TcbInfoLayout> {
+        let body = &self.body;
+        let fmspc = JsonField::find_first(body, FMSPC_KEY)?;
+        let pce_id = JsonField::find_first(body, PCE_ID_KEY)?;
+        ensure!(
+            fmspc.hex(body)? == self.fmspc && pce_id.hex(body)? == self.pce_id,
+            "located value does not match the parsed one"
+        );
+        let tcb_levels = JsonField::find_array(body, TCB_LEVELS_KEY)?;
+        for field in [&fmspc, &pce_id, &tcb_levels] {
+            ensure!(
+                JSON_FIELD_SEARCH.contains(&field.offset()),
+                "{} is too far into the body",
+                String::from_utf8_lossy(&field.key)
+            );
+        }
+
+        let walked = walk_tcb_levels(
+            body,
+            tcb_levels.value.start,
+            SGX_TCB_COMPONENTS + 1,
+            PCE_SVN_KEY,
+        )?;
+        let levels = self.tcb_levels.levels();
+        ensure!(
+            walked.len() == levels.len()
+                && walked.iter().zip(levels).all(|((numbers, status), level)| {
+                    numbers[..SGX_TCB_COMPONENTS]
+                        .iter()
+                        .zip(level.sgx_tcb_components)
+                        .all(|(number, component)| *number == component as u64)
+                        && numbers[SGX_TCB_COMPONENTS] == level.pce_svn as u64
+                        && *status == level.status
+                }),
+            "walked TCB levels do not match the parsed ones"
+        );
+        Ok(TcbInfoLayout {
+            fmspc,
+            pce_id,
+            tcb_levels,
+        })
+    }
+}
+
+/// Walks the TCB levels array opening at `start` of a whitespace-free body the way
+/// `SgxDcapVerifierConfig::json_tcb_levels` does in-circuit, returning the numbers and the status
+/// of each level. A level is an object of at least `TCB_LEVEL_MIN_LEN` bytes holding `numbers`
+/// numbers, each after a key ending with `SVN_KEY_SUFFIX` and the last one after `last_key`, and
+/// a single `tcbStatus`.
+fn walk_tcb_levels(
+    body: &[u8],
+    start: usize,
+    numbers: usize,
+    last_key: &str,
+) -> Result<Vec<(Vec<u64>, TcbStatus)>> {
+    let mut levels = vec![];
+    let (mut depth, mut in_string, mut level_start) = (0, false, start);
+    let (mut values, mut status) = (vec![], None);
+    let mut i = start;
+    while let Some(&c) = body.get(i) {
+        // the circuit does not unescape strings
+        ensure!(c != b'\\', "escaped JSON strings are not supported");
+        if in_string {
+            in_string = c != b'"';
+        } else if c == b'"' {
+            in_string = true;
+            if body[..i].ends_with(&TCB_STATUS_KEY.as_bytes()[..TCB_STATUS_KEY.len() - 1]) {
+                ensure!(status.is_none(), "TCB level with several statuses");
+                let len = body[i + 1..]
+                    .iter()
+                    .position(|c| *c == b'"')
+                    .ok_or_else(|| anyhow!("unterminated JSON string"))?;
+                status = Some(std::str::from_utf8(&body[i + 1..i + 1 + len])?.parse()?);
+            }
+        } else if c.is_ascii_digit() {
+            let len = body[i..]
+                .iter()
+                .position(|c| !c.is_ascii_digit())
+                .ok_or_else(|| anyhow!("unterminated JSON number"))?;
+            let is_last = values.len() + 1 == numbers;
+            ensure!(
+                body[..i].ends_with(SVN_KEY_SUFFIX.as_bytes())
+                    && body[..i].ends_with(last_key.as_bytes()) == is_last,
+                "unexpected number in TCB level"
+            );
+            values.push(std::str::from_utf8(&body[i..i + len])?.parse()?);
+            i += len;
+            continue;
+        } else if c == b'{' || c == b'[' {
+            if depth == 1 {
+                level_start = i;
+            }
+            depth += 1;
+        } else if c == b'}' || c == b']' {
+            depth -= 1;
+            if depth == 0 {
+                return Ok(levels);
+            }
+            if depth == 1 {
+                ensure!(
+                    values.len() == numbers && i - level_start >= TCB_LEVEL_MIN_LEN,
+                    "invalid TCB level"
+                );
+                let status = status
+                    .take()
+                    .ok_or_else(|| anyhow!("TCB level without status"))?;
+                levels.push((std::mem::take(&mut values), status));
+            }
+        }
+        i += 1;
+    }
+    Err(anyhow!("unterminated TCB levels"))
+}
+
+/// Location of the values verified in-circuit in the signed `enclaveIdentity` body
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnclaveIdentityLayout {
+    pub id: JsonField,
+    pub misc_select: JsonField,
+    pub misc_select_mask: JsonField,
+    pub attributes: JsonField,
+    pub attributes_mask: JsonField,
+    pub mr_signer: JsonField,
+    pub isv_prod_id: JsonField,
+    /// ISVSVN and status of each TCB level
+    pub tcb_levels: Vec<(JsonField, JsonField)>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnclaveIdentityDocument {
+    enclave_identity: EnclaveIdentityJson,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EnclaveIdentityJson {
+    id: String,
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    miscselect: String,
+    miscselect_mask: String,
+    attributes: String,
+    attributes_mask: String,
+    mrsigner: String,
+    isvprodid: u16,
+    tcb_levels: Vec<QeTcbLevelJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QeTcbLevelJson {
+    tcb: QeTcbJson,
+    tcb_status: String,
+}
+
+#[derive(Deserialize)]
+struct QeTcbJson {
+    isvsvn: u16,
+}
+
+/// Intel's QE Identity, v2
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EnclaveIdentity {
+    pub version: u32,
+    /// "QE" for the QE Identity
+    pub id: String,
+    pub issue_date: String,
+    pub next_update: String,
+    pub identity: QeIdentity,
+    /// The whitespace-free `enclaveIdentity` body, i.e. the message signed by the TCB Signing
+    /// certificate
+    pub body: Vec<u8>,
+    /// Big-endian `r || s` signature of the body
+    pub signature: [u8; 64],
+}
+
+impl EnclaveIdentity {
+    /// Parses an enclave identity JSON document as served by the PCS, i.e.
+    /// `{"enclaveIdentity":{...},"signature":"..."}`
+    pub fn parse(json: &[u8]) -> Result<Self> {
+        let document: EnclaveIdentityDocument = serde_json::from_slice(json)?;
+        let identity = document.enclave_identity;
+        ensure!(
+            identity.version == 2,
+            "unsupported enclave identity version {}",
+            identity.version
+        );
+
+        let levels = identity
+            .tcb_levels
+            .iter()
+            .map(|level| {
+                Ok(QeTcbLevel {
+                    isv_svn: level.tcb.isvsvn,
+                    status: level.tcb_status.parse()?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            version: identity.version,
+            id: identity.id,
+            issue_date: identity.issue_date,
+            next_update: identity.next_update,
+            identity: QeIdentity::new(
+                hex_array(&identity.miscselect, "MISCSELECT")?,
+                hex_array(&identity.miscselect_mask, "MISCSELECT mask")?,
+                hex_array(&identity.attributes, "attributes")?,
+                hex_array(&identity.attributes_mask, "attributes mask")?,
+                hex_array(&identity.mrsigner, "MRSIGNER")?,
+                identity.isvprodid,
+                levels,
+            )?,
+            body: signed_body(json, ENCLAVE_IDENTITY_KEY)?,
+            signature: hex_array(&document.signature, "enclave identity signature")?,
+        })
+    }
+
+    /// Verifies the signature of the body with the big-endian `x || y` public key of the TCB
+    /// Signing certificate
+    pub fn verify_signature(&self, tcb_signing_pubkey: &[u8]) -> Result<()> {
+        verify_signature(tcb_signing_pubkey, &self.body, &self.signature)
+    }
+
+    /// Verifies the DER encoded TCB Signing certificate with Intel's root CA, then the signature
+    /// of the body with the TCB Signing certificate
+    pub fn verify(&self, tcb_signing_cert: &[u8]) -> Result<()> {
+        let tcb_signing_pubkey = verify_cert(tcb_signing_cert, &INTEL_SGX_ROOT_CA_PUBKEY)?;
+        self.verify_signature(&tcb_signing_pubkey)
+    }
+
+    /// Locates the identity and its TCB levels array in the body. The located values and the
+    /// levels walked like in-circuit are checked against the parsed ones.
+    pub fn layout(&self) -> Result<EnclaveIdentityLayout> {
+        let body = &self.body;
+        let identity = &self.identity;
+        let id = JsonField::find_first(body, ID_KEY)?;
+        let misc_select = JsonField::find_first(body, MISC_SELECT_KEY)?;
+        let misc_select_mask = JsonField::find_first(body, MISC_SELECT_MASK_KEY)?;
+        let attributes = JsonField::find_first(body, ATTRIBUTES_KEY)?;
+        let attributes_mask = JsonField::find_first(body, ATTRIBUTES_MASK_KEY)?;
+        let mr_signer = JsonField::find_first(body, MR_SIGNER_KEY)?;
+        let isv_prod_id = JsonField::find_first(body, ISV_PROD_ID_KEY)?;
+        ensure!(
+            body[id.value.clone()] == *self.id.as_bytes()
+                && misc_select.hex(body)? == identity.misc_select
+                && misc_select_mask.hex(body)? == identity.misc_select_mask
+                && attributes.hex(body)? == identity.attributes
+                && attributes_mask.hex(body)? == identity.attributes_mask
+                && mr_signer.hex(body)? == identity.mr_signer
+                && isv_prod_id.number(body)? == identity.isv_prod_id as u64,
+            "located value does not match the parsed one"
+        );
+        let tcb_levels = JsonField::find_array(body, TCB_LEVELS_KEY)?;
+        for field in [
+            &id,
+            &misc_select,
+            &misc_select_mask,
+            &attributes,
+            &attributes_mask,
+            &mr_signer,
+            &isv_prod_id,
+            &tcb_levels,
+        ] {
+            ensure!(
+                JSON_FIELD_SEARCH.contains(&field.offset()),
+                "{} is too far into the body",
+                String::from_utf8_lossy(&field.key)
+            );
+        }
+
+        let walked = walk_tcb_levels(body, tcb_levels.value.start, 1, ISV_SVN_KEY)?;
+        let levels = identity.tcb_levels();
+        ensure!(
+            walked.len() == levels.len()
+                && walked.iter().zip(levels).all(|((numbers, status), level)| {
+                    numbers[0] == level.isv_svn as u64 && *status == level.status
+                }),
+            "walked TCB levels do not match the parsed ones"
+        );
+        Ok(EnclaveIdentityLayout {
+            id,
+            misc_select,
+            misc_select_mask,
+            attributes,
+            attributes_mask,
+            mr_signer,
+            isv_prod_id,
+            tcb_levels,
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::quote::tests::to_pem;
+    use crate::sgx_dcap_verifier::{CircuitParams, CircuitParamsSpec, DefaultCircuitParams};
+    use crate::tcb::MAX_TCB_LEVELS;
+    use base64::{engine::general_purpose, Engine};
+    use rand_chacha::rand_core::OsRng;
+    use std::marker::PhantomData;
+
+    // secret keys of the leaf, intermediate and root certificates of `test_pck_cert_chain`,
+    // fixed so that `TestRootCa` holds the root public key in its type
+    pub(crate) const TEST_LEAF_KEY: u64 = 0x1eaf;
+    pub(crate) const TEST_INTERMEDIATE_KEY: u64 = 0x1c4;
+    const TEST_ROOT_CA_KEY: u64 = 0x4007;
+
+    fn to_be(mut le: [u8; 32]) -> [u8; 32] {
+        le.reverse();
+        le
+    }
+
+    /// Big-endian `x || y` public key of the secp256r1 secret key `sk`
+    pub(crate) fn pubkey(sk: Fq) -> [u8; 64] {
+        let pk = (Secp256r1Affine::generator() * sk).to_affine();
+        [to_be(pk.x.to_bytes()), to_be(pk.y.to_bytes())]
+            .concat()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Signs `message` with the secp256r1 secret key `sk`, returning the big-endian `r || s`
+    /// signature
+    pub(crate) fn sign_with(sk: Fq, message: &[u8]) -> [u8; 64] {
+        let msghash = Fq::from_bytes(&to_be(Sha256::digest(message).into())).unwrap();
+
+        let k = Fq::random(OsRng);
+        let k_point = (Secp256r1Affine::generator() * k).to_affine();
+        let r = biguint_to_fe::<Fq>(&(fe_to_biguint(&k_point.x) % modulus::<Fq>()));
+        let s = k.invert().unwrap() * (msghash + r * sk);
+
+        [to_be(r.to_bytes()), to_be(s.to_bytes())]
+            .concat()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Signs `message` with a fresh secp256r1 key, returning the big-endian `x || y` public key
+    /// and `r || s` signature
+    pub(crate) fn sign(message: &[u8]) -> ([u8; 64], [u8; 64]) {
+        let sk = Fq::random(OsRng);
+        (pubkey(sk), sign_with(sk, message))
+    }
+
+    /// DER SEQUENCE with a two-byte length
+    pub(crate) fn der_sequence(contents: &[u8]) -> Vec<u8> {
+        let len = (contents.len() as u16).to_be_bytes();
+        [&[0x30, 0x82, len[0], len[1]][..], contents].concat()
+    }
+
+    /// Re-signs a DER certificate with `issuer_key`, its subject public key replaced by the one
+    /// of `subject_key`. The other fields and the signature algorithm are kept.
+    pub(crate) fn resign_cert(der: &[u8], subject_key: Fq, issuer_key: Fq) -> Vec<u8> {
+        let layout = CertLayout::parse(der).unwrap();
+        let mut tbs = der[layout.tbs.clone()].to_vec();
+        let pubkey_start = layout.pubkey.start - layout.tbs.start;
+        tbs[pubkey_start..pubkey_start + 64].copy_from_slice(&pubkey(subject_key));
+
+        // ECDSA-Sig-Value, a SEQUENCE of the minimal positive INTEGERs r and s in a BIT STRING
+        let signature = sign_with(issuer_key, &tbs);
+        let integer = |be: &[u8]| {
+            let start = be
+                .iter()
+                .position(|byte| *byte != 0)
+                .unwrap_or(be.len() - 1);
+            let sign: &[u8] = if be[start] >= 0x80 { &[0] } else { &[] };
+            let len = (sign.len() + be.len() - start) as u8;
+            [&[0x02, len][..], sign, &be[start..]].concat()
+        };
+        let value = [integer(&signature[..32]), integer(&signature[32..])].concat();
+        let value = [&[0x30, value.len() as u8][..], &value].concat();
+        let bit_string = [&[0x03, value.len() as u8 + 1, 0][..], &value].concat();
+
+        let algorithm_len = 2 + der[layout.tbs.end + 1] as usize;
+        let algorithm = &der[layout.tbs.end..layout.tbs.end + algorithm_len];
+        der_sequence(&[&tbs, algorithm, &bit_string].concat())
+    }
+
+    /// PEM leaf, intermediate and root certificates of `assets/pck_cert_chain.pem` re-signed
+    /// with `TEST_LEAF_KEY`, `TEST_INTERMEDIATE_KEY` and the root key of `TestRootCa`
+    pub(crate) fn test_pck_cert_chain() -> Vec<Vec<u8>> {
+        let chain =
+            crate::quote::PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem"))
+                .unwrap();
+        let [leaf_key, intermediate_key, root_key] =
+            [TEST_LEAF_KEY, TEST_INTERMEDIATE_KEY, TEST_ROOT_CA_KEY].map(Fq::from);
+        [
+            (&chain.leaf, leaf_key, intermediate_key),
+            (&chain.intermediate, intermediate_key, root_key),
+            (&chain.root, root_key, root_key),
+        ]
+        .into_iter()
+        .map(|(cert, subject_key, issuer_key)| {
+            let (der, _) = CertLayout::from_base64(cert).unwrap();
+            let der = resign_cert(&der, subject_key, issuer_key);
+            to_pem(general_purpose::STANDARD.encode(der).as_bytes())
+        })
+        .collect()
+    }
+
+    /// The parameters of `P` with the root CA of `test_pck_cert_chain` in place of Intel's
+    #[derive(Debug, Default, Clone)]
+    pub(crate) struct TestRootCa<P>(PhantomData<P>);
+
+    impl<P: CircuitParamsSpec> CircuitParamsSpec for TestRootCa<P> {
+        fn params() -> CircuitParams {
+            P::params()
+        }
+
+        fn root_ca_pubkey() -> [u8; 64] {
+            pubkey(Fq::from(TEST_ROOT_CA_KEY))
+        }
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    /// A whitespace-free `tcbInfo` body with two TCB levels
+    pub(crate) fn tcb_info_body(version: u32) -> String {
+        let tcb = |component: u8, pce_svn: u16| match version {
+            2 => {
+                let components: Vec<String> = (1..=SGX_TCB_COMPONENTS)
+                    .map(|i| format!("\"sgxtcbcomp{:02}svn\":{}", i, component))
+                    .collect();
+                format!("{{{},\"pcesvn\":{}}}", components.join(","), pce_svn)
+            }
+            _ => {
+                let components: Vec<String> = (0..SGX_TCB_COMPONENTS)
+                    .map(|_| format!("{{\"svn\":{},\"category\":\"BIOS\"}}", component))
+                    .collect();
+                format!(
+                    "{{\"sgxtcbcomponents\":[{}],\"pcesvn\":{}}}",
+                    components.join(","),
+                    pce_svn
+                )
+            }
+        };
+        format!(
+            "{{{}\"version\":{},\"issueDate\":\"2024-01-01T00:00:00Z\",\
+             \"nextUpdate\":\"2024-02-01T00:00:00Z\",\"fmspc\":\"00606A000000\",\
+             \"pceId\":\"0000\",\"tcbType\":0,\"tcbEvaluationDataNumber\":16,\"tcbLevels\":[\
+             {{\"tcb\":{},\"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"UpToDate\"}},\
+             {{\"tcb\":{},\"tcbDate\":\"2018-01-04T00:00:00Z\",\"tcbStatus\":\"OutOfDate\"}}]}}",
+            if version == 3 { "\"id\":\"SGX\"," } else { "" },
+            version,
+            tcb(14, 13),
+            tcb(2, 5),
+        )
+    }
+
+    /// A `tcbInfo` body of `num_levels` TCB levels shaped like the PCS's v3 ones, with the
+    /// category and type of the components and growing lists of advisory IDs
+    pub(crate) fn pcs_tcb_info_body(num_levels: usize) -> String {
+        let categories = [
+            ",\"category\":\"BIOS\",\"type\":\"Early Microcode Update\"",
+            ",\"category\":\"OS/VMM\",\"type\":\"SGX Late Microcode Update\"",
+            ",\"category\":\"OS/VMM\",\"type\":\"TXT SINIT\"",
+            ",\"category\":\"BIOS\"",
+            ",\"category\":\"BIOS\"",
+            ",\"category\":\"BIOS\"",
+            "",
+            ",\"category\":\"OS/VMM\",\"type\":\"SEAMLDR ACM\"",
+        ];
+        let statuses = [
+            TcbStatus::OutOfDate,
+            TcbStatus::SWHardeningNeeded,
+            TcbStatus::OutOfDateConfigurationNeeded,
+        ];
+        let levels: Vec<String> = (0..num_levels)
+            .map(|i| {
+                let svn = 2 * (num_levels - i);
+                let components: Vec<String> = (0..SGX_TCB_COMPONENTS)
+                    .map(|j| format!("{{\"svn\":{}{}}}", svn, categories.get(j).unwrap_or(&"")))
+                    .collect();
+                let (status, advisories) = match i {
+                    0 => (TcbStatus::UpToDate, String::new()),
+                    _ => {
+                        let ids: Vec<String> = (0..(2 * i).min(24))
+                            .map(|id| format!("\"INTEL-SA-{:05}\"", 100 + id))
+                            .collect();
+                        (
+                            statuses[i % statuses.len()],
+                            format!(",\"advisoryIDs\":[{}]", ids.join(",")),
+                        )
+                    }
+                };
+                format!(
+                    "{{\"tcb\":{{\"sgxtcbcomponents\":[{}],\"pcesvn\":{}}},\
+                     \"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"{}\"{}}}",
+                    components.join(","),
+                    svn,
+                    status.name(),
+                    advisories
+                )
+            })
+            .collect();
+        format!(
+            "{{\"id\":\"SGX\",\"version\":3,\"issueDate\":\"2024-01-01T00:00:00Z\",\
+             \"nextUpdate\":\"2024-02-01T00:00:00Z\",\"fmspc\":\"00606A000000\",\
+             \"pceId\":\"0000\",\"tcbType\":0,\"tcbEvaluationDataNumber\":16,\
+             \"tcbLevels\":[{}]}}",
+            levels.join(",")
+        )
+    }
+
+    /// A TCBInfo document around `body`, signed with the secret key `sk`
+    pub(crate) fn signed_tcb_info_document(body: &str, sk: Fq) -> String {
+        format!(
+            "{{\"tcbInfo\":{},\"signature\":\"{}\"}}",
+            body,
+            to_hex(&sign_with(sk, body.as_bytes()))
+        )
+    }
+
+    /// A TCBInfo document around `body`, signed with a fresh key
+    pub(crate) fn tcb_info_document(body: &str) -> (String, [u8; 64]) {
+        let sk = Fq::random(OsRng);
+        (signed_tcb_info_document(body, sk), pubkey(sk))
+    }
+
+    #[test]
+    fn test_parse_tcb_info() {
+        for version in [2, 3] {
+            let body = tcb_info_body(version);
+            let (json, pubkey) = tcb_info_document(&body);
+
+            // reformatting does not change the signed body
+            let pretty: serde_json::Value = serde_json::from_str(&json).unwrap();
+            let pretty = serde_json::to_string_pretty(&pretty).unwrap();
+            for json in [json, pretty] {
+                let tcb_info = TcbInfo::parse(json.as_bytes()).unwrap();
+                assert_eq!(tcb_info.version, version);
+                assert_eq!(tcb_info.body, body.as_bytes());
+                assert_eq!(tcb_info.fmspc, [0x00, 0x60, 0x6a, 0x00, 0x00, 0x00]);
+                let levels = tcb_info.tcb_levels.levels();
+                assert_eq!(levels.len(), 2);
+                assert_eq!(levels[0].sgx_tcb_components, [14; SGX_TCB_COMPONENTS]);
+                assert_eq!(levels[1].pce_svn, 5);
+                assert_eq!(levels[1].status, TcbStatus::OutOfDate);
+                tcb_info.verify_signature(&pubkey).unwrap();
+
+                let layout = tcb_info.layout().unwrap();
+                assert_eq!(&tcb_info.body[layout.fmspc.value.clone()], b"00606A000000");
+                let tcb_levels = &tcb_info.body[layout.tcb_levels.value.clone()];
+                assert!(tcb_levels.starts_with(b"[{") && tcb_levels.ends_with(b"}]"));
+            }
+        }
+
+        // tampering with the body invalidates the signature
+        let body = tcb_info_body(2);
+        let (json, pubkey) = tcb_info_document(&body);
+        let tampered = json.replace("OutOfDate", "UpToDate");
+        let tcb_info = TcbInfo::parse(tampered.as_bytes()).unwrap();
+        assert!(tcb_info.verify_signature(&pubkey).is_err());
+
+        // the TCB Signing certificate must be issued by Intel's root CA
+        let chain =
+            crate::quote::PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem"))
+                .unwrap();
+        let (leaf, _) = CertLayout::from_base64(&chain.leaf).unwrap();
+        assert!(tcb_info.verify(&leaf).is_err());
+    }
+
+    /// A whitespace-free `enclaveIdentity` body of the QE with two TCB levels
+    pub(crate) fn qe_identity_body() -> String {
+        "{\"id\":\"QE\",\"version\":2,\"issueDate\":\"2024-01-01T00:00:00Z\",\
+         \"nextUpdate\":\"2024-02-01T00:00:00Z\",\"tcbEvaluationDataNumber\":16,\
+         \"miscselect\":\"00000000\",\"miscselectMask\":\"FFFFFFFF\",\
+         \"attributes\":\"11000000000000000000000000000000\",\
+         \"attributesMask\":\"FBFFFFFFFFFFFFFF0000000000000000\",\
+         \"mrsigner\":\"8C4F5775D796503E96137F77C68A829A0056AC8DED70140B081B094490C57BFF\",\
+         \"isvprodid\":1,\"tcbLevels\":[\
+         {\"tcb\":{\"isvsvn\":8},\"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"UpToDate\"},\
+         {\"tcb\":{\"isvsvn\":6},\"tcbDate\":\"2021-11-10T00:00:00Z\",\"tcbStatus\":\"OutOfDate\"}]}"
+            .to_string()
+    }
+
+    /// A QE Identity document around `body`, signed with the secret key `sk`
+    pub(crate) fn signed_qe_identity_document(body: &str, sk: Fq) -> String {
+        format!(
+            "{{\"enclaveIdentity\":{},\"signature\":\"{}\"}}",
+            body,
+            to_hex(&sign_with(sk, body.as_bytes()))
+        )
+    }
+
+    /// A QE Identity document around `body`, signed with a fresh key
+    pub(crate) fn qe_identity_document(body: &str) -> (String, [u8; 64]) {
+        let sk = Fq::random(OsRng);
+        (signed_qe_identity_document(body, sk), pubkey(sk))
+    }
+
+    #[test]
+    fn test_parse_enclave_identity() {
+        let body = qe_identity_body();
+        let (json, pubkey) = qe_identity_document(&body);
+        let pretty: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let pretty = serde_json::to_string_pretty(&pretty).unwrap();
+        for json in [json, pretty] {
+            let qe_identity = EnclaveIdentity::parse(json.as_bytes()).unwrap();
+            assert_eq!(qe_identity.id, QE_IDENTITY_ID);
+            assert_eq!(qe_identity.body, body.as_bytes());
+            let identity = &qe_identity.identity;
+            assert_eq!(identity.misc_select_mask, [0xff; 4]);
+            assert_eq!(identity.attributes[0], 0x11);
+            assert_eq!(identity.mr_signer[..2], [0x8c, 0x4f]);
+            assert_eq!(identity.isv_prod_id, 1);
+            assert_eq!(identity.tcb_levels().len(), 2);
+            assert_eq!(identity.evaluate(7), TcbStatus::OutOfDate);
+            qe_identity.verify_signature(&pubkey).unwrap();
+
+            let layout = qe_identity.layout().unwrap();
+            assert_eq!(&qe_identity.body[layout.id.value.clone()], b"QE");
+            assert_eq!(&qe_identity.body[layout.isv_prod_id.value.clone()], b"1");
+            let tcb_levels = &qe_identity.body[layout.tcb_levels.value.clone()];
+            assert!(tcb_levels.starts_with(b"[{") && tcb_levels.ends_with(b"}]"));
+        }
+
+        let (json, pubkey) = qe_identity_document(&body);
+        let tampered = json.replace("\"isvsvn\":8", "\"isvsvn\":2");
+        let qe_identity = EnclaveIdentity::parse(tampered.as_bytes()).unwrap();
+        assert!(qe_identity.verify_signature(&pubkey).is_err());
+    }
+
+    #[test]
+    fn test_parse_pcs_tcb_info() {
+        let body = pcs_tcb_info_body(MAX_TCB_LEVELS);
+        let (json, _) = tcb_info_document(&body);
+        let tcb_info = TcbInfo::parse(json.as_bytes()).unwrap();
+        let levels = tcb_info.tcb_levels.levels();
+        assert_eq!(levels.len(), MAX_TCB_LEVELS);
+        assert_eq!(levels[1].status, TcbStatus::SWHardeningNeeded);
+        assert_eq!(levels[MAX_TCB_LEVELS - 1].pce_svn, 2);
+        tcb_info.layout().unwrap();
+    }
+
+    #[test]
+    fn test_resigned_pck_cert_chain() {
+        let chain = crate::quote::PckCertChain::parse(&test_pck_cert_chain().concat()).unwrap();
+        let certs = [&chain.leaf, &chain.intermediate, &chain.root]
+            .map(|cert| CertLayout::from_base64(cert).unwrap().0);
+        let root_pubkey = TestRootCa::<DefaultCircuitParams>::root_ca_pubkey();
+        assert_eq!(verify_cert(&certs[2], &root_pubkey).unwrap(), root_pubkey);
+        let intermediate_pubkey = verify_cert(&certs[1], &root_pubkey).unwrap();
+        assert_eq!(
+            verify_cert(&certs[0], &intermediate_pubkey).unwrap(),
+            pubkey(Fq::from(TEST_LEAF_KEY))
+        );
+        assert!(verify_cert(&certs[1], &INTEL_SGX_ROOT_CA_PUBKEY).is_err());
+    }
+
+    #[test]
+    fn test_verify_cert() {
+        let chain =
+            crate::quote::PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem"))
+                .unwrap();
+        let (intermediate, _) = CertLayout::from_base64(&chain.intermediate).unwrap();
+        let (leaf, _) = CertLayout::from_base64(&chain.leaf).unwrap();
+
+        let intermediate_pubkey = verify_cert(&intermediate, &INTEL_SGX_ROOT_CA_PUBKEY).unwrap();
+        verify_cert(&leaf, &intermediate_pubkey).unwrap();
+        assert!(verify_cert(&leaf, &INTEL_SGX_ROOT_CA_PUBKEY).is_err());
+    }
+}
@@ -0,0 +1,251 @@
+//! Host-side sorted Merkle tree over the serial numbers revoked by Intel's PCK CRL.
+//!
+//! The leaves are the gaps between consecutive revoked serial numbers, sorted as big-endian
+//! integers: SHA256(low || high) of two `SERIAL_NUMBER_LEN`-byte serial numbers, from the all-zero
+//! serial number to the all-0xff one. A serial number strictly inside a gap is not revoked, which
+//! takes a single Merkle proof. The tree has a fixed depth of `CRL_DEPTH`, padding leaves are the
+//! empty gap from the all-0xff serial number to itself.
+use anyhow::{anyhow, ensure, Result};
+use sha2::{Digest, Sha256};
+
+use crate::collateral::verify_crl;
+use crate::merkle::{MerkleProof, MerkleTree};
+use crate::x509::SERIAL_NUMBER_LEN;
+
+pub const CRL_DEPTH: usize = 10;
+/// Max sha256 input size of a gap, i.e. two serial numbers and the padding
+pub(crate) const CRL_GAP_MAX_BYTE_SIZE: usize = 64;
+const MIN_SERIAL: [u8; SERIAL_NUMBER_LEN] = [0; SERIAL_NUMBER_LEN];
+const MAX_SERIAL: [u8; SERIAL_NUMBER_LEN] = [0xff; SERIAL_NUMBER_LEN];
+
+type Serial = [u8; SERIAL_NUMBER_LEN];
+
+fn hash_gap(low: &Serial, high: &Serial) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(low)
+        .chain_update(high)
+        .finalize()
+        .into()
+}
+
+/// The serial numbers revoked by a CRL
+#[derive(Debug, Clone)]
+pub struct RevokedSerials {
+    // consecutive bounds, sorted
+    gaps: Vec<(Serial, Serial)>,
+    tree: MerkleTree,
+}
+
+impl RevokedSerials {
+    /// Serial numbers are left-padded to `SERIAL_NUMBER_LEN` bytes, see
+    /// `crate::x509::serial_number`
+    pub fn new(serials: &[Serial]) -> Result<Self> {
+        let mut serials = serials.to_vec();
+        serials.sort();
+        serials.dedup();
+        ensure!(
+            serials.len() < 1 << CRL_DEPTH,
+            "CRL holds at most {} serial numbers",
+            (1 << CRL_DEPTH) - 1
+        );
+        ensure!(
+            serials
+                .iter()
+                .all(|serial| *serial != MIN_SERIAL && *serial != MAX_SERIAL),
+            "invalid revoked serial number"
+        );
+
+        let mut bounds = vec![MIN_SERIAL];
+        bounds.extend(serials);
+        bounds.push(MAX_SERIAL);
+        let gaps: Vec<(Serial, Serial)> =
+            bounds.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        let mut leaves: Vec<[u8; 32]> =
+            gaps.iter().map(|(low, high)| hash_gap(low, high)).collect();
+        leaves.resize(1 << CRL_DEPTH, hash_gap(&MAX_SERIAL, &MAX_SERIAL));
+        Ok(Self {
+            gaps,
+            tree: MerkleTree::new(leaves, CRL_DEPTH),
+        })
+    }
+
+    /// Parses a DER encoded CRL, verifying its signature with the big-endian `x || y` public key
+    /// of the issuing PCK CA
+    pub fn from_crl(der: &[u8], issuer_pubkey: &[u8]) -> Result<Self> {
+        let layout = verify_crl(der, issuer_pubkey)?;
+        Self::new(&layout.revoked_serials(der)?)
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.tree.root()
+    }
+
+    pub fn is_revoked(&self, serial: &Serial) -> bool {
+        self.gaps.iter().any(|(low, _)| low == serial)
+    }
+
+    /// Proof that `serial` is not revoked, i.e. the Merkle proof of the gap it falls in
+    pub fn proof(&self, serial: &Serial) -> Result<NonRevocationProof> {
+        let index = self
+            .gaps
+            .iter()
+            .position(|(low, high)| low < serial && serial < high)
+            .ok_or_else(|| anyhow!("serial number is revoked"))?;
+        let (low, high) = self.gaps[index];
+        Ok(NonRevocationProof {
+            low,
+            high,
+            proof: self.tree.proof(index),
+        })
+    }
+}
+
+/// The gap a serial number falls in and its Merkle proof
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NonRevocationProof {
+    pub low: Serial,
+    pub high: Serial,
+    pub proof: MerkleProof,
+}
+
+/// An empty gap with a proof of `CRL_DEPTH` zero siblings, the shape the circuits expect
+impl Default for NonRevocationProof {
+    fn default() -> Self {
+        Self {
+            low: Serial::default(),
+            high: Serial::default(),
+            proof: MerkleProof {
+                index: 0,
+                siblings: vec![[0; 32]; CRL_DEPTH],
+            },
+        }
+    }
+}
+
+impl NonRevocationProof {
+    pub fn leaf(&self) -> [u8; 32] {
+        hash_gap(&self.low, &self.high)
+    }
+
+    /// The `low || high` gap followed by the `left || right` message hashed at each level
+    pub fn messages(&self) -> Vec<Vec<u8>> {
+        let mut messages = vec![[self.low, self.high].concat()];
+        messages.extend(self.proof.messages(&self.leaf()));
+        messages
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        self.proof.root(&self.leaf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::tests::sign;
+
+    // DER element of `tag` around `contents`
+    fn der(tag: u8, contents: &[u8]) -> Vec<u8> {
+        let mut element = vec![tag];
+        if contents.len() < 0x80 {
+            element.push(contents.len() as u8);
+        } else {
+            element.extend([0x82, (contents.len() >> 8) as u8, contents.len() as u8]);
+        }
+        element.extend(contents);
+        element
+    }
+
+    /// A DER CRL revoking the given INTEGER serial numbers, signed with a fresh key
+    fn build_crl(serials: &[&[u8]]) -> (Vec<u8>, [u8; 64]) {
+        let ecdsa_with_sha256 = der(
+            0x30,
+            &der(0x06, &[0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]),
+        );
+        let time = der(0x17, b"240101000000Z");
+        let entries: Vec<u8> = serials
+            .iter()
+            .flat_map(|serial| der(0x30, &[der(0x02, serial), time.clone()].concat()))
+            .collect();
+        let tbs = der(
+            0x30,
+            &[
+                der(0x02, &[1]),
+                ecdsa_with_sha256.clone(),
+                der(0x30, &[]),
+                time.clone(),
+                time,
+                der(0x30, &entries),
+                der(0xa0, &der(0x30, &[])),
+            ]
+            .concat(),
+        );
+
+        let (pubkey, signature) = sign(&tbs);
+        // positive DER integers
+        let integer = |bytes: &[u8]| der(0x02, &[&[0][..], bytes].concat());
+        let signature_value = der(
+            0x30,
+            &[integer(&signature[..32]), integer(&signature[32..])].concat(),
+        );
+        let crl = der(
+            0x30,
+            &[
+                tbs,
+                ecdsa_with_sha256,
+                der(0x03, &[&[0][..], &signature_value].concat()),
+            ]
+            .concat(),
+        );
+        (crl, pubkey)
+    }
+
+    #[test]
+    fn test_non_revocation_proof() {
+        let serial = |last: u8| {
+            let mut serial = [0; SERIAL_NUMBER_LEN];
+            serial[0] = 0x80;
+            serial[SERIAL_NUMBER_LEN - 1] = last;
+            serial
+        };
+        let revoked = RevokedSerials::new(&[serial(5), serial(1), serial(3), serial(3)]).unwrap();
+        assert!(revoked.is_revoked(&serial(3)));
+        assert!(revoked.proof(&serial(3)).is_err());
+
+        for last in [0, 2, 4, 6] {
+            let proof = revoked.proof(&serial(last)).unwrap();
+            assert!(proof.low < serial(last) && serial(last) < proof.high);
+            assert_eq!(proof.proof.siblings.len(), CRL_DEPTH);
+            assert_eq!(proof.root(), revoked.root());
+            assert_eq!(proof.messages().len(), CRL_DEPTH + 1);
+        }
+        // below the lowest and above the highest revoked serial numbers
+        assert_eq!(revoked.proof(&serial(0)).unwrap().low, MIN_SERIAL);
+        assert_eq!(revoked.proof(&serial(6)).unwrap().high, MAX_SERIAL);
+
+        // a forged gap does not lead to the root
+        let mut forged = revoked.proof(&serial(2)).unwrap();
+        forged.high = serial(4);
+        assert_ne!(forged.root(), revoked.root());
+
+        // an empty CRL revokes nothing
+        let empty = RevokedSerials::new(&[]).unwrap();
+        assert!(empty.proof(&serial(3)).is_ok());
+        assert!(RevokedSerials::new(&[MAX_SERIAL]).is_err());
+    }
+
+    #[test]
+    fn test_parse_crl() {
+        let (crl, pubkey) = build_crl(&[&[0x00, 0xd9, 0xea], &[0x12]]);
+        let revoked = RevokedSerials::from_crl(&crl, &pubkey).unwrap();
+        let mut serial = [0; SERIAL_NUMBER_LEN];
+        serial[SERIAL_NUMBER_LEN - 2..].copy_from_slice(&[0xd9, 0xea]);
+        assert!(revoked.is_revoked(&serial));
+        serial[SERIAL_NUMBER_LEN - 2..].copy_from_slice(&[0x00, 0x12]);
+        assert!(revoked.is_revoked(&serial));
+
+        // the CRL must be signed by the given issuer
+        let (_, other_pubkey) = build_crl(&[]);
+        assert!(RevokedSerials::from_crl(&crl, &other_pubkey).is_err());
+    }
+}
@@ -1,3 +1,20 @@
+pub mod allowlist;
+pub mod base64_decode;
+pub mod collateral;
+pub mod crl;
+pub mod merkle;
+pub mod pem;
+pub mod qe_identity_verifier;
+pub mod quote;
 pub mod sgx_dcap_verifier;
 pub mod table;
+pub mod tcb;
+pub mod tcb_info_verifier;
+pub mod tdx_verifier;
+pub mod x509;
+pub use crate::base64_decode::*;
+pub use crate::pem::*;
+pub use crate::qe_identity_verifier::*;
 pub use crate::sgx_dcap_verifier::*;
+pub use crate::tcb_info_verifier::*;
+pub use crate::tdx_verifier::*;
@@ -0,0 +1,92 @@
+//! Host-side fixed-depth SHA256 Merkle trees, shared by the allowlist and the PCK CRL.
+//!
+//! Inner nodes are SHA256(left || right) of their 32-byte children. The depth is fixed by the
+//! user of the tree so that the circuit shape does not depend on the number of leaves.
+use sha2::{Digest, Sha256};
+
+/// Max sha256 input size of an inner node, i.e. two children and the padding
+pub(crate) const MERKLE_NODE_MAX_BYTE_SIZE: usize = 128;
+
+fn hash_node(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MerkleTree {
+    // levels[0] holds the leaves, the last level the root
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+impl MerkleTree {
+    /// Builds a tree of `depth` over `leaves`, which must already be padded to `1 << depth`
+    pub(crate) fn new(leaves: Vec<[u8; 32]>, depth: usize) -> Self {
+        assert_eq!(leaves.len(), 1 << depth);
+        let mut levels = vec![leaves];
+        for _ in 0..depth {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| hash_node(&pair[0], &pair[1]))
+                .collect();
+            levels.push(level);
+        }
+        Self { levels }
+    }
+
+    pub(crate) fn leaves(&self) -> &[[u8; 32]] {
+        &self.levels[0]
+    }
+
+    pub(crate) fn root(&self) -> [u8; 32] {
+        self.levels.last().unwrap()[0]
+    }
+
+    pub(crate) fn proof(&self, index: usize) -> MerkleProof {
+        let siblings = (0..self.levels.len() - 1)
+            .map(|level| self.levels[level][(index >> level) ^ 1])
+            .collect();
+        MerkleProof { index, siblings }
+    }
+}
+
+/// Authentication path of a leaf, from the leaf level up
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub index: usize,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleProof {
+    /// Whether the node at `level` is a right child
+    pub fn is_right(&self, level: usize) -> bool {
+        (self.index >> level) & 1 == 1
+    }
+
+    /// The `left || right` message hashed at each level, from the leaf level up
+    pub fn messages(&self, leaf: &[u8; 32]) -> Vec<Vec<u8>> {
+        let mut node = *leaf;
+        let mut messages = vec![];
+        for (level, sibling) in self.siblings.iter().enumerate() {
+            let (left, right) = if self.is_right(level) {
+                (sibling, &node)
+            } else {
+                (&node, sibling)
+            };
+            messages.push([left.as_slice(), right.as_slice()].concat());
+            node = hash_node(left, right);
+        }
+        messages
+    }
+
+    pub fn root(&self, leaf: &[u8; 32]) -> [u8; 32] {
+        match self.messages(leaf).last() {
+            Some(message) => Sha256::digest(message).into(),
+            None => *leaf,
+        }
+    }
+}
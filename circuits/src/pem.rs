@@ -0,0 +1,336 @@
+//! PEM framing chip, proving which characters of a PEM certificate form its base64 body.
+//!
+//! Certificates are framed as `PEM_BEGIN_CERTIFICATE` and a newline, the base64 body in lines of
+//! `PEM_LINE_LEN` characters each followed by a newline, and `PEM_END_CERTIFICATE`, as OpenSSL
+//! and Intel's PCS write them. The body characters are then at fixed positions of the PEM bytes,
+//! so only the position of the footer depends on the witnessed length of the body.
+use anyhow::{ensure, Result};
+use halo2_base::utils::PrimeField;
+use halo2_base::{
+    gates::{flex_gate::FlexGateConfig, GateInstructions},
+    halo2_proofs::{circuit::Value, plonk::Error},
+    AssignedValue, Context, QuantumCell,
+};
+
+use crate::base64_decode::{Base64DecodeChip, BASE64_PADDING_CHAR};
+use crate::quote::{PEM_BEGIN_CERTIFICATE, PEM_END_CERTIFICATE};
+use crate::table::Base64Alphabet;
+
+/// Number of base64 characters per line of a PEM body
+pub const PEM_LINE_LEN: usize = 64;
+
+fn pem_header() -> Vec<u8> {
+    format!("{PEM_BEGIN_CERTIFICATE}\n").into_bytes()
+}
+
+fn pem_footer() -> Vec<u8> {
+    format!("\n{PEM_END_CERTIFICATE}").into_bytes()
+}
+
+// position of the footer after a body of `encoded_len` characters, i.e. after the header, the
+// body and the newlines between its lines
+fn footer_start(encoded_len: usize) -> usize {
+    pem_header().len() + encoded_len + encoded_len.saturating_sub(1) / PEM_LINE_LEN
+}
+
+/// Number of bytes of a PEM certificate whose body has up to `max_encoded_len` characters
+pub fn max_pem_len(max_encoded_len: usize) -> usize {
+    footer_start(max_encoded_len) + pem_footer().len()
+}
+
+/// Wraps a base64 certificate body into a PEM certificate, without a newline after the footer
+pub fn to_pem(body: &[u8]) -> Vec<u8> {
+    let lines: Vec<&[u8]> = body.chunks(PEM_LINE_LEN).collect();
+    [pem_header(), lines.join(&b'\n'), pem_footer()].concat()
+}
+
+/// Base64 body of a PEM certificate framed as `to_pem` does, which `PemChip` expects
+pub fn pem_body(pem: &[u8]) -> Result<Vec<u8>> {
+    let (header, footer) = (pem_header(), pem_footer());
+    ensure!(
+        pem.len() >= header.len() + footer.len()
+            && pem.starts_with(&header)
+            && pem.ends_with(&footer),
+        "missing PEM certificate armor"
+    );
+    let body: Vec<u8> = pem[header.len()..pem.len() - footer.len()]
+        .iter()
+        .copied()
+        .filter(|c| *c != b'\n')
+        .collect();
+    ensure!(
+        to_pem(&body) == pem,
+        "PEM certificate body should be in lines of {} characters",
+        PEM_LINE_LEN
+    );
+    Ok(body)
+}
+
+/// PEM certificate assigned by `PemChip`
+#[derive(Debug, Clone)]
+pub struct AssignedPem<'v, F: PrimeField> {
+    /// Bytes of the PEM certificate, followed by unconstrained bytes up to `max_pem_len`
+    pub pem: Vec<AssignedValue<'v, F>>,
+    /// Number of bytes of the PEM certificate
+    pub pem_len: AssignedValue<'v, F>,
+    /// Bytes encoded by the body, followed by zero bytes up to the max decoded length of the
+    /// base64 chip
+    pub decoded: Vec<AssignedValue<'v, F>>,
+    /// Number of bytes encoded by the body, without the bytes of the padding
+    pub decoded_len: AssignedValue<'v, F>,
+}
+
+/// Takes the base64 body of PEM certificates in the region of a halo2-lib context and decodes
+/// it with a `Base64DecodeChip` of the standard alphabet
+#[derive(Debug)]
+pub struct PemChip<'a, F: PrimeField> {
+    gate: &'a FlexGateConfig<F>,
+}
+
+impl<'a, F: PrimeField> PemChip<'a, F> {
+    pub fn new(gate: &'a FlexGateConfig<F>) -> Self {
+        Self { gate }
+    }
+
+    /// Assigns the bytes of `pem` and decodes its body, whose characters are constrained to be
+    /// the PEM bytes between the header and the footer, skipping the newline of each line. The
+    /// footer follows the body at the position set by the witnessed number of characters, which
+    /// is the encoded length of the decoded string.
+    pub fn decode<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        base64: &mut Base64DecodeChip<'_, F>,
+        pem: &[u8],
+    ) -> Result<AssignedPem<'v, F>, Error> {
+        let gate = self.gate;
+        let max_encoded_len = base64.config().max_encoded_len();
+        if base64.config().alphabet() != Base64Alphabet::Standard {
+            return Err(Error::Synthesis);
+        }
+        let body = pem_body(pem).map_err(|_| Error::Synthesis)?;
+        // fails if the body does not fit in the base64 chip
+        let encoded = base64.decode_string(ctx, &body)?;
+
+        let mut bytes = pem.to_vec();
+        bytes.resize(max_pem_len(max_encoded_len), 0);
+        let assigned: Vec<AssignedValue<'v, F>> = bytes
+            .iter()
+            .map(|byte| gate.load_witness(ctx, Value::known(F::from(*byte as u64))))
+            .collect();
+        let header = pem_header();
+        for (byte, expected) in assigned.iter().zip(header.iter()) {
+            gate.assert_is_const(ctx, byte, F::from(*expected as u64));
+        }
+
+        // the body is made of whole quartets, as many as the string decoded by the base64 chip
+        let groups = gate.load_witness(ctx, Value::known(F::from((body.len() / 4) as u64)));
+        let encoded_len = gate.mul(
+            ctx,
+            QuantumCell::Existing(&groups),
+            QuantumCell::Constant(F::from(4)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&encoded_len),
+            QuantumCell::Existing(&encoded.encoded_len),
+        );
+        let indicator =
+            gate.idx_to_indicator(ctx, QuantumCell::Existing(&groups), max_encoded_len / 4 + 1);
+
+        // a quartet is in the body until the indicator of the number of quartets is met
+        let mut in_body = gate.load_constant(ctx, F::one());
+        for (g, characters) in encoded.encoded.chunks(4).enumerate() {
+            in_body = gate.sub(
+                ctx,
+                QuantumCell::Existing(&in_body),
+                QuantumCell::Existing(&indicator[g]),
+            );
+            for (j, character) in characters.iter().enumerate() {
+                let i = 4 * g + j;
+                // characters past the body are the padding character of the base64 chip
+                let expected = gate.select(
+                    ctx,
+                    QuantumCell::Existing(&assigned[header.len() + i + i / PEM_LINE_LEN]),
+                    QuantumCell::Constant(F::from(BASE64_PADDING_CHAR as u64)),
+                    QuantumCell::Existing(&in_body),
+                );
+                gate.assert_equal(
+                    ctx,
+                    QuantumCell::Existing(character),
+                    QuantumCell::Existing(&expected),
+                );
+            }
+
+            // a full line of the body is followed by a newline, which starts the footer after
+            // the last line
+            let line_end = 4 * g + 4;
+            if line_end % PEM_LINE_LEN == 0 {
+                let newline = &assigned[footer_start(line_end)];
+                let expected = gate.select(
+                    ctx,
+                    QuantumCell::Constant(F::from(b'\n' as u64)),
+                    QuantumCell::Existing(newline),
+                    QuantumCell::Existing(&in_body),
+                );
+                gate.assert_equal(
+                    ctx,
+                    QuantumCell::Existing(newline),
+                    QuantumCell::Existing(&expected),
+                );
+            }
+        }
+
+        // the footer is selected at one of its positions for each number of quartets
+        let footer = pem_footer();
+        let starts: Vec<usize> = (0..=max_encoded_len / 4)
+            .map(|groups| footer_start(4 * groups))
+            .collect();
+        for (d, expected) in footer.iter().enumerate() {
+            let candidates = starts
+                .iter()
+                .map(|start| QuantumCell::Existing(&assigned[start + d]));
+            let byte = gate.select_by_indicator(ctx, candidates, &indicator);
+            gate.assert_is_const(ctx, &byte, F::from(*expected as u64));
+        }
+        let start = gate.inner_product(
+            ctx,
+            indicator.iter().map(QuantumCell::Existing).collect(),
+            starts
+                .iter()
+                .map(|start| QuantumCell::Constant(F::from(*start as u64)))
+                .collect(),
+        );
+        let pem_len = gate.add(
+            ctx,
+            QuantumCell::Existing(&start),
+            QuantumCell::Constant(F::from(footer.len() as u64)),
+        );
+
+        Ok(AssignedPem {
+            pem: assigned,
+            pem_len,
+            decoded: encoded.decoded,
+            decoded_len: encoded.decoded_len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sgx_dcap_verifier::{CircuitParams, SgxDcapVerifierConfig};
+    use base64::{engine::general_purpose, Engine};
+    use halo2_base::halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::MockProver,
+        halo2curves::bn256::Fr,
+        plonk::{Circuit, ConstraintSystem},
+    };
+    use halo2_base::SKIP_FIRST_PASS;
+
+    const MAX_ENCODED_LEN: usize = 128;
+
+    // decodes the body of `pem` and exposes the decoded length and the length of the PEM bytes
+    #[derive(Default)]
+    struct PemTestCircuit {
+        pem: Vec<u8>,
+        decoded: Vec<u8>,
+    }
+
+    impl Circuit<Fr> for PemTestCircuit {
+        type Config = SgxDcapVerifierConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            SgxDcapVerifierConfig::configure_with_params(
+                meta,
+                &CircuitParams::default(),
+                vec![64],
+                MAX_ENCODED_LEN,
+            )
+            .unwrap()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            let mut outputs = vec![];
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "PEM",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let mut aux = config.new_context(region);
+                    let ctx = &mut aux;
+
+                    let mut base64 = config.base64_chip();
+                    let pem = PemChip::new(config.gate()).decode(ctx, &mut base64, &self.pem)?;
+                    let mut expected = self.decoded.clone();
+                    expected.resize(MAX_ENCODED_LEN / 4 * 3, 0);
+                    config.constrain_bytes(ctx, &pem.decoded, &expected);
+                    outputs = vec![pem.decoded_len.cell(), pem.pem_len.cell()];
+
+                    config.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            config.constrain_instances(&mut layouter, outputs)
+        }
+    }
+
+    #[test]
+    fn test_pem_decode() {
+        let k = 17;
+        // empty, single line, exactly one line, and a last line with a single quartet
+        for len in [0, 5, 48, 50, 96] {
+            let decoded: Vec<u8> = (0..len as u8).collect();
+            let pem = to_pem(general_purpose::STANDARD.encode(&decoded).as_bytes());
+            let circuit = PemTestCircuit {
+                pem: pem.clone(),
+                decoded: decoded.clone(),
+            };
+            let instances = vec![Fr::from(len as u64), Fr::from(pem.len() as u64)];
+            let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+            prover.assert_satisfied();
+
+            // the footer is not elsewhere
+            let instances = vec![Fr::from(len as u64), Fr::from(pem.len() as u64 + 1)];
+            let prover = MockProver::run(k, &circuit, vec![instances]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+
+        // bodies in other line lengths or with CRLF line breaks, and bodies that do not fit
+        let body = general_purpose::STANDARD.encode([0; 96]);
+        let long_lines = [
+            pem_header(),
+            body.as_bytes()[..76].to_vec(),
+            b"\n".to_vec(),
+            body.as_bytes()[76..].to_vec(),
+            pem_footer(),
+        ]
+        .concat();
+        let crlf = String::from_utf8(to_pem(body.as_bytes()))
+            .unwrap()
+            .replace('\n', "\r\n")
+            .into_bytes();
+        let too_long = to_pem(general_purpose::STANDARD.encode([0; 99]).as_bytes());
+        for pem in [long_lines, crlf, too_long] {
+            let circuit = PemTestCircuit {
+                pem,
+                decoded: vec![0; 96],
+            };
+            let instances = vec![Fr::zero(), Fr::zero()];
+            assert!(MockProver::run(k, &circuit, vec![instances]).is_err());
+        }
+    }
+}
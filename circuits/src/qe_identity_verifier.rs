@@ -0,0 +1,421 @@
+use anyhow::{anyhow, ensure};
+use halo2_base::utils::PrimeField;
+use halo2_base::{
+    gates::GateInstructions,
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    AssignedValue, Context, QuantumCell, SKIP_FIRST_PASS,
+};
+use std::marker::PhantomData;
+
+use crate::collateral::{
+    EnclaveIdentity, EnclaveIdentityLayout, ATTRIBUTES_KEY, ATTRIBUTES_MASK_KEY, ID_KEY,
+    ISV_PROD_ID_KEY, ISV_SVN_KEY, MISC_SELECT_KEY, MISC_SELECT_MASK_KEY, MR_SIGNER_KEY,
+    QE_IDENTITY_ID, TCB_LEVELS_KEY,
+};
+use crate::quote::pem_certificates;
+use crate::sgx_dcap_verifier::{
+    pack_instances, CircuitParams, CircuitParamsSpec, SgxDcapVerifierConfig,
+};
+use crate::tcb::{QeIdentity, QE_IDENTITY_LEN, QE_IDENTITY_MAX_BYTE_SIZE, QE_TCB_LEVEL_LEN};
+use crate::x509::CertLayout;
+
+/// Max sha256 input size of the signed `enclaveIdentity` body, which is hashed in-circuit as a
+/// whole
+pub const QE_IDENTITY_BODY_MAX_BYTE_SIZE: usize = 4096;
+
+// parameters of `QeIdentityCircuitParams`
+const QE_IDENTITY_CIRCUIT_PARAMS: &str = include_str!("configs/qe_identity_circuit.config");
+
+// Public instances of `QeIdentityVerifierCircuit`: the SHA256 commitment to the QE Identity in 2
+// instances, matching the QE Identity instances of `SgxDcapVerifierCircuit`
+pub const QE_IDENTITY_COMMITMENT_INSTANCE: usize = 0;
+pub const QE_IDENTITY_NUM_INSTANCES: usize = 2;
+
+/// Takes the identity of the QE from the signed `enclaveIdentity` body located by `layout`,
+/// encoded like `QeIdentity::to_bytes` with the TCB levels of `identity`, which are constrained
+/// to be those of the body, see `SgxDcapVerifierConfig::json_tcb_levels`
+fn encode_qe_identity<'v, F: PrimeField>(
+    config: &SgxDcapVerifierConfig<F>,
+    ctx: &mut Context<'v, F>,
+    body: &[AssignedValue<'v, F>],
+    layout: &EnclaveIdentityLayout,
+    identity: &QeIdentity,
+) -> Vec<AssignedValue<'v, F>> {
+    let gate = config.gate();
+
+    // the identity of the QE, not of the QVE or TDX QE
+    let id = config.json_string_field(ctx, body, &layout.id, ID_KEY, QE_IDENTITY_ID.len());
+    config.constrain_bytes(ctx, &id, QE_IDENTITY_ID.as_bytes());
+
+    let mut encoded = vec![];
+    for (field, key, len) in [
+        (&layout.misc_select, MISC_SELECT_KEY, 4),
+        (&layout.misc_select_mask, MISC_SELECT_MASK_KEY, 4),
+        (&layout.attributes, ATTRIBUTES_KEY, 16),
+        (&layout.attributes_mask, ATTRIBUTES_MASK_KEY, 16),
+        (&layout.mr_signer, MR_SIGNER_KEY, 32),
+    ] {
+        let hex = config.json_string_field(ctx, body, field, key, 2 * len);
+        encoded.extend(config.parse_hex(ctx, &hex));
+    }
+
+    // ISVPRODID is encoded big-endian, like the ISVSVN of each level
+    let isv_prod_id = config.load_bytes(ctx, &identity.isv_prod_id.to_be_bytes());
+    let value = config.pack_bytes(ctx, &isv_prod_id);
+    let parsed = config.json_number(ctx, body, &layout.isv_prod_id, ISV_PROD_ID_KEY, 5);
+    gate.assert_equal(
+        ctx,
+        QuantumCell::Existing(&parsed),
+        QuantumCell::Existing(&value[0]),
+    );
+    encoded.extend(isv_prod_id);
+
+    let levels = config.load_bytes(ctx, &identity.to_bytes()[QE_IDENTITY_LEN..]);
+    let (array_start, bracket) =
+        config.json_field(ctx, body, layout.tcb_levels.offset(), TCB_LEVELS_KEY, 1);
+    config.constrain_bytes(ctx, &bracket, b"[");
+    let num_levels = gate.load_witness(
+        ctx,
+        Value::known(F::from(identity.tcb_levels().len() as u64)),
+    );
+    config.json_tcb_levels(
+        ctx,
+        body,
+        &array_start,
+        ISV_SVN_KEY,
+        &levels,
+        QE_TCB_LEVEL_LEN,
+        &num_levels,
+    );
+    encoded.extend(levels);
+    encoded
+}
+
+/// The parameters of `src/configs/qe_identity_circuit.config`, the default ones of
+/// `QeIdentityVerifierCircuit`. The walk of the body slot takes a few hundred cells per byte,
+/// which 6 advice columns of 2^20 rows hold with the 2 ECDSA verifications.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QeIdentityCircuitParams;
+
+impl CircuitParamsSpec for QeIdentityCircuitParams {
+    fn params() -> CircuitParams {
+        CircuitParams::from_json(QE_IDENTITY_CIRCUIT_PARAMS.as_bytes())
+            .expect("built-in circuit params should be valid")
+    }
+}
+
+/// Verifies Intel's signature of the QE Identity and commits to it, with the ECDSA chips
+/// configured with the parameters of `P`
+#[derive(Default, Clone)]
+pub struct QeIdentityVerifierCircuit<F: PrimeField, P: CircuitParamsSpec = QeIdentityCircuitParams>
+{
+    // base64 body of the TCB Signing certificate, issued by Intel's root CA
+    pub tcb_signing_cert: Vec<u8>,
+    pub qe_identity: EnclaveIdentity,
+    _marker: PhantomData<(F, P)>,
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> QeIdentityVerifierCircuit<F, P> {
+    // max sha256 input sizes in order of digest: TCB Signing TBSCertificate, enclaveIdentity body
+    // and encoded QE Identity
+    const MAX_BYTE_SIZES: [usize; 3] = [
+        704,
+        QE_IDENTITY_BODY_MAX_BYTE_SIZE,
+        QE_IDENTITY_MAX_BYTE_SIZE,
+    ];
+
+    /// `qe_identity_issuer_chain` is the PEM `SGX-Enclave-Identity-Issuer-Chain` served with the
+    /// QE Identity, i.e. the TCB Signing certificate followed by the root CA certificate
+    pub fn new(
+        qe_identity: EnclaveIdentity,
+        qe_identity_issuer_chain: &[u8],
+    ) -> anyhow::Result<Self> {
+        let tcb_signing_cert = pem_certificates(qe_identity_issuer_chain)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("QE Identity issuer chain is empty"))?;
+        ensure!(
+            qe_identity.id == QE_IDENTITY_ID,
+            "not a QE Identity: {}",
+            qe_identity.id
+        );
+        ensure!(
+            qe_identity.body.len() + 9 <= QE_IDENTITY_BODY_MAX_BYTE_SIZE,
+            "enclaveIdentity body does not fit in its sha256 slot"
+        );
+        qe_identity.layout()?;
+        Ok(Self {
+            tcb_signing_cert,
+            qe_identity,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Parses a QE Identity JSON document and the PEM issuer chain served with it
+    pub fn from_collateral(
+        qe_identity: &[u8],
+        qe_identity_issuer_chain: &[u8],
+    ) -> anyhow::Result<Self> {
+        Self::new(
+            EnclaveIdentity::parse(qe_identity)?,
+            qe_identity_issuer_chain,
+        )
+    }
+
+    /// Public instances of the circuit, laid out as described at
+    /// `QE_IDENTITY_COMMITMENT_INSTANCE`
+    pub fn instances(&self) -> Vec<F> {
+        pack_instances(&self.qe_identity.identity.commitment())
+    }
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> Circuit<F> for QeIdentityVerifierCircuit<F, P> {
+    type Config = SgxDcapVerifierConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        SgxDcapVerifierConfig::configure_with_max_byte_sizes(
+            meta,
+            &P::params(),
+            Self::MAX_BYTE_SIZES.to_vec(),
+        )
+        .expect("circuit params should be valid")
+        .with_root_ca_pubkey(P::root_ca_pubkey())
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+
+        // see `SgxDcapVerifierCircuit::synthesize`, a certificate that does not parse fails the
+        // proof
+        let (cert_der, cert_layout) =
+            CertLayout::from_base64(&self.tcb_signing_cert).unwrap_or_default();
+        // a body that does not lay out fails the proof, like a certificate
+        let layout = self.qe_identity.layout().unwrap_or_default();
+        let identity = &self.qe_identity.identity;
+
+        let digests = config.digest(
+            &mut layouter,
+            &[
+                cert_der[cert_layout.tbs.clone()].to_vec(),
+                self.qe_identity.body.clone(),
+                identity.to_bytes(),
+            ],
+        )?;
+        let (tbs_digest, body_digest, identity_digest) = (&digests[0], &digests[1], &digests[2]);
+
+        let mut outputs = vec![];
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "ECDSA",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = config.new_context(region);
+                let ctx = &mut aux;
+
+                // Intel's root CA signs the TCB Signing certificate, which signs the
+                // enclaveIdentity body
+                let body = config.verify_signed_collateral(
+                    ctx,
+                    &mut config.base64_chip(),
+                    &self.tcb_signing_cert,
+                    &cert_layout.signature(&cert_der),
+                    tbs_digest,
+                    &self.qe_identity.body,
+                    &self.qe_identity.signature,
+                    body_digest,
+                )?;
+
+                // the identity of the body, encoded like `QeIdentity::to_bytes`, is committed to
+                let encoded = encode_qe_identity(&config, ctx, &body, &layout, identity);
+                config.constrain_digest_input(ctx, identity_digest, &encoded);
+                let instances = config.pack_bytes(ctx, &identity_digest.output_bytes);
+                outputs = instances.iter().map(|x| x.cell()).collect();
+
+                config.finalize(ctx);
+                Ok(())
+            },
+        )?;
+
+        config.constrain_instances(&mut layouter, outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::tests::{
+        qe_identity_body, qe_identity_document, signed_qe_identity_document, test_pck_cert_chain,
+        TestRootCa, TEST_INTERMEDIATE_KEY,
+    };
+    use crate::tcb::{QeTcbLevel, TcbStatus};
+    use halo2_base::halo2_proofs::{
+        dev::MockProver,
+        halo2curves::{bn256::Fr, secp256r1::Fq},
+    };
+
+    // bytes of the body walked by the test circuit, rather than the whole sha256 slot
+    const TEST_BODY_LEN: usize = 1024;
+
+    // the walk takes more cells than the built-in advice columns hold in 2^17 rows
+    fn test_params() -> CircuitParams {
+        CircuitParams {
+            num_advice: 8,
+            ..CircuitParams::default()
+        }
+    }
+
+    // encodes the identity of an unsigned enclaveIdentity body with the given identity as witness
+    #[derive(Default)]
+    struct QeIdentityFieldsTestCircuit {
+        qe_identity: EnclaveIdentity,
+        identity: QeIdentity,
+    }
+
+    impl Circuit<Fr> for QeIdentityFieldsTestCircuit {
+        type Config = SgxDcapVerifierConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            SgxDcapVerifierConfig::configure_with_params(meta, &test_params(), vec![64], 16)
+                .unwrap()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            let layout = self.qe_identity.layout().unwrap_or_default();
+            let mut outputs = vec![];
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "enclaveIdentity",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let mut aux = config.new_context(region);
+                    let ctx = &mut aux;
+
+                    let mut body = self.qe_identity.body.clone();
+                    body.resize(TEST_BODY_LEN, 0);
+                    let body = config.load_bytes(ctx, &body);
+                    let encoded = encode_qe_identity(&config, ctx, &body, &layout, &self.identity);
+                    let instances = config.pack_bytes(ctx, &encoded);
+                    outputs = instances.iter().map(|x| x.cell()).collect();
+
+                    config.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            config.constrain_instances(&mut layouter, outputs)
+        }
+    }
+
+    #[test]
+    fn test_qe_identity_fields() {
+        let k = 17;
+        let (json, _) = qe_identity_document(&qe_identity_body());
+        let qe_identity = EnclaveIdentity::parse(json.as_bytes()).unwrap();
+        assert!(qe_identity.body.len() <= TEST_BODY_LEN);
+        let identity = qe_identity.identity.clone();
+        let instances = pack_instances(&identity.to_bytes());
+        let circuit = QeIdentityFieldsTestCircuit {
+            qe_identity,
+            identity: identity.clone(),
+        };
+        MockProver::run(k, &circuit, vec![instances.clone()])
+            .unwrap()
+            .assert_satisfied();
+
+        let levels = identity.tcb_levels();
+        let with_levels = |levels: Vec<QeTcbLevel>| {
+            QeIdentity::new(
+                identity.misc_select,
+                identity.misc_select_mask,
+                identity.attributes,
+                identity.attributes_mask,
+                identity.mr_signer,
+                identity.isv_prod_id,
+                levels,
+            )
+            .unwrap()
+        };
+        // an ISVSVN that is not in the body
+        let forged = QeTcbLevel {
+            isv_svn: 7,
+            ..levels[0].clone()
+        };
+        // the ISVSVN of the first level with the status of the second one
+        let spliced = QeTcbLevel {
+            status: TcbStatus::OutOfDate,
+            ..levels[0].clone()
+        };
+        for levels in [
+            vec![forged, levels[1].clone()],
+            vec![spliced, levels[1].clone()],
+            vec![levels[0].clone()],
+            vec![levels[1].clone(), levels[0].clone()],
+        ] {
+            let identity = with_levels(levels);
+            let forged = QeIdentityFieldsTestCircuit {
+                qe_identity: circuit.qe_identity.clone(),
+                identity: identity.clone(),
+            };
+            // the instances match the forged identity, only the walk of the body rejects it
+            let instances = pack_instances(&identity.to_bytes());
+            assert!(MockProver::run(k, &forged, vec![instances])
+                .unwrap()
+                .verify()
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_qe_identity_verifier() {
+        let params = QeIdentityCircuitParams::params();
+        let chain = test_pck_cert_chain()[1..].concat();
+        let json =
+            signed_qe_identity_document(&qe_identity_body(), Fq::from(TEST_INTERMEDIATE_KEY));
+        let circuit =
+            QeIdentityVerifierCircuit::<Fr, TestRootCa<QeIdentityCircuitParams>>::from_collateral(
+                json.as_bytes(),
+                &chain,
+            )
+            .unwrap();
+        let instances = circuit.instances();
+        MockProver::run(params.degree, &circuit, vec![instances.clone()])
+            .unwrap()
+            .assert_satisfied();
+
+        // the proof does not hold for another QE Identity
+        let mut tampered = instances;
+        tampered[QE_IDENTITY_COMMITMENT_INSTANCE] += Fr::from(1);
+        assert!(MockProver::run(params.degree, &circuit, vec![tampered])
+            .unwrap()
+            .verify()
+            .is_err());
+    }
+}
@@ -0,0 +1,840 @@
+//! Host-side parser for SGX DCAP ECDSA quotes (version 3) and TDX quotes (version 4).
+//!
+//! The layout follows Intel's "SGX ECDSA Quote Library API" and "TDX DCAP Quote
+//! Generation Library and Quote Verification Library" references. All
+//! integers in the quote are little-endian, while signatures and public keys
+//! are stored as big-endian `r || s` and `x || y` pairs.
+use anyhow::{anyhow, ensure, Result};
+
+pub const QUOTE_HEADER_LEN: usize = 48;
+pub const ENCLAVE_REPORT_LEN: usize = 384;
+pub const TD_REPORT_LEN: usize = 584;
+pub const ECDSA_SIGNATURE_LEN: usize = 64;
+pub const ECDSA_PUBKEY_LEN: usize = 64;
+
+pub const QUOTE_VERSION_3: u16 = 3;
+pub const QUOTE_VERSION_4: u16 = 4;
+pub const ATT_KEY_TYPE_ECDSA_P256: u16 = 2;
+pub const TEE_TYPE_SGX: u32 = 0x00;
+pub const TEE_TYPE_TDX: u32 = 0x81;
+pub const CERT_DATA_TYPE_PCK_CERT_CHAIN: u16 = 5;
+pub const CERT_DATA_TYPE_QE_REPORT_CERT_DATA: u16 = 6;
+// offsets of the fields in an SGX report body
+pub const MISC_SELECT_OFFSET: usize = 16;
+pub const ATTRIBUTES_OFFSET: usize = 48;
+pub const MR_ENCLAVE_OFFSET: usize = 64;
+pub const MR_SIGNER_OFFSET: usize = 128;
+pub const ISV_PROD_ID_OFFSET: usize = 256;
+pub const ISV_SVN_OFFSET: usize = 258;
+pub const REPORT_DATA_OFFSET: usize = 320;
+
+pub(crate) const PEM_BEGIN_CERTIFICATE: &str = "-----BEGIN CERTIFICATE-----";
+pub(crate) const PEM_END_CERTIFICATE: &str = "-----END CERTIFICATE-----";
+
+/// Simple forward-only reader over the raw quote bytes
+struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, offset: 0 }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| {
+                anyhow!(
+                    "quote truncated: need {} bytes at offset {}, {} available",
+                    len,
+                    self.offset,
+                    self.bytes.len() - self.offset
+                )
+            })?;
+        let res = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(res)
+    }
+
+    /// Fails if any bytes are left after the last field
+    fn finish(self) -> Result<()> {
+        ensure!(
+            self.offset == self.bytes.len(),
+            "{} trailing bytes at offset {}",
+            self.bytes.len() - self.offset,
+            self.offset
+        );
+        Ok(())
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuoteHeader {
+    pub version: u16,
+    pub att_key_type: u16,
+    pub tee_type: u32,
+    pub qe_svn: u16,
+    pub pce_svn: u16,
+    pub qe_vendor_id: [u8; 16],
+    pub user_data: [u8; 20],
+}
+
+impl QuoteHeader {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() == QUOTE_HEADER_LEN,
+            "quote header should be {} bytes",
+            QUOTE_HEADER_LEN
+        );
+        let mut reader = Reader::new(bytes);
+        Ok(Self {
+            version: reader.u16()?,
+            att_key_type: reader.u16()?,
+            tee_type: reader.u32()?,
+            qe_svn: reader.u16()?,
+            pce_svn: reader.u16()?,
+            qe_vendor_id: reader.array()?,
+            user_data: reader.array()?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(QUOTE_HEADER_LEN);
+        bytes.extend(self.version.to_le_bytes());
+        bytes.extend(self.att_key_type.to_le_bytes());
+        bytes.extend(self.tee_type.to_le_bytes());
+        bytes.extend(self.qe_svn.to_le_bytes());
+        bytes.extend(self.pce_svn.to_le_bytes());
+        bytes.extend(self.qe_vendor_id);
+        bytes.extend(self.user_data);
+        bytes
+    }
+}
+
+/// The 384-byte SGX report body, used for both the ISV enclave report and the QE report
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnclaveReportBody {
+    pub cpu_svn: [u8; 16],
+    pub misc_select: u32,
+    pub reserved1: [u8; 28],
+    pub attributes: [u8; 16],
+    pub mr_enclave: [u8; 32],
+    pub reserved2: [u8; 32],
+    pub mr_signer: [u8; 32],
+    pub reserved3: [u8; 96],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub reserved4: [u8; 60],
+    pub report_data: [u8; 64],
+}
+
+impl Default for EnclaveReportBody {
+    fn default() -> Self {
+        Self {
+            cpu_svn: [0; 16],
+            misc_select: 0,
+            reserved1: [0; 28],
+            attributes: [0; 16],
+            mr_enclave: [0; 32],
+            reserved2: [0; 32],
+            mr_signer: [0; 32],
+            reserved3: [0; 96],
+            isv_prod_id: 0,
+            isv_svn: 0,
+            reserved4: [0; 60],
+            report_data: [0; 64],
+        }
+    }
+}
+
+impl EnclaveReportBody {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() == ENCLAVE_REPORT_LEN,
+            "enclave report body should be {} bytes",
+            ENCLAVE_REPORT_LEN
+        );
+        let mut reader = Reader::new(bytes);
+        Ok(Self {
+            cpu_svn: reader.array()?,
+            misc_select: reader.u32()?,
+            reserved1: reader.array()?,
+            attributes: reader.array()?,
+            mr_enclave: reader.array()?,
+            reserved2: reader.array()?,
+            mr_signer: reader.array()?,
+            reserved3: reader.array()?,
+            isv_prod_id: reader.u16()?,
+            isv_svn: reader.u16()?,
+            reserved4: reader.array()?,
+            report_data: reader.array()?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(ENCLAVE_REPORT_LEN);
+        bytes.extend(self.cpu_svn);
+        bytes.extend(self.misc_select.to_le_bytes());
+        bytes.extend(self.reserved1);
+        bytes.extend(self.attributes);
+        bytes.extend(self.mr_enclave);
+        bytes.extend(self.reserved2);
+        bytes.extend(self.mr_signer);
+        bytes.extend(self.reserved3);
+        bytes.extend(self.isv_prod_id.to_le_bytes());
+        bytes.extend(self.isv_svn.to_le_bytes());
+        bytes.extend(self.reserved4);
+        bytes.extend(self.report_data);
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificationData {
+    pub cert_data_type: u16,
+    pub cert_data: Vec<u8>,
+}
+
+impl CertificationData {
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let cert_data_type = reader.u16()?;
+        let size = reader.u32()? as usize;
+        Ok(Self {
+            cert_data_type,
+            cert_data: reader.take(size)?.to_vec(),
+        })
+    }
+
+    /// Extracts the PCK certificate chain carried by certification data type 5
+    pub fn pck_cert_chain(&self) -> Result<PckCertChain> {
+        ensure!(
+            self.cert_data_type == CERT_DATA_TYPE_PCK_CERT_CHAIN,
+            "expected certification data type {}, got {}",
+            CERT_DATA_TYPE_PCK_CERT_CHAIN,
+            self.cert_data_type
+        );
+        PckCertChain::parse(&self.cert_data)
+    }
+}
+
+/// Base64 bodies of the PEM certificates in a PCK certificate chain, with the PEM armor and
+/// line breaks stripped, and the PEM certificates as they appear in the chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PckCertChain {
+    pub leaf: Vec<u8>,
+    pub intermediate: Vec<u8>,
+    pub root: Vec<u8>,
+    pub leaf_pem: Vec<u8>,
+    pub intermediate_pem: Vec<u8>,
+    pub root_pem: Vec<u8>,
+}
+
+/// Splits concatenated PEM certificates, each from its `PEM_BEGIN_CERTIFICATE` to its
+/// `PEM_END_CERTIFICATE` marker, with the bytes in between as they appear in `pem`
+pub fn pem_blocks(pem: &[u8]) -> Result<Vec<Vec<u8>>> {
+    // the chain may be NUL terminated
+    let pem = std::str::from_utf8(pem)?.trim_end_matches('\0');
+
+    let mut blocks = vec![];
+    let mut rest = pem;
+    while let Some(begin) = rest.find(PEM_BEGIN_CERTIFICATE) {
+        rest = &rest[begin..];
+        let end = rest
+            .find(PEM_END_CERTIFICATE)
+            .ok_or_else(|| anyhow!("unterminated PEM certificate"))?
+            + PEM_END_CERTIFICATE.len();
+        blocks.push(rest[..end].as_bytes().to_vec());
+        rest = &rest[end..];
+    }
+    Ok(blocks)
+}
+
+/// Base64 body of a PEM certificate block, with the PEM armor and line breaks stripped
+fn block_body(block: &[u8]) -> Result<Vec<u8>> {
+    let body: Vec<u8> = block[PEM_BEGIN_CERTIFICATE.len()..block.len() - PEM_END_CERTIFICATE.len()]
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    ensure!(body.len() % 4 == 0, "invalid base64 certificate body");
+    Ok(body)
+}
+
+/// Splits concatenated PEM certificates into their base64 bodies, with the PEM armor and line
+/// breaks stripped
+pub fn pem_certificates(pem: &[u8]) -> Result<Vec<Vec<u8>>> {
+    pem_blocks(pem)?
+        .iter()
+        .map(|block| block_body(block))
+        .collect()
+}
+
+/// Empty certificates, whose PEMs still decode in-circuit so that the default chain synthesizes
+impl Default for PckCertChain {
+    fn default() -> Self {
+        let pem = crate::pem::to_pem(b"");
+        Self {
+            leaf: vec![],
+            intermediate: vec![],
+            root: vec![],
+            leaf_pem: pem.clone(),
+            intermediate_pem: pem.clone(),
+            root_pem: pem,
+        }
+    }
+}
+
+impl PckCertChain {
+    /// Splits a concatenated `leaf || intermediate || root` PEM chain
+    pub fn parse(pem: &[u8]) -> Result<Self> {
+        let [leaf_pem, intermediate_pem, root_pem]: [Vec<u8>; 3] =
+            pem_blocks(pem)?.try_into().map_err(|certs: Vec<_>| {
+                anyhow!(
+                    "PCK certificate chain should have 3 certificates, got {}",
+                    certs.len()
+                )
+            })?;
+        Ok(Self {
+            leaf: block_body(&leaf_pem)?,
+            intermediate: block_body(&intermediate_pem)?,
+            root: block_body(&root_pem)?,
+            leaf_pem,
+            intermediate_pem,
+            root_pem,
+        })
+    }
+}
+
+/// The QE report, its signature and the PCK certificate chain certifying it. This is laid out
+/// inline in the v3 signature data and nested as certification data type 6 in v4 quotes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QeReportCertificationData {
+    pub qe_report: EnclaveReportBody,
+    /// ECDSA signature over the QE report, made by the PCK leaf certificate key
+    pub qe_report_signature: [u8; ECDSA_SIGNATURE_LEN],
+    pub qe_auth_data: Vec<u8>,
+    pub certification_data: CertificationData,
+}
+
+impl Default for QeReportCertificationData {
+    fn default() -> Self {
+        Self {
+            qe_report: EnclaveReportBody::default(),
+            qe_report_signature: [0; ECDSA_SIGNATURE_LEN],
+            qe_auth_data: vec![],
+            certification_data: CertificationData::default(),
+        }
+    }
+}
+
+impl QeReportCertificationData {
+    fn read(reader: &mut Reader<'_>) -> Result<Self> {
+        let qe_report = EnclaveReportBody::parse(reader.take(ENCLAVE_REPORT_LEN)?)?;
+        let qe_report_signature = reader.array()?;
+        let qe_auth_data_len = reader.u16()? as usize;
+        let qe_auth_data = reader.take(qe_auth_data_len)?.to_vec();
+        let certification_data = CertificationData::read(reader)?;
+
+        Ok(Self {
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            certification_data,
+        })
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        Self::read(&mut Reader::new(bytes))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QuoteSignatureData {
+    /// ECDSA signature over the quote header and the ISV enclave report body
+    pub isv_enclave_report_signature: [u8; ECDSA_SIGNATURE_LEN],
+    /// Raw `x || y` attestation public key
+    pub ecdsa_attestation_key: [u8; ECDSA_PUBKEY_LEN],
+    pub qe_report: EnclaveReportBody,
+    /// ECDSA signature over the QE report, made by the PCK leaf certificate key
+    pub qe_report_signature: [u8; ECDSA_SIGNATURE_LEN],
+    pub qe_auth_data: Vec<u8>,
+    pub certification_data: CertificationData,
+}
+
+impl Default for QuoteSignatureData {
+    fn default() -> Self {
+        Self {
+            isv_enclave_report_signature: [0; ECDSA_SIGNATURE_LEN],
+            ecdsa_attestation_key: [0; ECDSA_PUBKEY_LEN],
+            qe_report: EnclaveReportBody::default(),
+            qe_report_signature: [0; ECDSA_SIGNATURE_LEN],
+            qe_auth_data: vec![],
+            certification_data: CertificationData::default(),
+        }
+    }
+}
+
+impl QuoteSignatureData {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let isv_enclave_report_signature = reader.array()?;
+        let ecdsa_attestation_key = reader.array()?;
+        let QeReportCertificationData {
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            certification_data,
+        } = QeReportCertificationData::read(&mut reader)?;
+
+        Ok(Self {
+            isv_enclave_report_signature,
+            ecdsa_attestation_key,
+            qe_report,
+            qe_report_signature,
+            qe_auth_data,
+            certification_data,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Quote {
+    pub header: QuoteHeader,
+    pub isv_enclave_report: EnclaveReportBody,
+    pub signature_data_len: u32,
+    pub signature: QuoteSignatureData,
+}
+
+impl Quote {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let header = QuoteHeader::parse(reader.take(QUOTE_HEADER_LEN)?)?;
+        ensure!(
+            header.version == QUOTE_VERSION_3,
+            "unsupported quote version {}",
+            header.version
+        );
+        ensure!(
+            header.tee_type == TEE_TYPE_SGX,
+            "unsupported TEE type {:#x}",
+            header.tee_type
+        );
+        ensure!(
+            header.att_key_type == ATT_KEY_TYPE_ECDSA_P256,
+            "unsupported attestation key type {}",
+            header.att_key_type
+        );
+        let isv_enclave_report = EnclaveReportBody::parse(reader.take(ENCLAVE_REPORT_LEN)?)?;
+        let signature_data_len = reader.u32()?;
+        let signature = QuoteSignatureData::parse(reader.take(signature_data_len as usize)?)?;
+        reader.finish()?;
+
+        Ok(Self {
+            header,
+            isv_enclave_report,
+            signature_data_len,
+            signature,
+        })
+    }
+
+    /// The signed part of the quote, i.e. the header followed by the ISV enclave report body
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        [self.header.to_bytes(), self.isv_enclave_report.to_bytes()].concat()
+    }
+}
+
+/// The 584-byte TD 1.0 report body of a TDX quote
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TdReportBody {
+    pub tee_tcb_svn: [u8; 16],
+    pub mr_seam: [u8; 48],
+    pub mr_signer_seam: [u8; 48],
+    pub seam_attributes: [u8; 8],
+    pub td_attributes: [u8; 8],
+    pub xfam: [u8; 8],
+    pub mr_td: [u8; 48],
+    pub mr_config_id: [u8; 48],
+    pub mr_owner: [u8; 48],
+    pub mr_owner_config: [u8; 48],
+    pub rt_mr: [[u8; 48]; 4],
+    pub report_data: [u8; 64],
+}
+
+impl Default for TdReportBody {
+    fn default() -> Self {
+        Self {
+            tee_tcb_svn: [0; 16],
+            mr_seam: [0; 48],
+            mr_signer_seam: [0; 48],
+            seam_attributes: [0; 8],
+            td_attributes: [0; 8],
+            xfam: [0; 8],
+            mr_td: [0; 48],
+            mr_config_id: [0; 48],
+            mr_owner: [0; 48],
+            mr_owner_config: [0; 48],
+            rt_mr: [[0; 48]; 4],
+            report_data: [0; 64],
+        }
+    }
+}
+
+impl TdReportBody {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        ensure!(
+            bytes.len() == TD_REPORT_LEN,
+            "TD report body should be {} bytes",
+            TD_REPORT_LEN
+        );
+        let mut reader = Reader::new(bytes);
+        Ok(Self {
+            tee_tcb_svn: reader.array()?,
+            mr_seam: reader.array()?,
+            mr_signer_seam: reader.array()?,
+            seam_attributes: reader.array()?,
+            td_attributes: reader.array()?,
+            xfam: reader.array()?,
+            mr_td: reader.array()?,
+            mr_config_id: reader.array()?,
+            mr_owner: reader.array()?,
+            mr_owner_config: reader.array()?,
+            rt_mr: [
+                reader.array()?,
+                reader.array()?,
+                reader.array()?,
+                reader.array()?,
+            ],
+            report_data: reader.array()?,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(TD_REPORT_LEN);
+        bytes.extend(self.tee_tcb_svn);
+        bytes.extend(self.mr_seam);
+        bytes.extend(self.mr_signer_seam);
+        bytes.extend(self.seam_attributes);
+        bytes.extend(self.td_attributes);
+        bytes.extend(self.xfam);
+        bytes.extend(self.mr_td);
+        bytes.extend(self.mr_config_id);
+        bytes.extend(self.mr_owner);
+        bytes.extend(self.mr_owner_config);
+        self.rt_mr.iter().for_each(|rt_mr| bytes.extend(rt_mr));
+        bytes.extend(self.report_data);
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TdxQuoteSignatureData {
+    /// ECDSA signature over the quote header and the TD report body
+    pub quote_signature: [u8; ECDSA_SIGNATURE_LEN],
+    /// Raw `x || y` attestation public key
+    pub ecdsa_attestation_key: [u8; ECDSA_PUBKEY_LEN],
+    /// Certification data type 6
+    pub qe_report_certification_data: QeReportCertificationData,
+}
+
+impl Default for TdxQuoteSignatureData {
+    fn default() -> Self {
+        Self {
+            quote_signature: [0; ECDSA_SIGNATURE_LEN],
+            ecdsa_attestation_key: [0; ECDSA_PUBKEY_LEN],
+            qe_report_certification_data: QeReportCertificationData::default(),
+        }
+    }
+}
+
+impl TdxQuoteSignatureData {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let quote_signature = reader.array()?;
+        let ecdsa_attestation_key = reader.array()?;
+        let certification_data = CertificationData::read(&mut reader)?;
+        ensure!(
+            certification_data.cert_data_type == CERT_DATA_TYPE_QE_REPORT_CERT_DATA,
+            "expected certification data type {}, got {}",
+            CERT_DATA_TYPE_QE_REPORT_CERT_DATA,
+            certification_data.cert_data_type
+        );
+
+        Ok(Self {
+            quote_signature,
+            ecdsa_attestation_key,
+            qe_report_certification_data: QeReportCertificationData::parse(
+                &certification_data.cert_data,
+            )?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TdxQuote {
+    pub header: QuoteHeader,
+    pub td_report: TdReportBody,
+    pub signature_data_len: u32,
+    pub signature: TdxQuoteSignatureData,
+}
+
+impl TdxQuote {
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes);
+        let header = QuoteHeader::parse(reader.take(QUOTE_HEADER_LEN)?)?;
+        ensure!(
+            header.version == QUOTE_VERSION_4,
+            "unsupported quote version {}",
+            header.version
+        );
+        ensure!(
+            header.tee_type == TEE_TYPE_TDX,
+            "unsupported TEE type {:#x}",
+            header.tee_type
+        );
+        ensure!(
+            header.att_key_type == ATT_KEY_TYPE_ECDSA_P256,
+            "unsupported attestation key type {}",
+            header.att_key_type
+        );
+        let td_report = TdReportBody::parse(reader.take(TD_REPORT_LEN)?)?;
+        let signature_data_len = reader.u32()?;
+        let signature = TdxQuoteSignatureData::parse(reader.take(signature_data_len as usize)?)?;
+
+        Ok(Self {
+            header,
+            td_report,
+            signature_data_len,
+            signature,
+        })
+    }
+
+    /// The signed part of the quote, i.e. the header followed by the TD report body
+    pub fn signed_bytes(&self) -> Vec<u8> {
+        [self.header.to_bytes(), self.td_report.to_bytes()].concat()
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Wraps a base64 certificate body into PEM with 64-column lines
+    pub(crate) fn to_pem(body: &[u8]) -> Vec<u8> {
+        let mut pem = format!("{}\n", PEM_BEGIN_CERTIFICATE).into_bytes();
+        for line in body.chunks(64) {
+            pem.extend(line);
+            pem.push(b'\n');
+        }
+        pem.extend(format!("{}\n", PEM_END_CERTIFICATE).into_bytes());
+        pem
+    }
+
+    /// Assembles a v3 quote from its parts, the inverse of `Quote::parse`
+    pub(crate) fn build_quote(
+        isv_enclave_report: &EnclaveReportBody,
+        qe_report: &EnclaveReportBody,
+        qe_report_signature: &[u8; ECDSA_SIGNATURE_LEN],
+        cert_data_type: u16,
+        cert_data: &[u8],
+    ) -> Vec<u8> {
+        let header = QuoteHeader {
+            version: QUOTE_VERSION_3,
+            att_key_type: ATT_KEY_TYPE_ECDSA_P256,
+            ..Default::default()
+        };
+
+        let mut signature = vec![];
+        signature.extend([0; ECDSA_SIGNATURE_LEN]);
+        signature.extend([0; ECDSA_PUBKEY_LEN]);
+        signature.extend(qe_report.to_bytes());
+        signature.extend(qe_report_signature);
+        signature.extend(0u16.to_le_bytes());
+        signature.extend(cert_data_type.to_le_bytes());
+        signature.extend((cert_data.len() as u32).to_le_bytes());
+        signature.extend(cert_data);
+
+        let mut quote = header.to_bytes();
+        quote.extend(isv_enclave_report.to_bytes());
+        quote.extend((signature.len() as u32).to_le_bytes());
+        quote.extend(signature);
+        quote
+    }
+
+    /// Assembles a v4 TDX quote from its parts, the inverse of `TdxQuote::parse`
+    pub(crate) fn build_tdx_quote(
+        td_report: &TdReportBody,
+        qe_report: &EnclaveReportBody,
+        qe_report_signature: &[u8; ECDSA_SIGNATURE_LEN],
+        pck_cert_chain: &[u8],
+    ) -> Vec<u8> {
+        let header = QuoteHeader {
+            version: QUOTE_VERSION_4,
+            att_key_type: ATT_KEY_TYPE_ECDSA_P256,
+            tee_type: TEE_TYPE_TDX,
+            ..Default::default()
+        };
+
+        let mut qe_report_cert_data = qe_report.to_bytes();
+        qe_report_cert_data.extend(qe_report_signature);
+        qe_report_cert_data.extend(0u16.to_le_bytes());
+        qe_report_cert_data.extend(CERT_DATA_TYPE_PCK_CERT_CHAIN.to_le_bytes());
+        qe_report_cert_data.extend((pck_cert_chain.len() as u32).to_le_bytes());
+        qe_report_cert_data.extend(pck_cert_chain);
+
+        let mut signature = vec![];
+        signature.extend([0; ECDSA_SIGNATURE_LEN]);
+        signature.extend([0; ECDSA_PUBKEY_LEN]);
+        signature.extend(CERT_DATA_TYPE_QE_REPORT_CERT_DATA.to_le_bytes());
+        signature.extend((qe_report_cert_data.len() as u32).to_le_bytes());
+        signature.extend(qe_report_cert_data);
+
+        let mut quote = header.to_bytes();
+        quote.extend(td_report.to_bytes());
+        quote.extend((signature.len() as u32).to_le_bytes());
+        quote.extend(signature);
+        quote
+    }
+
+    #[test]
+    fn test_parse_quote() {
+        let qe_report = EnclaveReportBody {
+            isv_svn: 9,
+            mr_signer: [0x8c; 32],
+            ..Default::default()
+        };
+        let raw = build_quote(
+            &EnclaveReportBody::default(),
+            &qe_report,
+            &[7; 64],
+            5,
+            b"pem",
+        );
+
+        let quote = Quote::parse(&raw).unwrap();
+        assert_eq!(quote.header.version, QUOTE_VERSION_3);
+        assert_eq!(quote.signature.qe_report, qe_report);
+        assert_eq!(
+            quote.signature.qe_report.to_bytes().len(),
+            ENCLAVE_REPORT_LEN
+        );
+        assert_eq!(quote.signature.qe_report_signature, [7; 64]);
+        assert_eq!(quote.signature.certification_data.cert_data_type, 5);
+        assert_eq!(quote.signature.certification_data.cert_data, b"pem");
+
+        assert!(Quote::parse(&raw[..raw.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_parse_quote_rejects_tdx_tee_type() {
+        let mut raw = build_quote(
+            &EnclaveReportBody::default(),
+            &EnclaveReportBody::default(),
+            &[7; 64],
+            5,
+            b"pem",
+        );
+        raw[4..8].copy_from_slice(&TEE_TYPE_TDX.to_le_bytes());
+
+        assert!(Quote::parse(&raw).is_err());
+    }
+
+    #[test]
+    fn test_parse_quote_rejects_trailing_bytes() {
+        let mut raw = build_quote(
+            &EnclaveReportBody::default(),
+            &EnclaveReportBody::default(),
+            &[7; 64],
+            5,
+            b"pem",
+        );
+        assert!(Quote::parse(&raw).is_ok());
+        raw.push(0);
+
+        assert!(Quote::parse(&raw).is_err());
+    }
+
+    #[test]
+    fn test_parse_pck_cert_chain() {
+        let leaf = [b'A'; 1696];
+        let mut pem = [
+            to_pem(&leaf),
+            to_pem(b"SW50ZXJtZWRpYXRl"),
+            to_pem(b"Um9vdA=="),
+        ]
+        .concat();
+        pem.push(0);
+        let cert_data = CertificationData {
+            cert_data_type: CERT_DATA_TYPE_PCK_CERT_CHAIN,
+            cert_data: pem,
+        };
+
+        let chain = cert_data.pck_cert_chain().unwrap();
+        assert_eq!(chain.leaf, leaf);
+        assert_eq!(chain.intermediate, b"SW50ZXJtZWRpYXRl");
+        assert_eq!(chain.root, b"Um9vdA==");
+        // the PEM certificates are kept as they appear in the chain, without the final newline
+        let root_pem = to_pem(b"Um9vdA==");
+        assert_eq!(chain.root_pem, root_pem[..root_pem.len() - 1]);
+
+        assert!(PckCertChain::parse(&to_pem(&leaf)).is_err());
+    }
+
+    #[test]
+    fn test_parse_tdx_quote() {
+        let td_report = TdReportBody {
+            mr_td: [1; 48],
+            rt_mr: [[2; 48], [3; 48], [4; 48], [5; 48]],
+            report_data: [6; 64],
+            ..Default::default()
+        };
+        let pem = [
+            to_pem(b"TGVhZg=="),
+            to_pem(b"SW50ZXJtZWRpYXRl"),
+            to_pem(b"Um9vdA=="),
+        ]
+        .concat();
+        let raw = build_tdx_quote(&td_report, &EnclaveReportBody::default(), &[7; 64], &pem);
+
+        let quote = TdxQuote::parse(&raw).unwrap();
+        assert_eq!(quote.header.tee_type, TEE_TYPE_TDX);
+        assert_eq!(quote.td_report, td_report);
+        assert_eq!(
+            quote.signed_bytes(),
+            raw[..QUOTE_HEADER_LEN + TD_REPORT_LEN]
+        );
+
+        let qe_report_cert_data = &quote.signature.qe_report_certification_data;
+        assert_eq!(qe_report_cert_data.qe_report_signature, [7; 64]);
+        let chain = qe_report_cert_data
+            .certification_data
+            .pck_cert_chain()
+            .unwrap();
+        assert_eq!(chain.leaf, b"TGVhZg==");
+
+        // a v3 SGX quote is not a TDX quote
+        let sgx = build_quote(
+            &EnclaveReportBody::default(),
+            &EnclaveReportBody::default(),
+            &[0; 64],
+            5,
+            &pem,
+        );
+        assert!(TdxQuote::parse(&sgx).is_err());
+    }
+}
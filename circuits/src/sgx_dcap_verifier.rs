@@ -1,22 +1,18 @@
 #![allow(non_snake_case)]
 // use halo2::halo2curves::bn256::G1Affine;
-use base64::{engine::general_purpose, Engine};
-use halo2_base::utils::modulus;
 use halo2_base::utils::PrimeField;
+use halo2_base::utils::{fe_to_biguint, modulus};
 use halo2_base::{
     gates::range::RangeStrategy::Vertical,
-    gates::{range::RangeConfig, GateInstructions},
+    gates::{flex_gate::FlexGateConfig, range::RangeConfig, GateInstructions, RangeInstructions},
     halo2_proofs::{
-        circuit::{AssignedCell, Layouter, Region, SimpleFloorPlanner, Value},
-        halo2curves::{
-            secp256r1::{Fp, Fq, Secp256r1Affine},
-            CurveAffine,
-        },
-        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector},
-        poly::Rotation,
+        arithmetic::CurveAffine,
+        circuit::{Cell, Layouter, Region, SimpleFloorPlanner, Value},
+        halo2curves::secp256r1::{Fp, Fq, Secp256r1Affine},
+        plonk::{Circuit, Column, ConstraintSystem, Error, Instance},
     },
     utils::biguint_to_fe,
-    AssignedValue, QuantumCell, SKIP_FIRST_PASS,
+    AssignedValue, Context, QuantumCell, SKIP_FIRST_PASS,
 };
 use halo2_dynamic_sha256::*;
 use halo2_ecc::{
@@ -27,325 +23,2672 @@ use halo2_ecc::{
     },
 };
 use num_bigint::BigUint;
-use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::env::var;
-use std::fs::File;
-use std::{marker::PhantomData, vec};
-
-use crate::table::BitDecompositionTableConfig;
+use std::{marker::PhantomData, ops::Range, vec};
+
+use crate::allowlist::{Allowlist, AllowlistPolicy, AllowlistTarget, ALLOWLIST_DEPTH};
+use crate::base64_decode::{Base64DecodeChip, Base64DecodeConfig, BASE64_MAX_ENCODED_LEN};
+use crate::collateral::{JsonField, SVN_KEY_SUFFIX, TCB_LEVEL_MIN_LEN, TCB_STATUS_KEY};
+use crate::crl::{NonRevocationProof, RevokedSerials, CRL_DEPTH, CRL_GAP_MAX_BYTE_SIZE};
+use crate::merkle::{MerkleProof, MERKLE_NODE_MAX_BYTE_SIZE};
+use crate::pem::PemChip;
+use crate::quote::{
+    PckCertChain, Quote, ATTRIBUTES_OFFSET, ENCLAVE_REPORT_LEN, ISV_PROD_ID_OFFSET, ISV_SVN_OFFSET,
+    MISC_SELECT_OFFSET, MR_ENCLAVE_OFFSET, MR_SIGNER_OFFSET, QUOTE_HEADER_LEN, REPORT_DATA_OFFSET,
+};
+use crate::tcb::{
+    QeIdentity, TcbLevels, TcbStatus, QE_IDENTITY_LEN, QE_IDENTITY_MAX_BYTE_SIZE, QE_TCB_LEVEL_LEN,
+    TCB_LEVELS_MAX_BYTE_SIZE, TCB_LEVEL_LEN,
+};
+use crate::x509::{
+    CertLayout, SgxExtensionLayout, CPU_SVN_COMPONENT, DAYS_BEFORE_MONTH, ECDSA_WITH_SHA256,
+    FMSPC_HEADER, PCE_ID_HEADER, PCE_SVN_COMPONENT, SECP256R1_SPKI_HEADER, SERIAL_NUMBER_LEN,
+    SGX_TCB_COMPONENTS, TBS_VERSION_HEADER, TCB_COMPONENT_OID_PREFIX, TCB_SEQUENCE_HEADER,
+};
 // use snark_verifier_sdk::CircuitExt;
 
-// Checks a regex of string len
-const SHAHASH_BASE64_STRING_LEN: usize = 1696;
-const BIT_DECOMPOSITION_ADVICE_COL_COUNT: usize = 12;
+// positions of the fields of a decoded certificate located by `parse_cert`, for a serial number
+// of 1 to 21 bytes and issuer and subject Names shorter than 128 bytes: the validity SEQUENCE,
+// the subject Name and the secp256r1 SubjectPublicKeyInfo
+pub(crate) const VALIDITY_SEARCH: Range<usize> = 30..178;
+pub(crate) const SUBJECT_SEARCH: Range<usize> = 62..214;
+pub(crate) const SPKI_SEARCH: Range<usize> = 64..343;
+// positions of the TCB sequence searched in the decoded leaf certificate, which fall within the
+// SGX extension of PCK certificates
+pub(crate) const SGX_TCB_SEARCH: Range<usize> = 576..832;
+// positions of the keys of the fields taken from the signed TCBInfo and QE Identity bodies, which
+// come before their TCB levels
+pub(crate) const JSON_FIELD_SEARCH: Range<usize> = 0..512;
+// max length of a validity SEQUENCE: two GeneralizedTimes
+const VALIDITY_MAX_LEN: usize = 36;
+// max length of a UTCTime or GeneralizedTime, header included
+const TIME_MAX_LEN: usize = 17;
+// bytes of a TCB component of the SGX extension in front of its INTEGER value: the SEQUENCE
+// header, the OID and the INTEGER header
+const TCB_COMPONENT_HEADER_LEN: usize = 17;
+// CPUSVN, PCEID and FMSPC following PCESVN in the SGX extension, headers included
+const SGX_TCB_TAIL_LEN: usize = 73;
+// max length of the TCB sequence walked by `sgx_extension`, from its OID to the end of FMSPC:
+// 16 SGX TCB components of up to 2 value bytes and PCESVN of up to 3
+const SGX_TCB_MAX_LEN: usize = TCB_SEQUENCE_HEADER.len()
+    + 2
+    + SGX_TCB_COMPONENTS * (TCB_COMPONENT_HEADER_LEN + 2)
+    + TCB_COMPONENT_HEADER_LEN
+    + 3
+    + SGX_TCB_TAIL_LEN;
+/// Number of big-endian bytes packed into each public instance
+pub const BYTES_PER_INSTANCE: usize = 16;
+// bits of the Unix timestamps compared in-circuit
+const TIMESTAMP_BITS: usize = 40;
+
+// Public instances of `SgxDcapVerifierCircuit`, given as the index of the first instance of each
+// field of the ISV enclave report. 32-byte measurements take 2 instances, the 16-byte attributes
+// 1 and the 64-byte report_data 4. ISVPRODID and ISVSVN are a single instance holding their value.
+// With an allowlist policy, the instances of the allowlisted measurement hold the allowlist root.
+pub const MR_ENCLAVE_INSTANCE: usize = 0;
+pub const MR_SIGNER_INSTANCE: usize = 2;
+pub const ISV_PROD_ID_INSTANCE: usize = 4;
+pub const ISV_SVN_INSTANCE: usize = 5;
+pub const ATTRIBUTES_INSTANCE: usize = 6;
+pub const REPORT_DATA_INSTANCE: usize = 7;
+// FMSPC and PCEID of the PCK leaf certificate follow, a single big-endian instance each
+pub const FMSPC_INSTANCE: usize = 11;
+pub const PCE_ID_INSTANCE: usize = 12;
+// then the SHA256 commitment to the TCB levels in 2 instances and the status code of the matched
+// TCB level, see `crate::tcb`
+pub const TCB_LEVELS_INSTANCE: usize = 13;
+pub const TCB_STATUS_INSTANCE: usize = 15;
+// then the SHA256 commitment to the QE Identity the QE report is checked against in 2 instances
+// and the status code of the QE's TCB level
+pub const QE_IDENTITY_INSTANCE: usize = 16;
+pub const QE_TCB_STATUS_INSTANCE: usize = 18;
+// then the root of the PCK CRL the PCK leaf certificate is not revoked by in 2 instances, see
+// `crate::crl`
+pub const PCK_CRL_INSTANCE: usize = 19;
+// and last the Unix timestamp within the validity period of the leaf, intermediate and root
+// certificates
+pub const TIMESTAMP_INSTANCE: usize = 21;
+pub const SGX_NUM_INSTANCES: usize = 22;
+/// Big-endian `x || y` public key of the Intel SGX Root CA, which issues the PCK Platform and
+/// Processor CAs
+pub const INTEL_SGX_ROOT_CA_PUBKEY: [u8; 64] = [
+    0x0b, 0xa9, 0xc4, 0xc0, 0xc0, 0xc8, 0x61, 0x93, 0xa3, 0xfe, 0x23, 0xd6, 0xb0, 0x2c, 0xda, 0x10,
+    0xa8, 0xbb, 0xd4, 0xe8, 0x8e, 0x48, 0xb4, 0x45, 0x85, 0x61, 0xa3, 0x6e, 0x70, 0x55, 0x25, 0xf5,
+    0x67, 0x91, 0x8e, 0x2e, 0xdc, 0x88, 0xe4, 0x0d, 0x86, 0x0b, 0xd0, 0xcc, 0x4e, 0xe2, 0x6a, 0xac,
+    0xc9, 0x88, 0xe5, 0x05, 0xa9, 0x53, 0x55, 0x8c, 0x45, 0x3f, 0x6b, 0x09, 0x04, 0xae, 0x73, 0x94,
+];
+
+#[derive(Debug, Clone)]
+pub(crate) struct AssignedDigest<F: PrimeField> {
+    pub input_len: AssignedValue<'static, F>,
+    // the message bytes followed by the sha256 padding, filling the slot of the message
+    pub input_bytes: Vec<AssignedValue<'static, F>>,
+    // big-endian
+    pub output_bytes: Vec<AssignedValue<'static, F>>,
+    // NOTE (xiaowentao) This value is msghash mod p where p is Fr's modulus 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
+    pub msghash_mod_by_fr_p: AssignedValue<'static, F>,
+}
+
+/// Fields of a decoded certificate located by `SgxDcapVerifierConfig::parse_cert`
+#[derive(Debug, Clone)]
+pub(crate) struct AssignedCert<'v, F: PrimeField> {
+    // length of the TBSCertificate, header included, which follows the 4-byte certificate header
+    pub tbs_len: AssignedValue<'v, F>,
+    // indicator of the length of the serial number INTEGER among 1 to `SERIAL_NUMBER_LEN + 1`
+    pub serial_len_indicator: Vec<AssignedValue<'v, F>>,
+    // Unix timestamps of the start and end of the validity period
+    pub not_before: AssignedValue<'v, F>,
+    pub not_after: AssignedValue<'v, F>,
+    // big-endian `x || y` of the subject's secp256r1 public key
+    pub pubkey: Vec<AssignedValue<'v, F>>,
+}
 
+/// Values of the SGX extension of a PCK leaf certificate taken by
+/// `SgxDcapVerifierConfig::sgx_extension`
 #[derive(Debug, Clone)]
-pub struct AssignedSgxDcapVerifierResult<F: PrimeField> {
-    pub encoded: Vec<AssignedCell<F, F>>,
-    pub decoded: Vec<AssignedCell<F, F>>,
+pub(crate) struct AssignedSgxExtension<'v, F: PrimeField> {
+    // the 16 SGX TCB components followed by PCESVN, each a single field element holding its value
+    pub tcb: Vec<AssignedValue<'v, F>>,
+    pub pce_id: Vec<AssignedValue<'v, F>>,
+    pub fmspc: Vec<AssignedValue<'v, F>>,
 }
 
-#[derive(Serialize, Deserialize)]
-struct CircuitParams {
-    strategy: FpStrategy,
-    degree: u32,
-    num_advice: usize,
-    num_lookup_advice: usize,
-    num_fixed: usize,
-    lookup_bits: usize,
-    limb_bits: usize,
-    num_limbs: usize,
+// parameters of the ECDSA chips of `DefaultCircuitParams`
+const DEFAULT_CIRCUIT_PARAMS: &str = include_str!("configs/ecdsa_circuit.config");
+// parameters of `SgxDcapCircuitParams`
+const SGX_DCAP_CIRCUIT_PARAMS: &str = include_str!("configs/sgx_dcap_circuit.config");
+
+/// Parameters of the non-native field chips verifying the ECDSA signatures, as in the JSON files
+/// of `src/configs`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitParams {
+    pub strategy: FpStrategy,
+    pub degree: u32,
+    pub num_advice: usize,
+    pub num_lookup_advice: usize,
+    pub num_fixed: usize,
+    pub lookup_bits: usize,
+    pub limb_bits: usize,
+    pub num_limbs: usize,
+}
+
+impl CircuitParams {
+    /// Parses and validates JSON parameters
+    pub fn from_json(json: &[u8]) -> anyhow::Result<Self> {
+        let params: Self = serde_json::from_slice(json)?;
+        params.validate()?;
+        Ok(params)
+    }
+
+    /// Checks that the limbs hold 256-bit field elements and that the lookup table fits in the
+    /// rows of the circuit
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.limb_bits * self.num_limbs >= 256,
+            "limb_bits * num_limbs should be at least 256, got {} * {}",
+            self.limb_bits,
+            self.num_limbs
+        );
+        anyhow::ensure!(
+            self.lookup_bits < self.degree as usize,
+            "lookup_bits should be less than degree, got {} and {}",
+            self.lookup_bits,
+            self.degree
+        );
+        Ok(())
+    }
+}
+
+impl Default for CircuitParams {
+    fn default() -> Self {
+        Self::from_json(DEFAULT_CIRCUIT_PARAMS.as_bytes())
+            .expect("built-in circuit params should be valid")
+    }
+}
+
+/// Parameters the circuits of this crate configure their ECDSA chips with, given as a type
+/// parameter since `Circuit::configure` takes no circuit to read them from. `Circuit::configure`
+/// panics if they are invalid, see `CircuitParams::validate`.
+pub trait CircuitParamsSpec: Clone + Default {
+    fn params() -> CircuitParams;
+
+    /// Big-endian `x || y` public key of the root CA the certificates must chain up to, a
+    /// constant of the circuit and so of its verifying key
+    fn root_ca_pubkey() -> [u8; 64] {
+        INTEL_SGX_ROOT_CA_PUBKEY
+    }
+}
+
+/// The parameters of `src/configs/ecdsa_circuit.config`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DefaultCircuitParams;
+
+impl CircuitParamsSpec for DefaultCircuitParams {
+    fn params() -> CircuitParams {
+        CircuitParams::default()
+    }
+}
+
+/// The parameters of `src/configs/sgx_dcap_circuit.config`, the default ones of
+/// `SgxDcapVerifierCircuit` and `TdxVerifierCircuit`. A quote takes 5 ECDSA verifications, of the
+/// 3 certificates, the QE report and the quote, some 2.5M cells, which 6 advice columns of 2^20
+/// rows hold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SgxDcapCircuitParams;
+
+impl CircuitParamsSpec for SgxDcapCircuitParams {
+    fn params() -> CircuitParams {
+        CircuitParams::from_json(SGX_DCAP_CIRCUIT_PARAMS.as_bytes())
+            .expect("built-in circuit params should be valid")
+    }
 }
 
 type FpChip<F> = FpConfig<F, Fp>;
 
-// Here we decompose a transition into 3-value lookups.
+/// Little-endian bytes of a 32-byte big-endian integer (as stored in quotes and certificates)
+/// assigned byte by byte. The value is unknown during key generation.
+fn assigned_le_bytes<F: PrimeField>(be: &[AssignedValue<'_, F>]) -> Value<[u8; 32]> {
+    be.iter()
+        .rev()
+        .map(|byte| byte.value.map(|byte| fe_to_biguint(&byte).to_bytes_le()[0]))
+        .collect::<Value<Vec<u8>>>()
+        .map(|le| {
+            le.try_into()
+                .unwrap_or_else(|_| panic!("failed to convert vec to array"))
+        })
+}
+
+// coffes for converting big-endian bytes to original bigint
+// load constants from [2^248, 2^240, ..., 2^8, 2^0]
+fn be_bytes_coffes<'a, F: PrimeField>() -> Vec<QuantumCell<'a, 'a, F>> {
+    (0..32)
+        .map(|i| QuantumCell::Constant(biguint_to_fe(&BigUint::from(2u32).pow(248 - 8 * i))))
+        .collect()
+}
+
+/// Coordinates of the point `(x, y)` if it is on the curve, else of the generator
+fn curve_point_or_generator<C: CurveAffine>(x: C::Base, y: C::Base) -> (C::Base, C::Base) {
+    let point = Option::<C>::from(C::from_xy(x, y)).unwrap_or_else(C::generator);
+    let coordinates = point.coordinates().unwrap();
+    (*coordinates.x(), *coordinates.y())
+}
+
+/// Drops the context lifetime of an assigned value so that it can be referenced from other regions
+fn detach<F: PrimeField>(x: &AssignedValue<'_, F>) -> AssignedValue<'static, F> {
+    AssignedValue {
+        cell: x.cell,
+        value: x.value,
+        row_offset: x.row_offset,
+        context_id: x.context_id,
+        _marker: PhantomData,
+    }
+}
+
+/// Packs big-endian bytes into field elements the same way `SgxDcapVerifierConfig::pack_bytes`
+/// does in-circuit
+pub fn pack_instances<F: PrimeField>(bytes: &[u8]) -> Vec<F> {
+    bytes
+        .chunks(BYTES_PER_INSTANCE)
+        .map(|chunk| {
+            chunk.iter().fold(F::zero(), |acc, byte| {
+                acc * F::from(256) + F::from(*byte as u64)
+            })
+        })
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct SgxDcapVerifierConfig<F: PrimeField> {
-    encoded_chars: Column<Advice>, // This is the raw ASCII character value -- like 'a' would be 97
-    bit_decompositions: [Column<Advice>; BIT_DECOMPOSITION_ADVICE_COL_COUNT],
-    decoded_chars: Column<Advice>, // This has a 1 char gap between each group of 3 chars
-    decoded_chars_without_gap: Column<Advice>,
-    bit_decomposition_table: BitDecompositionTableConfig<F>,
-    q_decode_selector: Selector,
+    base64: Base64DecodeConfig<F>,
     fp_config: FpConfig<F, Fp>,
     sha256_config: Sha256DynamicConfig<F>,
+    // parameters the ECDSA chips are configured with
+    params: CircuitParams,
+    // public key of the root CA, Intel's unless set `with_root_ca_pubkey`
+    root_ca_pubkey: [u8; 64],
+    // max sha256 input size of each digest, in order
+    max_byte_sizes: Vec<usize>,
+    instance: Column<Instance>,
     _marker: PhantomData<F>,
 }
 
-impl<F: PrimeField> SgxDcapVerifierConfig<F> {
-    const MAX_BYTE_SIZE1: usize = 128;
-    const MAX_BYTE_SIZE2: usize = 128;
-    const NUM_ADVICE: usize = 3;
-    const NUM_FIXED: usize = 1;
-    const NUM_LOOKUP_ADVICE: usize = 1;
-    const LOOKUP_BITS: usize = 16;
+impl<F: PrimeField> SgxDcapVerifierConfig<F> {
+    // max sha256 input sizes in order of digest: QE report, leaf TBSCertificate, intermediate
+    // TBSCertificate, signed quote body, attestation key || QE authentication data, TCB levels,
+    // QE Identity, root TBSCertificate, followed by the gap and inner nodes of the PCK CRL proof
+    // and the inner nodes of the optional allowlist proof
+    const MAX_BYTE_SIZES: [usize; 8] = [
+        448,
+        1280,
+        704,
+        704,
+        128,
+        TCB_LEVELS_MAX_BYTE_SIZE,
+        QE_IDENTITY_MAX_BYTE_SIZE,
+        704,
+    ];
+    const NUM_ADVICE: usize = 3;
+    const NUM_FIXED: usize = 1;
+    const NUM_LOOKUP_ADVICE: usize = 1;
+    const LOOKUP_BITS: usize = 16;
+
+    fn max_byte_sizes() -> Vec<usize> {
+        let mut max_byte_sizes = Self::MAX_BYTE_SIZES.to_vec();
+        max_byte_sizes.push(CRL_GAP_MAX_BYTE_SIZE);
+        max_byte_sizes.extend([MERKLE_NODE_MAX_BYTE_SIZE; CRL_DEPTH]);
+        max_byte_sizes.extend([MERKLE_NODE_MAX_BYTE_SIZE; ALLOWLIST_DEPTH]);
+        max_byte_sizes
+    }
+
+    /// Configures the chips of `SgxDcapVerifierCircuit` and `TdxVerifierCircuit` with the
+    /// given parameters of the ECDSA chips. Fails if they are invalid, see
+    /// `configure_with_params`.
+    pub fn configure(
+        meta: &mut ConstraintSystem<F>,
+        params: &CircuitParams,
+    ) -> anyhow::Result<Self> {
+        Self::configure_with_max_byte_sizes(meta, params, Self::max_byte_sizes())
+    }
+
+    /// Configures the chips with the given max sha256 input sizes, for circuits other than
+    /// `SgxDcapVerifierCircuit` and `TdxVerifierCircuit` that hash other messages
+    pub fn configure_with_max_byte_sizes(
+        meta: &mut ConstraintSystem<F>,
+        params: &CircuitParams,
+        max_byte_sizes: Vec<usize>,
+    ) -> anyhow::Result<Self> {
+        Self::configure_with_params(meta, params, max_byte_sizes, BASE64_MAX_ENCODED_LEN)
+    }
+
+    /// Configures the chips with the given parameters of the ECDSA chips, max sha256 input sizes
+    /// and max number of base64 characters of a certificate body, a multiple of 4. Every
+    /// certificate takes `max_encoded_len` rows, so that a single verifying key covers
+    /// certificates of any length up to it. Fails if the parameters are invalid, see
+    /// `CircuitParams::validate`, or if the sha256 lookup table does not fit in `degree`.
+    pub fn configure_with_params(
+        meta: &mut ConstraintSystem<F>,
+        params: &CircuitParams,
+        max_byte_sizes: Vec<usize>,
+        max_encoded_len: usize,
+    ) -> anyhow::Result<Self> {
+        params.validate()?;
+        anyhow::ensure!(
+            Self::LOOKUP_BITS < params.degree as usize,
+            "degree should be more than {}, got {}",
+            Self::LOOKUP_BITS,
+            params.degree
+        );
+        let base64 = Base64DecodeConfig::configure(meta, max_encoded_len);
+
+        let fp_config = FpConfig::<F, Fp>::configure(
+            meta,
+            params.strategy,
+            &[params.num_advice],
+            &[params.num_lookup_advice],
+            params.num_fixed,
+            params.lookup_bits,
+            params.limb_bits,
+            params.num_limbs,
+            modulus::<Fp>(),
+            0,
+            params.degree as usize,
+        );
+
+        let range_config = RangeConfig::configure(
+            meta,
+            Vertical,
+            &[Self::NUM_ADVICE],
+            &[Self::NUM_LOOKUP_ADVICE],
+            Self::NUM_FIXED,
+            Self::LOOKUP_BITS,
+            0,
+            params.degree as usize,
+        );
+        let instance = meta.instance_column();
+        meta.enable_equality(instance);
+        let sha256_config: Sha256DynamicConfig<F> =
+            Sha256DynamicConfig::configure(meta, max_byte_sizes.clone(), range_config, 8, 2, true);
+
+        Ok(Self {
+            base64,
+            fp_config,
+            sha256_config,
+            params: params.clone(),
+            root_ca_pubkey: INTEL_SGX_ROOT_CA_PUBKEY,
+            max_byte_sizes,
+            instance,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Makes the certificates chain up to the root CA of the big-endian `x || y` public key
+    /// rather than Intel's, see `CircuitParamsSpec::root_ca_pubkey`
+    pub fn with_root_ca_pubkey(mut self, root_ca_pubkey: [u8; 64]) -> Self {
+        self.root_ca_pubkey = root_ca_pubkey;
+        self
+    }
+
+    /// Parameters of the ECDSA chips
+    pub fn params(&self) -> &CircuitParams {
+        &self.params
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+        self.fp_config.range.load_lookup_table(layouter)?;
+        self.base64.load(layouter)?;
+        self.sha256_config.range().load_lookup_table(layouter)?;
+        self.sha256_config.load(layouter)
+    }
+
+    /// Hashes the messages with the dynamic sha256 chip in a single region. The i-th message uses
+    /// the i-th max sha256 input size of the config and is hashed in-circuit as a whole. Fails
+    /// if there are more messages than sizes or a padded message does not fit in its slot.
+    pub(crate) fn digest(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        messages: &[Vec<u8>],
+    ) -> Result<Vec<AssignedDigest<F>>, Error> {
+        // sha256 pads a message with at least 9 bytes
+        if messages.len() > self.max_byte_sizes.len()
+            || messages
+                .iter()
+                .zip(self.max_byte_sizes.iter())
+                .any(|(message, max_byte_size)| message.len() + 9 > *max_byte_size)
+        {
+            return Err(Error::Synthesis);
+        }
+
+        let mut sha256 = self.sha256_config.clone();
+        let range = sha256.range().clone();
+        let flex_config = range.gate.clone();
+        let coffes = be_bytes_coffes();
+
+        let mut first_pass = SKIP_FIRST_PASS;
+        let mut digests = vec![];
+        layouter.assign_region(
+            || "dynamic sha2",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let ctx = &mut sha256.new_context(region);
+                for message in messages {
+                    let result = sha256.digest(ctx, message, Some(0))?;
+                    let input_len = detach(&result.input_len);
+                    let input_bytes = result.input_bytes.iter().map(detach).collect();
+                    let output_bytes = result.output_bytes.iter().map(detach).collect();
+                    let hash_bytes: Vec<QuantumCell<'_, '_, F>> = result
+                        .output_bytes
+                        .into_iter()
+                        .map(|v| QuantumCell::ExistingOwned(v))
+                        .collect();
+                    // big-endian
+                    let (_, msghash) = flex_config.inner_product_simple_with_assignments(
+                        ctx,
+                        coffes.clone(),
+                        hash_bytes,
+                    );
+
+                    digests.push(AssignedDigest {
+                        input_len,
+                        input_bytes,
+                        output_bytes,
+                        msghash_mod_by_fr_p: detach(&msghash),
+                    });
+                }
+
+                range.finalize(ctx);
+                Ok(())
+            },
+        )?;
+        Ok(digests)
+    }
+
+    pub(crate) fn gate(&self) -> &FlexGateConfig<F> {
+        self.fp_config.gate()
+    }
+
+    pub(crate) fn new_context<'v>(&self, region: Region<'v, F>) -> Context<'v, F> {
+        self.fp_config.new_context(region)
+    }
+
+    // IMPORTANT: this copies cells to the lookup advice column to perform range check lookups
+    // This is not optional.
+    pub(crate) fn finalize(&self, ctx: &mut Context<'_, F>) {
+        self.fp_config.finalize(ctx);
+    }
+
+    /// Assigns host-side bytes in the ECDSA context, each range checked to 8 bits
+    pub(crate) fn load_bytes<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[u8],
+    ) -> Vec<AssignedValue<'v, F>> {
+        bytes
+            .iter()
+            .map(|byte| {
+                let assigned = self
+                    .fp_config
+                    .gate()
+                    .load_witness(ctx, Value::known(F::from(*byte as u64)));
+                self.fp_config.range.range_check(ctx, &assigned, 8);
+                assigned
+            })
+            .collect()
+    }
+
+    /// Assigns the root CA public key, Intel's by default, as fixed constants
+    pub(crate) fn load_root_ca_pubkey<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+    ) -> Vec<AssignedValue<'v, F>> {
+        self.root_ca_pubkey
+            .iter()
+            .map(|byte| {
+                self.fp_config
+                    .gate()
+                    .load_constant(ctx, F::from(*byte as u64))
+            })
+            .collect()
+    }
+
+    /// Base64 chip decoding strings in the region of `ctx`, see `Base64DecodeChip::new`
+    pub fn base64_chip(&self) -> Base64DecodeChip<'_, F> {
+        Base64DecodeChip::new(&self.base64, self.fp_config.gate())
+    }
+
+    /// Base64-decodes a PEM certificate body in the ECDSA context. The decoded length is
+    /// constrained to be the length of the DER certificate, so that the zero bytes past it are
+    /// not certificate data.
+    pub(crate) fn decode_cert<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        base64: &mut Base64DecodeChip<'_, F>,
+        encoded: &[u8],
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let (decoded, decoded_len) = base64.decode_with_len(ctx, encoded)?;
+        self.constrain_der_len(ctx, &decoded, &decoded_len);
+        Ok(decoded)
+    }
+
+    /// Same as `decode_cert`, for a PEM certificate as it appears in a certificate chain, see
+    /// `PemChip::decode`
+    pub(crate) fn decode_pem_cert<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        base64: &mut Base64DecodeChip<'_, F>,
+        pem: &[u8],
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let pem = PemChip::new(self.fp_config.gate()).decode(ctx, base64, pem)?;
+        self.constrain_der_len(ctx, &pem.decoded, &pem.decoded_len);
+        Ok(pem.decoded)
+    }
+
+    fn constrain_der_len<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        decoded: &[AssignedValue<'v, F>],
+        decoded_len: &AssignedValue<'v, F>,
+    ) {
+        let gate = self.fp_config.gate();
+        // a certificate is a SEQUENCE with a 2-byte length, following its 4-byte header
+        self.constrain_bytes(ctx, &decoded[..2], &[0x30, 0x82]);
+        let contents_len = gate.inner_product(
+            ctx,
+            decoded[2..4].iter().map(QuantumCell::Existing).collect(),
+            vec![
+                QuantumCell::Constant(F::from(256)),
+                QuantumCell::Constant(F::one()),
+            ],
+        );
+        let der_len = gate.add(
+            ctx,
+            QuantumCell::Existing(&contents_len),
+            QuantumCell::Constant(F::from(4)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(decoded_len),
+            QuantumCell::Existing(&der_len),
+        );
+    }
+
+    /// Verifies an ECDSA signature over a message hashed by `digest`. `pubkey` holds the
+    /// big-endian `x || y` bytes of the public key and `signature` the big-endian `r || s`.
+    pub(crate) fn ecdsa_verify<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        pubkey: &[AssignedValue<'v, F>],
+        signature: &[AssignedValue<'v, F>],
+        digest: &AssignedDigest<F>,
+    ) -> AssignedValue<'v, F> {
+        let fp_chip = &self.fp_config;
+        let coffes = be_bytes_coffes();
+
+        // NOTE (xiaowentao) All the values must be Little-Endian
+        // values out of the field's range are replaced by zero, which fails the checks of the
+        // native values below. So are zero scalars by one and points off the curve by the
+        // generator, which the ECC chips cannot invert or double, e.g. in the default witnesses
+        // of key generation.
+        let msghash = assigned_le_bytes(&digest.output_bytes)
+            .map(|le| Fq::from_bytes(&le).unwrap_or(Fq::from(0)));
+        let nonzero_scalar = |le: [u8; 32]| {
+            Option::<Fq>::from(Fq::from_bytes(&le))
+                .filter(|scalar| *scalar != Fq::from(0))
+                .unwrap_or(Fq::from(1))
+        };
+        let r_point = assigned_le_bytes(&signature[..32]).map(nonzero_scalar);
+        let s_point = assigned_le_bytes(&signature[32..64]).map(nonzero_scalar);
+        // signature bytes are big-endian as well, r and s are tied to them like the pubkey
+        let r_mod = fp_chip.gate().inner_product(
+            ctx,
+            signature[..32]
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            coffes.clone(),
+        );
+        let s_mod = fp_chip.gate().inner_product(
+            ctx,
+            signature[32..64]
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            coffes.clone(),
+        );
+
+        // note that pubkey bytes are big-endian and after inner_product
+        // they will be mod by Fr's modulus
+        let pubkey_x_mod = fp_chip.gate().inner_product(
+            ctx,
+            pubkey[..32]
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            coffes.clone(),
+        );
+        let pubkey_y_mod = fp_chip.gate().inner_product(
+            ctx,
+            pubkey[32..64]
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            coffes,
+        );
+        // big-endian => little-endian
+        let pubkey_x =
+            assigned_le_bytes(&pubkey[..32]).map(|le| Fp::from_bytes(&le).unwrap_or(Fp::from(0)));
+        let pubkey_y =
+            assigned_le_bytes(&pubkey[32..64]).map(|le| Fp::from_bytes(&le).unwrap_or(Fp::from(0)));
+        let (pubkey_x, pubkey_y) = pubkey_x
+            .zip(pubkey_y)
+            .map(|(x, y)| curve_point_or_generator::<Secp256r1Affine>(x, y))
+            .unzip();
+
+        let (r_assigned, s_assigned, m_assigned) = {
+            let fq_chip = FpConfig::<F, Fq>::construct(
+                fp_chip.range.clone(),
+                fp_chip.limb_bits,
+                fp_chip.num_limbs,
+                modulus::<Fq>(),
+            );
+
+            let m_assigned = fq_chip.load_private(ctx, FpConfig::<F, Fq>::fe_to_witness(&msghash));
+            let r_assigned = fq_chip.load_private(ctx, FpConfig::<F, Fq>::fe_to_witness(&r_point));
+            let s_assigned = fq_chip.load_private(ctx, FpConfig::<F, Fq>::fe_to_witness(&s_point));
+            (r_assigned, s_assigned, m_assigned)
+        };
+
+        // NOTE (xiaowentao) check msghash mod by Fr's p is actually the native value in m_assigned (CRTInteger)
+        // NOTE (xiaowentao) we need to ensure that the m_assigned is indeed the msghash outputed by sha256 circuit
+        fp_chip.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(m_assigned.native()),
+            QuantumCell::Existing(&digest.msghash_mod_by_fr_p),
+        );
+
+        fp_chip.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(r_assigned.native()),
+            QuantumCell::Existing(&r_mod),
+        );
+        fp_chip.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(s_assigned.native()),
+            QuantumCell::Existing(&s_mod),
+        );
+
+        let ecc_chip = EccChip::<F, FpChip<F>>::construct(fp_chip.clone());
+        let pk_assigned = ecc_chip.load_private(ctx, (pubkey_x, pubkey_y));
+
+        // checks like msghash_mod_by_fr_p
+        fp_chip.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(pk_assigned.x.native()),
+            QuantumCell::Existing(&pubkey_x_mod),
+        );
+        fp_chip.gate().assert_equal(
+            ctx,
+            QuantumCell::Existing(pk_assigned.y.native()),
+            QuantumCell::Existing(&pubkey_y_mod),
+        );
+
+        let ecdsa = ecdsa_verify_no_pubkey_check::<F, Fp, Fq, Secp256r1Affine>(
+            &ecc_chip.field_chip,
+            ctx,
+            &pk_assigned,
+            &r_assigned,
+            &s_assigned,
+            &m_assigned,
+            4,
+            4,
+        );
+
+        // check the ecdsa signature verification result is ok
+        fp_chip.gate().assert_is_const(ctx, &ecdsa, F::one());
+        ecdsa
+    }
+
+    /// Constrains the message hashed by `digest` to start with `bytes`
+    pub(crate) fn constrain_digest_prefix<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        digest: &AssignedDigest<F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) {
+        for (input_byte, byte) in digest.input_bytes.iter().zip(bytes.iter()) {
+            ctx.region
+                .constrain_equal(input_byte.cell(), byte.cell())
+                .unwrap();
+        }
+    }
+
+    /// Constrains the length of the message hashed by `digest`
+    pub(crate) fn constrain_digest_len(
+        &self,
+        ctx: &mut Context<'_, F>,
+        digest: &AssignedDigest<F>,
+        len: usize,
+    ) {
+        self.fp_config
+            .gate()
+            .assert_is_const(ctx, &digest.input_len, F::from(len as u64));
+    }
+
+    /// Constrains the message hashed by `digest` to be exactly `bytes`
+    pub(crate) fn constrain_digest_input<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        digest: &AssignedDigest<F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) {
+        self.constrain_digest_prefix(ctx, digest, bytes);
+        self.constrain_digest_len(ctx, digest, bytes.len());
+    }
+
+    /// Constrains the message hashed by `digest` to be the first `len` bytes of `bytes`, for a
+    /// witnessed `len` at most the length of the slot and of `bytes`
+    pub(crate) fn constrain_digest_window<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        digest: &AssignedDigest<F>,
+        bytes: &[AssignedValue<'v, F>],
+        len: &AssignedValue<'v, F>,
+    ) {
+        let gate = self.fp_config.gate();
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&digest.input_len),
+            QuantumCell::Existing(len),
+        );
+        let mask = self.prefix_mask(ctx, len, digest.input_bytes.len().min(bytes.len()));
+        for ((input_byte, byte), in_message) in digest.input_bytes.iter().zip(bytes).zip(mask) {
+            let diff = gate.sub(
+                ctx,
+                QuantumCell::Existing(input_byte),
+                QuantumCell::Existing(byte),
+            );
+            let masked = gate.mul(
+                ctx,
+                QuantumCell::Existing(&diff),
+                QuantumCell::Existing(&in_message),
+            );
+            gate.assert_is_const(ctx, &masked, F::zero());
+        }
+    }
+
+    /// Indicator of `idx` among `len` positions, see `GateInstructions::idx_to_indicator`. An
+    /// `idx` out of `0..len` has no position to indicate, which fails the proof.
+    pub(crate) fn indicator<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        idx: &AssignedValue<'v, F>,
+        len: usize,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        let indicator = gate.idx_to_indicator(ctx, QuantumCell::Existing(idx), len);
+        let count = gate.sum(ctx, indicator.iter().map(QuantumCell::Existing));
+        gate.assert_is_const(ctx, &count, F::one());
+        indicator
+    }
+
+    /// Indicator of the assigned `position` among the positions of `search`
+    fn position_indicator<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        position: &AssignedValue<'v, F>,
+        search: &Range<usize>,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let idx = self.fp_config.gate().sub(
+            ctx,
+            QuantumCell::Existing(position),
+            QuantumCell::Constant(F::from(search.start as u64)),
+        );
+        self.indicator(ctx, &idx, search.len())
+    }
+
+    /// Flags of the positions `0..n` that are less than `len`, which is at most `n`
+    pub(crate) fn prefix_mask<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        len: &AssignedValue<'v, F>,
+        n: usize,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        let indicator = self.indicator(ctx, len, n + 1);
+        // a position is in the prefix until the indicator of the length is met
+        let mut in_prefix = gate.load_constant(ctx, F::one());
+        let mut mask = vec![];
+        for flag in &indicator[..n] {
+            in_prefix = gate.sub(
+                ctx,
+                QuantumCell::Existing(&in_prefix),
+                QuantumCell::Existing(flag),
+            );
+            mask.push(in_prefix.clone());
+        }
+        mask
+    }
+
+    /// Selects the `len` bytes starting at the position of `indicator` among `search`. Bytes
+    /// past the end of `bytes` are zero.
+    pub(crate) fn select_window<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[AssignedValue<'v, F>],
+        indicator: &[AssignedValue<'v, F>],
+        search: Range<usize>,
+        len: usize,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        (0..len)
+            .map(|i| {
+                let candidates = search.clone().map(|position| {
+                    bytes
+                        .get(position + i)
+                        .map(QuantumCell::Existing)
+                        .unwrap_or(QuantumCell::Constant(F::zero()))
+                });
+                gate.select_by_indicator(ctx, candidates, indicator)
+            })
+            .collect()
+    }
+
+    /// Selects the `len` bytes after the constant `key` of a JSON field of `body`, whose key is
+    /// at the witnessed `offset` among `JSON_FIELD_SEARCH`, see `JsonField::offset`. Returns
+    /// the position of the selected bytes too.
+    pub(crate) fn json_field<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        body: &[AssignedValue<'v, F>],
+        offset: usize,
+        key: &str,
+        len: usize,
+    ) -> (AssignedValue<'v, F>, Vec<AssignedValue<'v, F>>) {
+        let gate = self.fp_config.gate();
+        let offset = gate.load_witness(ctx, Value::known(F::from(offset as u64)));
+        let indicator = self.position_indicator(ctx, &offset, &JSON_FIELD_SEARCH);
+        let mut field =
+            self.select_window(ctx, body, &indicator, JSON_FIELD_SEARCH, key.len() + len);
+        self.constrain_bytes(ctx, &field[..key.len()], key.as_bytes());
+        let position = gate.add(
+            ctx,
+            QuantumCell::Existing(&offset),
+            QuantumCell::Constant(F::from(key.len() as u64)),
+        );
+        (position, field.split_off(key.len()))
+    }
+
+    /// Takes the `len` characters of the JSON string after `key`, see `json_field`, up to its
+    /// closing quote
+    pub(crate) fn json_string_field<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        body: &[AssignedValue<'v, F>],
+        field: &JsonField,
+        key: &str,
+        len: usize,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let (_, mut value) = self.json_field(ctx, body, field.offset(), key, len + 1);
+        self.constrain_bytes(ctx, &value[len..], b"\"");
+        value.truncate(len);
+        value
+    }
+
+    /// Parses the JSON number after `key`, see `json_field`, of at most `max_digits` digits up
+    /// to its first non-digit
+    pub(crate) fn json_number<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        body: &[AssignedValue<'v, F>],
+        field: &JsonField,
+        key: &str,
+        max_digits: usize,
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let (_, chars) = self.json_field(ctx, body, field.offset(), key, max_digits + 1);
+        let mut in_number = gate.load_constant(ctx, F::one());
+        let mut value = gate.load_zero(ctx);
+        for (i, c) in chars.iter().enumerate() {
+            let digit = self.is_digit(ctx, c);
+            if i == 0 {
+                gate.assert_is_const(ctx, &digit, F::one());
+            }
+            in_number = gate.mul(
+                ctx,
+                QuantumCell::Existing(&in_number),
+                QuantumCell::Existing(&digit),
+            );
+            let shifted = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&value),
+                QuantumCell::Constant(F::from(10)),
+                QuantumCell::Existing(c),
+            );
+            let shifted = gate.sub(
+                ctx,
+                QuantumCell::Existing(&shifted),
+                QuantumCell::Constant(F::from(b'0' as u64)),
+            );
+            value = gate.select(
+                ctx,
+                QuantumCell::Existing(&shifted),
+                QuantumCell::Existing(&value),
+                QuantumCell::Existing(&in_number),
+            );
+        }
+        // the number ends within the selected chars
+        gate.assert_is_const(ctx, &in_number, F::zero());
+        value
+    }
+
+    /// Whether `byte` is an ASCII digit
+    fn is_digit<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        byte: &AssignedValue<'v, F>,
+    ) -> AssignedValue<'v, F> {
+        let range = &self.fp_config.range;
+        let below = range.is_less_than(
+            ctx,
+            QuantumCell::Existing(byte),
+            QuantumCell::Constant(F::from(b'0' as u64)),
+            8,
+        );
+        let up_to = range.is_less_than(
+            ctx,
+            QuantumCell::Existing(byte),
+            QuantumCell::Constant(F::from(b'9' as u64 + 1)),
+            8,
+        );
+        self.fp_config.gate().sub(
+            ctx,
+            QuantumCell::Existing(&up_to),
+            QuantumCell::Existing(&below),
+        )
+    }
+
+    /// Whether `byte` is one of the two constant `chars`
+    fn is_one_of<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        byte: &AssignedValue<'v, F>,
+        chars: [u8; 2],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let [a, b] = chars.map(|c| {
+            gate.sub(
+                ctx,
+                QuantumCell::Existing(byte),
+                QuantumCell::Constant(F::from(c as u64)),
+            )
+        });
+        let product = gate.mul(ctx, QuantumCell::Existing(&a), QuantumCell::Existing(&b));
+        gate.is_zero(ctx, &product)
+    }
+
+    /// Walks the TCB levels array whose `[` is at `array_start` of a whitespace-free JSON `body`
+    /// and constrains its levels to be the first `num_levels` of the encoded `levels`, the others
+    /// being zero levels of status `Unrecognized`. An encoded level is `level_len` bytes: its
+    /// numbers, one byte each but the last one of 2 big-endian bytes, then its status code, see
+    /// `TcbLevel::to_bytes` and `QeTcbLevel::to_bytes`.
+    ///
+    /// The body is read byte by byte from the array on, keeping track of strings and of the
+    /// depth, up to the `]` closing the array, which must be within the body. A level object must
+    /// hold `level_len - 2` numbers, each after a key ending with `SVN_KEY_SUFFIX` and the last
+    /// one after `last_key`, and a single `tcbStatus`. A number runs up to the first non-digit
+    /// and is packed with the others of its level, and the status string is packed into the
+    /// status name of the code of the level. A level object is at least `TCB_LEVEL_MIN_LEN`
+    /// bytes long, so at most one ends in a block of as many bytes, and the levels ending in the
+    /// blocks are compared in order with the encoded ones.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn json_tcb_levels<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        body: &[AssignedValue<'v, F>],
+        array_start: &AssignedValue<'v, F>,
+        last_key: &str,
+        levels: &[AssignedValue<'v, F>],
+        level_len: usize,
+        num_levels: &AssignedValue<'v, F>,
+    ) {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+        let numbers = level_len - 2;
+        let max_levels = levels.len() / level_len;
+        let pack = |bytes: &[u8], base: u64| {
+            bytes.iter().fold(F::zero(), |acc, byte| {
+                acc * F::from(base) + F::from(*byte as u64)
+            })
+        };
+        let status_names: Vec<F> = (0..=TcbStatus::Unrecognized.code())
+            .map(|code| pack(TcbStatus::from_code(code).unwrap().name().as_bytes(), 128))
+            .collect();
+        // the keys in front of a byte are read in windows of the bytes before it
+        let keys = [SVN_KEY_SUFFIX, last_key, TCB_STATUS_KEY];
+        let key_values = keys.map(|key| pack(key.as_bytes(), 256));
+        let key_shifts =
+            keys.map(|key| -(0..key.len()).fold(F::one(), |acc, _| acc * F::from(256)));
+        let mut windows = keys.map(|_| gate.load_zero(ctx));
+
+        let before_array = self.prefix_mask(ctx, array_start, body.len());
+        let zero = gate.load_zero(ctx);
+        let (mut closed, mut in_string, mut depth) = (zero.clone(), zero.clone(), zero.clone());
+        // the number being read, the count and packed value of the numbers of the level
+        let (mut in_number, mut number, mut count, mut packed) =
+            (zero.clone(), zero.clone(), zero.clone(), zero.clone());
+        // whether the status is being read, the count of statuses of the level and its name
+        let (mut in_status, mut statuses, mut name) = (zero.clone(), zero.clone(), zero);
+        // whether a level ends at the byte, its packed numbers and status name
+        let mut ends = vec![];
+        for (i, byte) in body.iter().enumerate() {
+            let started = gate.not(ctx, QuantumCell::Existing(&before_array[i]));
+            let open = gate.not(ctx, QuantumCell::Existing(&closed));
+            let live = gate.mul(
+                ctx,
+                QuantumCell::Existing(&started),
+                QuantumCell::Existing(&open),
+            );
+            let in_live_string = gate.mul(
+                ctx,
+                QuantumCell::Existing(&live),
+                QuantumCell::Existing(&in_string),
+            );
+            let outside = gate.sub(
+                ctx,
+                QuantumCell::Existing(&live),
+                QuantumCell::Existing(&in_live_string),
+            );
+            let [is_svn_key, is_last_key, is_status_key] = [0, 1, 2].map(|k| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(&windows[k]),
+                    QuantumCell::Constant(key_values[k]),
+                )
+            });
+
+            // quotes toggle strings, which hold no escapes in the signed bodies
+            let is_quote = gate.is_equal(
+                ctx,
+                QuantumCell::Existing(byte),
+                QuantumCell::Constant(F::from(b'"' as u64)),
+            );
+            let toggle = gate.mul(
+                ctx,
+                QuantumCell::Existing(&live),
+                QuantumCell::Existing(&is_quote),
+            );
+            let flip = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&in_string),
+                QuantumCell::Constant(-F::from(2)),
+                QuantumCell::Constant(F::one()),
+            );
+            in_string = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&toggle),
+                QuantumCell::Existing(&flip),
+                QuantumCell::Existing(&in_string),
+            );
+
+            // brackets outside of strings move the depth, the array is at depth 1
+            let opening = self.is_one_of(ctx, byte, [b'[', b'{']);
+            let closing = self.is_one_of(ctx, byte, [b']', b'}']);
+            let opens = gate.mul(
+                ctx,
+                QuantumCell::Existing(&outside),
+                QuantumCell::Existing(&opening),
+            );
+            let closes = gate.mul(
+                ctx,
+                QuantumCell::Existing(&outside),
+                QuantumCell::Existing(&closing),
+            );
+            let [in_array, in_level] = [1, 2].map(|d| {
+                gate.is_equal(
+                    ctx,
+                    QuantumCell::Existing(&depth),
+                    QuantumCell::Constant(F::from(d)),
+                )
+            });
+            let level_end = gate.mul(
+                ctx,
+                QuantumCell::Existing(&closes),
+                QuantumCell::Existing(&in_level),
+            );
+            let array_end = gate.mul(
+                ctx,
+                QuantumCell::Existing(&closes),
+                QuantumCell::Existing(&in_array),
+            );
+            let moved = gate.sub(
+                ctx,
+                QuantumCell::Existing(&opens),
+                QuantumCell::Existing(&closes),
+            );
+            depth = gate.add(
+                ctx,
+                QuantumCell::Existing(&depth),
+                QuantumCell::Existing(&moved),
+            );
+            closed = gate.add(
+                ctx,
+                QuantumCell::Existing(&closed),
+                QuantumCell::Existing(&array_end),
+            );
+
+            // a number starts after the key of its position in the level and ends at the first
+            // non-digit
+            let digit = self.is_digit(ctx, byte);
+            let is_number = gate.mul(
+                ctx,
+                QuantumCell::Existing(&outside),
+                QuantumCell::Existing(&digit),
+            );
+            let continued = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_number),
+                QuantumCell::Existing(&in_number),
+            );
+            let number_start = gate.sub(
+                ctx,
+                QuantumCell::Existing(&is_number),
+                QuantumCell::Existing(&continued),
+            );
+            let number_end = gate.sub(
+                ctx,
+                QuantumCell::Existing(&in_number),
+                QuantumCell::Existing(&continued),
+            );
+            let is_last = gate.is_equal(
+                ctx,
+                QuantumCell::Existing(&count),
+                QuantumCell::Constant(F::from(numbers as u64 - 1)),
+            );
+            let unkeyed = gate.not(ctx, QuantumCell::Existing(&is_svn_key));
+            let misplaced = gate.sub(
+                ctx,
+                QuantumCell::Existing(&is_last_key),
+                QuantumCell::Existing(&is_last),
+            );
+            for check in [unkeyed, misplaced] {
+                let failed = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&number_start),
+                    QuantumCell::Existing(&check),
+                );
+                gate.assert_is_const(ctx, &failed, F::zero());
+            }
+
+            // the last number of a level is 2 bytes, the others 1
+            let value = gate.mul(
+                ctx,
+                QuantumCell::Existing(&number_end),
+                QuantumCell::Existing(&number),
+            );
+            range.range_check(ctx, &value, 16);
+            let not_last = gate.not(ctx, QuantumCell::Existing(&is_last));
+            let byte_value = gate.mul(
+                ctx,
+                QuantumCell::Existing(&value),
+                QuantumCell::Existing(&not_last),
+            );
+            range.range_check(ctx, &byte_value, 8);
+            let base = gate.select(
+                ctx,
+                QuantumCell::Constant(F::from(1 << 16)),
+                QuantumCell::Constant(F::from(256)),
+                QuantumCell::Existing(&is_last),
+            );
+            let shifted = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&packed),
+                QuantumCell::Existing(&base),
+                QuantumCell::Existing(&number),
+            );
+            let packed_now = gate.select(
+                ctx,
+                QuantumCell::Existing(&shifted),
+                QuantumCell::Existing(&packed),
+                QuantumCell::Existing(&number_end),
+            );
+            let count_now = gate.add(
+                ctx,
+                QuantumCell::Existing(&count),
+                QuantumCell::Existing(&number_end),
+            );
+            let digits = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&number),
+                QuantumCell::Constant(F::from(10)),
+                QuantumCell::Existing(byte),
+            );
+            let digits = gate.sub(
+                ctx,
+                QuantumCell::Existing(&digits),
+                QuantumCell::Constant(F::from(b'0' as u64)),
+            );
+            number = gate.mul(
+                ctx,
+                QuantumCell::Existing(&is_number),
+                QuantumCell::Existing(&digits),
+            );
+            in_number = is_number;
+
+            // the status string after its key is packed up to its closing quote
+            let status_start = gate.mul(
+                ctx,
+                QuantumCell::Existing(&live),
+                QuantumCell::Existing(&is_status_key),
+            );
+            let in_status_now = gate.add(
+                ctx,
+                QuantumCell::Existing(&in_status),
+                QuantumCell::Existing(&status_start),
+            );
+            let status_end = gate.mul(
+                ctx,
+                QuantumCell::Existing(&in_status_now),
+                QuantumCell::Existing(&is_quote),
+            );
+            in_status = gate.sub(
+                ctx,
+                QuantumCell::Existing(&in_status_now),
+                QuantumCell::Existing(&status_end),
+            );
+            let status_char = gate.mul(
+                ctx,
+                QuantumCell::Existing(&in_status),
+                QuantumCell::Existing(byte),
+            );
+            range.range_check(ctx, &status_char, 7);
+            let shifted_name = gate.mul_add(
+                ctx,
+                QuantumCell::Existing(&name),
+                QuantumCell::Constant(F::from(128)),
+                QuantumCell::Existing(byte),
+            );
+            let name_now = gate.select(
+                ctx,
+                QuantumCell::Existing(&shifted_name),
+                QuantumCell::Existing(&name),
+                QuantumCell::Existing(&in_status),
+            );
+            let statuses_now = gate.add(
+                ctx,
+                QuantumCell::Existing(&statuses),
+                QuantumCell::Existing(&status_end),
+            );
+
+            // a level ends with all its numbers and a single status
+            for (value, expected) in [(&count_now, numbers as u64), (&statuses_now, 1)] {
+                let diff = gate.sub(
+                    ctx,
+                    QuantumCell::Existing(value),
+                    QuantumCell::Constant(F::from(expected)),
+                );
+                let failed = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&level_end),
+                    QuantumCell::Existing(&diff),
+                );
+                gate.assert_is_const(ctx, &failed, F::zero());
+            }
+            let not_end = gate.not(ctx, QuantumCell::Existing(&level_end));
+            [packed, count, name, statuses] = [&packed_now, &count_now, &name_now, &statuses_now]
+                .map(|value| {
+                    gate.mul(
+                        ctx,
+                        QuantumCell::Existing(value),
+                        QuantumCell::Existing(&not_end),
+                    )
+                });
+            ends.push((level_end, packed_now, name_now));
+
+            for (k, key) in keys.iter().enumerate() {
+                let shifted = gate.mul_add(
+                    ctx,
+                    QuantumCell::Existing(&windows[k]),
+                    QuantumCell::Constant(F::from(256)),
+                    QuantumCell::Existing(byte),
+                );
+                windows[k] = match i.checked_sub(key.len()) {
+                    Some(dropped) => gate.mul_add(
+                        ctx,
+                        QuantumCell::Existing(&body[dropped]),
+                        QuantumCell::Constant(key_shifts[k]),
+                        QuantumCell::Existing(&shifted),
+                    ),
+                    None => shifted,
+                };
+            }
+        }
+        gate.assert_is_const(ctx, &closed, F::one());
+
+        // levels past `num_levels` are zero levels of status `Unrecognized`
+        let in_list = self.prefix_mask(ctx, num_levels, max_levels);
+        let unrecognized = F::from(TcbStatus::Unrecognized.code() as u64);
+        let mut expected = vec![];
+        for (level, listed) in levels.chunks(level_len).zip(in_list.iter()) {
+            let unlisted = gate.not(ctx, QuantumCell::Existing(listed));
+            let code = gate.sub(
+                ctx,
+                QuantumCell::Existing(&level[level_len - 1]),
+                QuantumCell::Constant(unrecognized),
+            );
+            for value in level[..level_len - 1].iter().chain([&code]) {
+                let padding = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&unlisted),
+                    QuantumCell::Existing(value),
+                );
+                gate.assert_is_const(ctx, &padding, F::zero());
+            }
+            expected.push((
+                self.pack_be(ctx, &level[..level_len - 1]),
+                level[level_len - 1].clone(),
+            ));
+        }
+
+        // the levels of the body in order are the listed ones
+        let mut position = gate.load_zero(ctx);
+        for block in ends.chunks(TCB_LEVEL_MIN_LEN) {
+            let ended = gate.sum(
+                ctx,
+                block.iter().map(|(end, _, _)| QuantumCell::Existing(end)),
+            );
+            gate.assert_bit(ctx, &ended);
+            let [walked_numbers, walked_name] = [0, 1].map(|field| {
+                gate.inner_product(
+                    ctx,
+                    block
+                        .iter()
+                        .map(|(end, _, _)| QuantumCell::Existing(end))
+                        .collect::<Vec<QuantumCell<F>>>(),
+                    block
+                        .iter()
+                        .map(|(_, packed, name)| QuantumCell::Existing([packed, name][field]))
+                        .collect::<Vec<QuantumCell<F>>>(),
+                )
+            });
+            let indicator = self.indicator(ctx, &position, max_levels + 1);
+            let past_end = gate.mul(
+                ctx,
+                QuantumCell::Existing(&ended),
+                QuantumCell::Existing(&indicator[max_levels]),
+            );
+            gate.assert_is_const(ctx, &past_end, F::zero());
+            let expected_packed = gate.select_by_indicator(
+                ctx,
+                expected
+                    .iter()
+                    .map(|(packed, _)| QuantumCell::Existing(packed))
+                    .chain([QuantumCell::Constant(F::zero())]),
+                &indicator,
+            );
+            let code = gate.select_by_indicator(
+                ctx,
+                expected
+                    .iter()
+                    .map(|(_, code)| QuantumCell::Existing(code))
+                    .chain([QuantumCell::Constant(unrecognized)]),
+                &indicator,
+            );
+            let code_indicator = self.indicator(ctx, &code, status_names.len());
+            let expected_name = gate.select_by_indicator(
+                ctx,
+                status_names.iter().map(|name| QuantumCell::Constant(*name)),
+                &code_indicator,
+            );
+            for (walked, listed) in [
+                (walked_numbers, expected_packed),
+                (walked_name, expected_name),
+            ] {
+                let diff = gate.sub(
+                    ctx,
+                    QuantumCell::Existing(&walked),
+                    QuantumCell::Existing(&listed),
+                );
+                let failed = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&ended),
+                    QuantumCell::Existing(&diff),
+                );
+                gate.assert_is_const(ctx, &failed, F::zero());
+            }
+            position = gate.add(
+                ctx,
+                QuantumCell::Existing(&position),
+                QuantumCell::Existing(&ended),
+            );
+        }
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&position),
+            QuantumCell::Existing(num_levels),
+        );
+    }
+
+    /// Binds the attestation key to the QE report: the first 32 bytes of its `report_data` must
+    /// be SHA256(attestation_key || qe_auth_data). The QE authentication data has a variable
+    /// length, bounded by its sha256 slot.
+    pub(crate) fn constrain_qe_report_data<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        qe_report_digest: &AssignedDigest<F>,
+        attestation_key: &[AssignedValue<'v, F>],
+        attestation_key_digest: &AssignedDigest<F>,
+    ) {
+        let gate = self.fp_config.gate();
+
+        // the hashed message is the attestation key followed by at least 0 bytes of auth data
+        self.constrain_digest_prefix(ctx, attestation_key_digest, attestation_key);
+        let qe_auth_data_len = gate.sub(
+            ctx,
+            QuantumCell::Existing(&attestation_key_digest.input_len),
+            QuantumCell::Constant(F::from(attestation_key.len() as u64)),
+        );
+        self.fp_config.range.range_check(ctx, &qe_auth_data_len, 16);
+
+        for (report_data_byte, hash_byte) in qe_report_digest.input_bytes
+            [REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + 32]
+            .iter()
+            .zip(attestation_key_digest.output_bytes.iter())
+        {
+            ctx.region
+                .constrain_equal(report_data_byte.cell(), hash_byte.cell())
+                .unwrap();
+        }
+    }
+
+    /// Verifies the issuer's signature of a decoded certificate located by `parse_cert`:
+    /// `tbs_digest` must be the digest of the certificate's TBSCertificate and `issuer_pubkey`
+    /// the big-endian `x || y` public key of the issuer. The big-endian `r || s` `signature`,
+    /// see `CertLayout::signature`, is a witness like any other since it signs the TBSCertificate.
+    pub(crate) fn verify_cert_signature<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert: &[AssignedValue<'v, F>],
+        parsed: &AssignedCert<'v, F>,
+        signature: &[u8],
+        issuer_pubkey: &[AssignedValue<'v, F>],
+        tbs_digest: &AssignedDigest<F>,
+    ) -> AssignedValue<'v, F> {
+        // the TBSCertificate follows the 4-byte certificate header
+        self.constrain_digest_window(ctx, tbs_digest, &cert[4..], &parsed.tbs_len);
+        let signature = self.load_bytes(ctx, signature);
+        self.ecdsa_verify(ctx, issuer_pubkey, &signature, tbs_digest)
+    }
+
+    /// Verifies a collateral body signed by the TCB Signing certificate, given as its base64
+    /// body and the signature of its issuer, Intel's root CA. `tbs_digest` and `body_digest` are
+    /// the digests of the certificate's TBSCertificate and of the body. Returns the assigned
+    /// body, zero-padded to the slot of `body_digest`, whose length is the digest's input length.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn verify_signed_collateral<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        base64: &mut Base64DecodeChip<'_, F>,
+        tcb_signing_cert: &[u8],
+        cert_signature: &[u8],
+        tbs_digest: &AssignedDigest<F>,
+        body: &[u8],
+        signature: &[u8],
+        body_digest: &AssignedDigest<F>,
+    ) -> Result<Vec<AssignedValue<'v, F>>, Error> {
+        let cert = self.decode_cert(ctx, base64, tcb_signing_cert)?;
+        let parsed = self.parse_cert(ctx, &cert);
+        let root_ca_pubkey = self.load_root_ca_pubkey(ctx);
+        self.verify_cert_signature(
+            ctx,
+            &cert,
+            &parsed,
+            cert_signature,
+            &root_ca_pubkey,
+            tbs_digest,
+        );
+
+        // every body takes the whole slot, so that its length does not fix the circuit
+        let mut padded = body.to_vec();
+        padded.resize(body_digest.input_bytes.len(), 0);
+        let padded = self.load_bytes(ctx, &padded);
+        let body_len = self
+            .fp_config
+            .gate()
+            .load_witness(ctx, Value::known(F::from(body.len() as u64)));
+        self.constrain_digest_window(ctx, body_digest, &padded, &body_len);
+        let signature = self.load_bytes(ctx, signature);
+        self.ecdsa_verify(ctx, &parsed.pubkey, &signature, body_digest);
+        Ok(padded)
+    }
+
+    /// Computes the Merkle root of `leaf` along `proof`. `digests` are the digests of the
+    /// `MerkleProof::messages`, whose inputs are constrained to the `left || right` nodes.
+    pub(crate) fn merkle_root<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        leaf: &[AssignedValue<'v, F>],
+        proof: &MerkleProof,
+        digests: &[AssignedDigest<F>],
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        let mut node = leaf.to_vec();
+        for (level, (sibling, digest)) in proof.siblings.iter().zip(digests.iter()).enumerate() {
+            let is_right =
+                gate.load_witness(ctx, Value::known(F::from(proof.is_right(level) as u64)));
+            gate.assert_bit(ctx, &is_right);
+            let sibling = self.load_bytes(ctx, sibling);
+
+            let mut left = vec![];
+            let mut right = vec![];
+            for (node_byte, sibling_byte) in node.iter().zip(sibling.iter()) {
+                left.push(gate.select(
+                    ctx,
+                    QuantumCell::Existing(sibling_byte),
+                    QuantumCell::Existing(node_byte),
+                    QuantumCell::Existing(&is_right),
+                ));
+                right.push(gate.select(
+                    ctx,
+                    QuantumCell::Existing(node_byte),
+                    QuantumCell::Existing(sibling_byte),
+                    QuantumCell::Existing(&is_right),
+                ));
+            }
+            left.extend(right);
+            self.constrain_digest_input(ctx, digest, &left);
+            node = digest.output_bytes.to_vec();
+        }
+        node
+    }
+
+    /// Locates the fields of a decoded certificate in-circuit from its DER lengths: the serial
+    /// number follows the version, then come the ecdsa-with-SHA256 signature algorithm, the
+    /// issuer, the validity, the subject and the secp256r1 SubjectPublicKeyInfo. Each field is
+    /// selected at the offset the lengths in front of it give, among the positions it can take,
+    /// see `VALIDITY_SEARCH`, so that the lengths do not fix the circuit. Names must be shorter
+    /// than 128 bytes.
+    pub(crate) fn parse_cert<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert: &[AssignedValue<'v, F>],
+    ) -> AssignedCert<'v, F> {
+        let gate = self.fp_config.gate();
+
+        // the TBSCertificate is a SEQUENCE with a 2-byte length, starting with the version and
+        // the serial number, at most 21 bytes long with its sign byte
+        self.constrain_bytes(ctx, &cert[4..6], &[0x30, 0x82]);
+        let tbs_len = gate.inner_product(
+            ctx,
+            vec![
+                QuantumCell::Existing(&cert[6]),
+                QuantumCell::Existing(&cert[7]),
+                QuantumCell::Constant(F::one()),
+            ],
+            [256, 1, 4]
+                .map(|coeff| QuantumCell::Constant(F::from(coeff)))
+                .to_vec(),
+        );
+        self.constrain_bytes(ctx, &cert[8..14], &TBS_VERSION_HEADER);
+        let serial_len = &cert[14];
+        let serial_index = gate.sub(
+            ctx,
+            QuantumCell::Existing(serial_len),
+            QuantumCell::Constant(F::one()),
+        );
+        let serial_len_indicator = self.indicator(ctx, &serial_index, SERIAL_NUMBER_LEN + 1);
+
+        // the signature algorithm and the issuer follow the serial number
+        let header = self.select_window(
+            ctx,
+            cert,
+            &serial_len_indicator,
+            16..17 + SERIAL_NUMBER_LEN,
+            ECDSA_WITH_SHA256.len() + 2,
+        );
+        self.constrain_bytes(ctx, &header[..ECDSA_WITH_SHA256.len()], &ECDSA_WITH_SHA256);
+        let issuer_len = self.name_len(ctx, &header[ECDSA_WITH_SHA256.len()..]);
+
+        let validity_start = gate.sum(
+            ctx,
+            [
+                QuantumCell::Existing(serial_len),
+                QuantumCell::Existing(&issuer_len),
+                QuantumCell::Constant(F::from((15 + header.len()) as u64)),
+            ],
+        );
+        let indicator = self.position_indicator(ctx, &validity_start, &VALIDITY_SEARCH);
+        let validity =
+            self.select_window(ctx, cert, &indicator, VALIDITY_SEARCH, VALIDITY_MAX_LEN);
+        let (not_before, not_after, validity_len) = self.parse_validity(ctx, &validity);
+
+        let subject_start = gate.sum(
+            ctx,
+            [
+                QuantumCell::Existing(&validity_start),
+                QuantumCell::Existing(&validity_len),
+                QuantumCell::Constant(F::from(2)),
+            ],
+        );
+        let indicator = self.position_indicator(ctx, &subject_start, &SUBJECT_SEARCH);
+        let subject = self.select_window(ctx, cert, &indicator, SUBJECT_SEARCH, 2);
+        let subject_len = self.name_len(ctx, &subject);
+
+        let spki_start = gate.sum(
+            ctx,
+            [
+                QuantumCell::Existing(&subject_start),
+                QuantumCell::Existing(&subject_len),
+                QuantumCell::Constant(F::from(2)),
+            ],
+        );
+        let indicator = self.position_indicator(ctx, &spki_start, &SPKI_SEARCH);
+        let spki = self.select_window(
+            ctx,
+            cert,
+            &indicator,
+            SPKI_SEARCH,
+            SECP256R1_SPKI_HEADER.len() + 64,
+        );
+        self.constrain_bytes(
+            ctx,
+            &spki[..SECP256R1_SPKI_HEADER.len()],
+            &SECP256R1_SPKI_HEADER,
+        );
+        let spki_end = gate.add(
+            ctx,
+            QuantumCell::Existing(&spki_start),
+            QuantumCell::Constant(F::from(spki.len() as u64)),
+        );
+        self.check_within_tbs(ctx, &spki_end, &tbs_len);
+
+        AssignedCert {
+            tbs_len,
+            serial_len_indicator,
+            not_before,
+            not_after,
+            pubkey: spki[SECP256R1_SPKI_HEADER.len()..].to_vec(),
+        }
+    }
+
+    /// Constrains `end` to be at most the end of the TBSCertificate of `tbs_len` bytes, i.e. the
+    /// bytes before it to be signed
+    fn check_within_tbs<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        end: &AssignedValue<'v, F>,
+        tbs_len: &AssignedValue<'v, F>,
+    ) {
+        let tbs_end = self.fp_config.gate().add(
+            ctx,
+            QuantumCell::Existing(tbs_len),
+            QuantumCell::Constant(F::from(5)),
+        );
+        self.fp_config.range.check_less_than(
+            ctx,
+            QuantumCell::Existing(end),
+            QuantumCell::Existing(&tbs_end),
+            16,
+        );
+    }
+
+    /// Length of the contents of the Name whose 2-byte header is `header`
+    fn name_len<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        header: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        self.fp_config
+            .gate()
+            .assert_is_const(ctx, &header[0], F::from(0x30));
+        // a single length byte
+        self.fp_config
+            .range
+            .check_less_than_safe(ctx, &header[1], 0x80);
+        header[1].clone()
+    }
+
+    /// Parses the validity SEQUENCE at the start of `validity` into the Unix timestamps of its
+    /// notBefore and notAfter, followed by the length of its contents
+    fn parse_validity<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        validity: &[AssignedValue<'v, F>],
+    ) -> (AssignedValue<'v, F>, AssignedValue<'v, F>, AssignedValue<'v, F>) {
+        let gate = self.fp_config.gate();
+        gate.assert_is_const(ctx, &validity[0], F::from(0x30));
+        let (not_before, not_before_is_generalized) =
+            self.parse_time(ctx, &validity[2..2 + TIME_MAX_LEN]);
+        // notAfter follows notBefore, 2 bytes further after a GeneralizedTime
+        let not_after: Vec<AssignedValue<'v, F>> = (0..TIME_MAX_LEN)
+            .map(|i| {
+                gate.select(
+                    ctx,
+                    QuantumCell::Existing(&validity[TIME_MAX_LEN + 2 + i]),
+                    QuantumCell::Existing(&validity[TIME_MAX_LEN + i]),
+                    QuantumCell::Existing(&not_before_is_generalized),
+                )
+            })
+            .collect();
+        let (not_after, not_after_is_generalized) = self.parse_time(ctx, &not_after);
+
+        let validity_len = gate.inner_product(
+            ctx,
+            vec![
+                QuantumCell::Existing(&not_before_is_generalized),
+                QuantumCell::Existing(&not_after_is_generalized),
+                QuantumCell::Constant(F::one()),
+            ],
+            [2, 2, 2 * (TIME_MAX_LEN - 2)]
+                .map(|coeff| QuantumCell::Constant(F::from(coeff as u64)))
+                .to_vec(),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&validity[1]),
+            QuantumCell::Existing(&validity_len),
+        );
+        (not_before, not_after, validity_len)
+    }
+
+    /// Parses the UTCTime `YYMMDDhhmmssZ` or GeneralizedTime `YYYYMMDDhhmmssZ` at the start of
+    /// `time` into a Unix timestamp, followed by whether it is a GeneralizedTime
+    fn parse_time<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        time: &[AssignedValue<'v, F>],
+    ) -> (AssignedValue<'v, F>, AssignedValue<'v, F>) {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+
+        // tag 0x17 or 0x18, and a length of 13 or 15
+        let is_generalized = gate.sub(
+            ctx,
+            QuantumCell::Existing(&time[0]),
+            QuantumCell::Constant(F::from(0x17)),
+        );
+        gate.assert_bit(ctx, &is_generalized);
+        let len = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&is_generalized),
+            QuantumCell::Constant(F::from(2)),
+            QuantumCell::Constant(F::from(13)),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&time[1]),
+            QuantumCell::Existing(&len),
+        );
+
+        // UTCTime years from 50 on are in the 1900s
+        let short_year = self.parse_decimal(ctx, &time[2..4]);
+        let is_2000s = range.is_less_than_safe(ctx, &short_year, 50);
+        let century = gate.select(
+            ctx,
+            QuantumCell::Constant(F::from(2000)),
+            QuantumCell::Constant(F::from(1900)),
+            QuantumCell::Existing(&is_2000s),
+        );
+        let short_year = gate.add(
+            ctx,
+            QuantumCell::Existing(&short_year),
+            QuantumCell::Existing(&century),
+        );
+        let long_year = self.parse_decimal(ctx, &time[2..6]);
+        let year = gate.select(
+            ctx,
+            QuantumCell::Existing(&long_year),
+            QuantumCell::Existing(&short_year),
+            QuantumCell::Existing(&is_generalized),
+        );
+
+        // MMDDhhmmssZ follows the 2 or 4 digits of the year
+        let rest: Vec<AssignedValue<'v, F>> = (0..11)
+            .map(|i| {
+                gate.select(
+                    ctx,
+                    QuantumCell::Existing(&time[6 + i]),
+                    QuantumCell::Existing(&time[4 + i]),
+                    QuantumCell::Existing(&is_generalized),
+                )
+            })
+            .collect();
+        gate.assert_is_const(ctx, &rest[10], F::from(b'Z' as u64));
+        (self.unix_time(ctx, &year, &rest[..10]), is_generalized)
+    }
+
+    /// Takes the serial number of a decoded certificate located by `parse_cert`, left-padded to
+    /// `SERIAL_NUMBER_LEN` bytes. The INTEGER at offset 15 is positive and of 1 up to
+    /// `SERIAL_NUMBER_LEN` + 1 bytes, the longest starting with a zero sign byte.
+    pub(crate) fn cert_serial<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert: &[AssignedValue<'v, F>],
+        parsed: &AssignedCert<'v, F>,
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        let indicator = &parsed.serial_len_indicator;
+
+        self.fp_config
+            .range
+            .check_less_than_safe(ctx, &cert[15], 0x80);
+        let sign_byte = gate.mul(
+            ctx,
+            QuantumCell::Existing(&cert[15]),
+            QuantumCell::Existing(&indicator[SERIAL_NUMBER_LEN]),
+        );
+        gate.assert_is_const(ctx, &sign_byte, F::zero());
+
+        // byte j of a serial number of len bytes is byte j + len - SERIAL_NUMBER_LEN of its value
+        (0..SERIAL_NUMBER_LEN)
+            .map(|j| {
+                let candidates = (1..=SERIAL_NUMBER_LEN + 1).map(|len| {
+                    if j + len >= SERIAL_NUMBER_LEN {
+                        QuantumCell::Existing(&cert[15 + j + len - SERIAL_NUMBER_LEN])
+                    } else {
+                        QuantumCell::Constant(F::zero())
+                    }
+                });
+                gate.select_by_indicator(ctx, candidates, indicator)
+            })
+            .collect()
+    }
+
+    /// Decodes a `year` and the digits `MMDDhhmmss` of a certificate time into a Unix
+    /// timestamp, see `crate::x509::unix_time`
+    pub(crate) fn unix_time<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        year: &AssignedValue<'v, F>,
+        digits: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+
+        let fields: Vec<_> = digits
+            .chunks(2)
+            .map(|pair| self.parse_decimal(ctx, pair))
+            .collect();
+        let (month, day, hour, minute, second) =
+            (&fields[0], &fields[1], &fields[2], &fields[3], &fields[4]);
+
+        // years from 1970 to 2099, in which every 4th year since 1969 is a leap year
+        let elapsed_years = gate.sub(
+            ctx,
+            QuantumCell::Existing(year),
+            QuantumCell::Constant(F::from(1970)),
+        );
+        range.check_less_than_safe(ctx, &elapsed_years, 130);
+        let years = gate.add(
+            ctx,
+            QuantumCell::Existing(&elapsed_years),
+            QuantumCell::Constant(F::one()),
+        );
+        let leap_years = gate.load_witness(
+            ctx,
+            years
+                .value
+                .map(|years| biguint_to_fe(&(fe_to_biguint(&years) / 4u32))),
+        );
+        let years_mod_4 = gate.load_witness(
+            ctx,
+            years
+                .value
+                .map(|years| biguint_to_fe(&(fe_to_biguint(&years) % 4u32))),
+        );
+        range.range_check(ctx, &leap_years, 8);
+        range.check_less_than_safe(ctx, &years_mod_4, 4);
+        let recomposed = gate.mul_add(
+            ctx,
+            QuantumCell::Existing(&leap_years),
+            QuantumCell::Constant(F::from(4)),
+            QuantumCell::Existing(&years_mod_4),
+        );
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&recomposed),
+            QuantumCell::Existing(&years),
+        );
+        let is_leap_year = gate.is_equal(
+            ctx,
+            QuantumCell::Existing(&years_mod_4),
+            QuantumCell::Constant(F::from(3)),
+        );
+
+        let month_index = gate.sub(
+            ctx,
+            QuantumCell::Existing(month),
+            QuantumCell::Constant(F::one()),
+        );
+        range.check_less_than_safe(ctx, &month_index, 12);
+        // days from 1 to 31, times of day up to 23:59:59
+        let day_index = gate.sub(
+            ctx,
+            QuantumCell::Existing(day),
+            QuantumCell::Constant(F::one()),
+        );
+        range.check_less_than_safe(ctx, &day_index, 31);
+        for (field, bound) in [(hour, 24), (minute, 60), (second, 60)] {
+            range.check_less_than_safe(ctx, field, bound);
+        }
+        let days_before_month = gate.select_from_idx(
+            ctx,
+            DAYS_BEFORE_MONTH
+                .iter()
+                .map(|days| QuantumCell::Constant(F::from(*days)))
+                .collect(),
+            QuantumCell::Existing(&month_index),
+        );
+        let is_before_march = range.is_less_than_safe(ctx, month, 3);
+        let is_after_february = gate.not(ctx, QuantumCell::Existing(&is_before_march));
+        let leap_day = gate.and(
+            ctx,
+            QuantumCell::Existing(&is_leap_year),
+            QuantumCell::Existing(&is_after_february),
+        );
+
+        let days = gate.inner_product(
+            ctx,
+            vec![
+                QuantumCell::Existing(&elapsed_years),
+                QuantumCell::Existing(&leap_years),
+                QuantumCell::Existing(&days_before_month),
+                QuantumCell::Existing(&leap_day),
+                QuantumCell::Existing(&day_index),
+            ],
+            [365, 1, 1, 1, 1]
+                .map(|coeff| QuantumCell::Constant(F::from(coeff)))
+                .to_vec(),
+        );
+        gate.inner_product(
+            ctx,
+            vec![
+                QuantumCell::Existing(&days),
+                QuantumCell::Existing(hour),
+                QuantumCell::Existing(minute),
+                QuantumCell::Existing(second),
+            ],
+            [86400, 3600, 60, 1]
+                .map(|coeff| QuantumCell::Constant(F::from(coeff)))
+                .to_vec(),
+        )
+    }
+
+    /// Constrains `timestamp` to be within the validity period of a certificate located by
+    /// `parse_cert`, bounds included
+    pub(crate) fn check_validity<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert: &AssignedCert<'v, F>,
+        timestamp: &AssignedValue<'v, F>,
+    ) {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+
+        // notBefore <= timestamp <= notAfter
+        let bounds = [(&cert.not_before, timestamp), (timestamp, &cert.not_after)];
+        for (earlier, later) in bounds {
+            let later = gate.add(
+                ctx,
+                QuantumCell::Existing(later),
+                QuantumCell::Constant(F::one()),
+            );
+            range.check_less_than(
+                ctx,
+                QuantumCell::Existing(earlier),
+                QuantumCell::Existing(&later),
+                TIMESTAMP_BITS,
+            );
+        }
+    }
+
+    /// Proves that `serial` is not revoked: it falls strictly inside the gap of `proof`, whose
+    /// Merkle root is returned. `digests` are the digests of `NonRevocationProof::messages`.
+    pub(crate) fn non_revocation_root<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        serial: &[AssignedValue<'v, F>],
+        proof: &NonRevocationProof,
+        digests: &[AssignedDigest<F>],
+    ) -> Vec<AssignedValue<'v, F>> {
+        let range = &self.fp_config.range;
+        let low = self.load_bytes(ctx, &proof.low);
+        let high = self.load_bytes(ctx, &proof.high);
+        let low_value = self.pack_be(ctx, &low);
+        let serial_value = self.pack_be(ctx, serial);
+        let high_value = self.pack_be(ctx, &high);
+        for (smaller, larger) in [(&low_value, &serial_value), (&serial_value, &high_value)] {
+            range.check_less_than(
+                ctx,
+                QuantumCell::Existing(smaller),
+                QuantumCell::Existing(larger),
+                8 * SERIAL_NUMBER_LEN,
+            );
+        }
+
+        let gap = [low, high].concat();
+        self.constrain_digest_input(ctx, &digests[0], &gap);
+        self.merkle_root(ctx, &digests[0].output_bytes, &proof.proof, &digests[1..])
+    }
+
+    /// Constrains the assigned bytes to be the constant `bytes`
+    pub(crate) fn constrain_bytes(
+        &self,
+        ctx: &mut Context<'_, F>,
+        assigned: &[AssignedValue<'_, F>],
+        bytes: &[u8],
+    ) {
+        assert_eq!(assigned.len(), bytes.len());
+        for (cell, byte) in assigned.iter().zip(bytes.iter()) {
+            self.fp_config
+                .gate()
+                .assert_is_const(ctx, cell, F::from(*byte as u64));
+        }
+    }
+
+    /// Parses the ASCII digits of a decimal number into its value
+    pub(crate) fn parse_decimal<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        digits: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let mut values = vec![];
+        for digit in digits {
+            let value = gate.sub(
+                ctx,
+                QuantumCell::Existing(digit),
+                QuantumCell::Constant(F::from(b'0' as u64)),
+            );
+            self.fp_config.range.check_less_than_safe(ctx, &value, 10);
+            values.push(value);
+        }
+        let powers = (0..digits.len())
+            .rev()
+            .map(|i| QuantumCell::Constant(F::from(10u64.pow(i as u32))))
+            .collect();
+        gate.inner_product(
+            ctx,
+            values
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            powers,
+        )
+    }
+
+    /// Parses uppercase ASCII hex digits into bytes
+    pub(crate) fn parse_hex<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        chars: &[AssignedValue<'v, F>],
+    ) -> Vec<AssignedValue<'v, F>> {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+        let mut nibbles = vec![];
+        for c in chars {
+            // '0'-'9' map to 0-9 and 'A'-'F' to 10-15, anything else fails a range check
+            let is_digit = range.is_less_than(
+                ctx,
+                QuantumCell::Existing(c),
+                QuantumCell::Constant(F::from(b'9' as u64 + 1)),
+                8,
+            );
+            let digit = gate.sub(
+                ctx,
+                QuantumCell::Existing(c),
+                QuantumCell::Constant(F::from(b'0' as u64)),
+            );
+            let letter = gate.sub(
+                ctx,
+                QuantumCell::Existing(c),
+                QuantumCell::Constant(F::from(b'A' as u64 - 10)),
+            );
+            let nibble = gate.select(
+                ctx,
+                QuantumCell::Existing(&digit),
+                QuantumCell::Existing(&letter),
+                QuantumCell::Existing(&is_digit),
+            );
+            range.check_less_than_safe(ctx, &nibble, 16);
+            let is_letter_nibble = range.is_less_than_safe(ctx, &nibble, 10);
+            let is_letter_nibble = gate.not(ctx, QuantumCell::Existing(&is_letter_nibble));
+            let is_valid = gate.or(
+                ctx,
+                QuantumCell::Existing(&is_digit),
+                QuantumCell::Existing(&is_letter_nibble),
+            );
+            gate.assert_is_const(ctx, &is_valid, F::one());
+            nibbles.push(nibble);
+        }
+        nibbles
+            .chunks(2)
+            .map(|pair| {
+                gate.mul_add(
+                    ctx,
+                    QuantumCell::Existing(&pair[0]),
+                    QuantumCell::Constant(F::from(16)),
+                    QuantumCell::Existing(&pair[1]),
+                )
+            })
+            .collect()
+    }
+
+    /// Takes the TCB, PCEID and FMSPC from the SGX extension of a decoded PCK leaf certificate
+    /// located by `parse_cert`. The TCB sequence is selected at the witnessed `tcb_offset` among
+    /// `SGX_TCB_SEARCH`, see `SgxExtensionLayout::tcb_offset`, and walked in-circuit: each of the
+    /// 16 SGX TCB components and PCESVN is a SEQUENCE of its OID and an INTEGER whose length
+    /// moves the components after it, followed by CPUSVN, PCEID and FMSPC. The walked bytes are
+    /// within the TBSCertificate.
+    pub(crate) fn sgx_extension<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        cert: &[AssignedValue<'v, F>],
+        parsed: &AssignedCert<'v, F>,
+        tcb_offset: usize,
+    ) -> AssignedSgxExtension<'v, F> {
+        let gate = self.fp_config.gate();
+        // an offset outside of the searched positions fails the indicator
+        let offset = gate.load_witness(ctx, Value::known(F::from(tcb_offset as u64)));
+        let indicator = self.position_indicator(ctx, &offset, &SGX_TCB_SEARCH);
+        let sequence =
+            self.select_window(ctx, cert, &indicator, SGX_TCB_SEARCH, SGX_TCB_MAX_LEN);
+        self.constrain_bytes(
+            ctx,
+            &sequence[..TCB_SEQUENCE_HEADER.len()],
+            &TCB_SEQUENCE_HEADER,
+        );
+
+        // number of value bytes beyond 1 of the components so far, which component k follows
+        let mut shift = gate.load_zero(ctx);
+        let mut tcb = vec![];
+        for (k, number) in (1..=PCE_SVN_COMPONENT).enumerate() {
+            // values up to 255 take 2 bytes with their sign byte, PCESVN up to 3
+            let max_value_len = if number == PCE_SVN_COMPONENT { 3 } else { 2 };
+            let start = TCB_SEQUENCE_HEADER.len() + 2 + (TCB_COMPONENT_HEADER_LEN + 1) * k;
+            let indicator = self.indicator(ctx, &shift, k + 1);
+            let component = self.select_window(
+                ctx,
+                &sequence,
+                &indicator,
+                start..start + k + 1,
+                TCB_COMPONENT_HEADER_LEN + max_value_len,
+            );
+            let (header, value) = component.split_at(TCB_COMPONENT_HEADER_LEN);
+            let value_len = &header[TCB_COMPONENT_HEADER_LEN - 1];
+            let sequence_len = gate.add(
+                ctx,
+                QuantumCell::Existing(value_len),
+                QuantumCell::Constant(F::from(TCB_COMPONENT_HEADER_LEN as u64 - 2)),
+            );
+            gate.assert_is_const(ctx, &header[0], F::from(0x30));
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(&header[1]),
+                QuantumCell::Existing(&sequence_len),
+            );
+            let oid = [&TCB_COMPONENT_OID_PREFIX[..], &[number, 0x02]].concat();
+            self.constrain_bytes(ctx, &header[2..TCB_COMPONENT_HEADER_LEN - 1], &oid);
+            tcb.push(self.der_integer(ctx, value_len, value));
+
+            shift = gate.sum(
+                ctx,
+                [
+                    QuantumCell::Existing(&shift),
+                    QuantumCell::Existing(value_len),
+                    QuantumCell::Constant(-F::one()),
+                ],
+            );
+        }
+
+        // CPUSVN, PCEID and FMSPC follow PCESVN
+        let start =
+            TCB_SEQUENCE_HEADER.len() + 2 + (TCB_COMPONENT_HEADER_LEN + 1) * tcb.len();
+        let max_shift = SGX_TCB_COMPONENTS + 2;
+        let indicator = self.indicator(ctx, &shift, max_shift + 1);
+        let tail = self.select_window(
+            ctx,
+            &sequence,
+            &indicator,
+            start..start + max_shift + 1,
+            SGX_TCB_TAIL_LEN,
+        );
+        let cpu_svn_header = [
+            &[0x30, 0x1f][..],
+            &TCB_COMPONENT_OID_PREFIX,
+            &[CPU_SVN_COMPONENT, 0x04, 0x10],
+        ]
+        .concat();
+        let pce_id_header = [&[0x30, 0x10][..], &PCE_ID_HEADER].concat();
+        let fmspc_header = [&[0x30, 0x14][..], &FMSPC_HEADER].concat();
+        let mut values = vec![];
+        let mut position = 0;
+        for (header, len) in [(cpu_svn_header, 16), (pce_id_header, 2), (fmspc_header, 6)] {
+            self.constrain_bytes(ctx, &tail[position..position + header.len()], &header);
+            position += header.len();
+            values.push(tail[position..position + len].to_vec());
+            position += len;
+        }
+        let end = gate.sum(
+            ctx,
+            [
+                QuantumCell::Existing(&offset),
+                QuantumCell::Existing(&shift),
+                QuantumCell::Constant(F::from((start + SGX_TCB_TAIL_LEN) as u64)),
+            ],
+        );
+        self.check_within_tbs(ctx, &end, &parsed.tbs_len);
+
+        let fmspc = values.pop().unwrap();
+        let pce_id = values.pop().unwrap();
+        AssignedSgxExtension { tcb, pce_id, fmspc }
+    }
+
+    /// Value of the DER INTEGER of `len` bytes, 1 up to `value.len()`, at the start of `value`.
+    /// The longest INTEGER starts with a zero sign byte.
+    fn der_integer<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        len: &AssignedValue<'v, F>,
+        value: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let len_index = gate.sub(
+            ctx,
+            QuantumCell::Existing(len),
+            QuantumCell::Constant(F::one()),
+        );
+        let indicator = self.indicator(ctx, &len_index, value.len());
+        let sign_byte = gate.mul(
+            ctx,
+            QuantumCell::Existing(&value[0]),
+            QuantumCell::Existing(&indicator[value.len() - 1]),
+        );
+        gate.assert_is_const(ctx, &sign_byte, F::zero());
+        let candidates: Vec<AssignedValue<'v, F>> = (1..=value.len())
+            .map(|len| self.pack_be(ctx, &value[..len]))
+            .collect();
+        gate.select_by_indicator(ctx, candidates.iter().map(QuantumCell::Existing), &indicator)
+    }
+
+    /// Evaluates the TCB status of `pck_tcb` against the encoded TCB `levels`: the status code of
+    /// the first level whose components are all less than or equal to `pck_tcb`'s, or
+    /// `Unrecognized` if there is none
+    pub(crate) fn tcb_status<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        pck_tcb: &[AssignedValue<'v, F>],
+        levels: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+
+        let mut matches = vec![];
+        for level in levels.chunks(TCB_LEVEL_LEN) {
+            let mut components = level[..SGX_TCB_COMPONENTS].to_vec();
+            components
+                .extend(self.pack_bytes(ctx, &level[SGX_TCB_COMPONENTS..SGX_TCB_COMPONENTS + 2]));
+
+            let mut matched = gate.load_constant(ctx, F::one());
+            for (component, level_component) in pck_tcb.iter().zip(components.iter()) {
+                // all components fit in 16 bits
+                let is_less = range.is_less_than(
+                    ctx,
+                    QuantumCell::Existing(component),
+                    QuantumCell::Existing(level_component),
+                    16,
+                );
+                let is_greater_or_equal = gate.not(ctx, QuantumCell::Existing(&is_less));
+                matched = gate.and(
+                    ctx,
+                    QuantumCell::Existing(&matched),
+                    QuantumCell::Existing(&is_greater_or_equal),
+                );
+            }
+            matches.push((matched, level[TCB_LEVEL_LEN - 1].clone()));
+        }
+        self.first_matched_status(ctx, &matches)
+    }
+
+    /// Takes the status code of the first matched level out of `(matched, status)` pairs, or
+    /// `Unrecognized` if no level matched
+    fn first_matched_status<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        matches: &[(AssignedValue<'v, F>, AssignedValue<'v, F>)],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let mut found = gate.load_zero(ctx);
+        let mut status = gate.load_constant(ctx, F::from(TcbStatus::Unrecognized.code() as u64));
+        for (matched, level_status) in matches {
+            // only the first matched level counts
+            let is_first = gate.not(ctx, QuantumCell::Existing(&found));
+            let take = gate.and(
+                ctx,
+                QuantumCell::Existing(matched),
+                QuantumCell::Existing(&is_first),
+            );
+            status = gate.select(
+                ctx,
+                QuantumCell::Existing(level_status),
+                QuantumCell::Existing(&status),
+                QuantumCell::Existing(&take),
+            );
+            found = gate.or(
+                ctx,
+                QuantumCell::Existing(&found),
+                QuantumCell::Existing(matched),
+            );
+        }
+        status
+    }
 
-    #[inline]
-    pub fn create_bit_lookup(
+    /// Constrains `value & mask == expected & mask` bit by bit
+    fn constrain_masked_equal<'v>(
         &self,
-        meta: &mut ConstraintSystem<F>,
-        encoded_or_decoded_index_offset: usize,
-        encoded_if_true_and_decoded_if_false: bool,
-        bit_query_cols: Vec<usize>,
-        bit_lookup_cols: Vec<usize>,
-        selector_col: Selector,
-    ) -> Option<bool> {
-        meta.lookup("lookup sgx_dcap_verifier encode/decode", |meta| {
-            assert!(bit_query_cols.len() == bit_lookup_cols.len());
-            let q = meta.query_selector(selector_col);
-
-            // One minus q defaults to the 'a' value and '0' bit values
-            let one_minus_q = Expression::Constant(F::from(1)) - q.clone();
-            let zero = Expression::Constant(F::from(0));
-            let zero_char = Expression::Constant(F::from(65));
-
-            let mut lookup_vec = vec![];
-            if encoded_if_true_and_decoded_if_false {
-                let encoded_char = meta.query_advice(
-                    self.encoded_chars,
-                    Rotation(encoded_or_decoded_index_offset as i32),
+        ctx: &mut Context<'v, F>,
+        value: &[AssignedValue<'v, F>],
+        expected: &[AssignedValue<'v, F>],
+        mask: &[AssignedValue<'v, F>],
+    ) {
+        let gate = self.fp_config.gate();
+        for ((value, expected), mask) in value.iter().zip(expected.iter()).zip(mask.iter()) {
+            let value_bits = gate.num_to_bits(ctx, value, 8);
+            let expected_bits = gate.num_to_bits(ctx, expected, 8);
+            let mask_bits = gate.num_to_bits(ctx, mask, 8);
+            for ((value_bit, expected_bit), mask_bit) in value_bits
+                .iter()
+                .zip(expected_bits.iter())
+                .zip(mask_bits.iter())
+            {
+                let diff = gate.sub(
+                    ctx,
+                    QuantumCell::Existing(value_bit),
+                    QuantumCell::Existing(expected_bit),
                 );
-                lookup_vec.push((
-                    q.clone() * encoded_char + one_minus_q.clone() * zero_char.clone(),
-                    self.bit_decomposition_table.character,
-                ));
-            } else {
-                let decoded_char = meta.query_advice(
-                    self.decoded_chars,
-                    Rotation(encoded_or_decoded_index_offset as i32),
+                let masked = gate.mul(
+                    ctx,
+                    QuantumCell::Existing(&diff),
+                    QuantumCell::Existing(mask_bit),
                 );
-                // println!("decoded_char: {:?}", decoded_char);
-                lookup_vec.push((
-                    q.clone() * decoded_char + one_minus_q.clone() * zero.clone(),
-                    self.bit_decomposition_table.value_decoded,
-                ));
-            }
-            for i in 0..bit_query_cols.len() {
-                let bit =
-                    meta.query_advice(self.bit_decompositions[bit_query_cols[i]], Rotation::cur());
-                // println!("bit: {:?}", bit);
-                lookup_vec.push((
-                    q.clone() * bit + one_minus_q.clone() * zero.clone(),
-                    self.bit_decomposition_table.bit_decompositions[bit_lookup_cols[i]],
-                ));
+                gate.assert_is_const(ctx, &masked, F::zero());
             }
-            lookup_vec
-        });
-        None
-    }
-
-    pub fn configure(meta: &mut ConstraintSystem<F>) -> Self {
-        let mut bit_decompositions = vec![];
-        for _ in 0..BIT_DECOMPOSITION_ADVICE_COL_COUNT {
-            bit_decompositions.push(meta.advice_column());
-        }
-        let encoded_chars = meta.advice_column();
-        let decoded_chars = meta.advice_column();
-        // let characters = meta.advice_column();
-        let decoded_chars_without_gap = meta.advice_column();
-        let bit_decomposition_table = BitDecompositionTableConfig::configure(meta);
-        let q_decode_selector = meta.complex_selector();
-
-        meta.enable_equality(encoded_chars);
-        meta.enable_equality(decoded_chars);
-        meta.enable_equality(decoded_chars_without_gap);
-
-        // Create bit lookup for each bit
-        const ENCODED_LOOKUP_COLS: [usize; 4] = [0, 1, 2, 3];
-        const ENCODED_BIT_LOOKUP_COLS: [[usize; 3]; 4] =
-            [[0, 1, 2], [3, 4, 5], [6, 7, 8], [9, 10, 11]];
-        const DECODED_LOOKUP_COLS: [usize; 3] = [0, 1, 2];
-        const DECODED_BIT_LOOKUP_COLS: [[usize; 4]; 3] =
-            [[0, 1, 2, 3], [4, 5, 6, 7], [8, 9, 10, 11]];
-
-        let path = var("ECDSA_CONFIG")
-            .unwrap_or_else(|_| "./src/configs/ecdsa_circuit.config".to_string());
-        let params: CircuitParams = serde_json::from_reader(
-            File::open(&path).unwrap_or_else(|_| panic!("{path:?} file should exist")),
-        )
-        .unwrap();
-        let fp_config = FpConfig::<F, Fp>::configure(
-            meta,
-            params.strategy,
-            &[params.num_advice],
-            &[params.num_lookup_advice],
-            params.num_fixed,
-            params.lookup_bits,
-            params.limb_bits,
-            params.num_limbs,
-            modulus::<Fp>(),
-            0,
-            params.degree as usize,
-        );
+        }
+    }
 
-        let range_config = RangeConfig::configure(
-            meta,
-            Vertical,
-            &[Self::NUM_ADVICE],
-            &[Self::NUM_LOOKUP_ADVICE],
-            Self::NUM_FIXED,
-            Self::LOOKUP_BITS,
-            0,
-            17,
+    /// Checks the QE report against the encoded QE `identity`, see `QeIdentity::matches`, and
+    /// evaluates the status of the QE's ISVSVN against its TCB levels, see `QeIdentity::evaluate`
+    pub(crate) fn qe_tcb_status<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        qe_report: &[AssignedValue<'v, F>],
+        identity: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        let gate = self.fp_config.gate();
+        let range = &self.fp_config.range;
+
+        self.constrain_masked_equal(
+            ctx,
+            &qe_report[MISC_SELECT_OFFSET..MISC_SELECT_OFFSET + 4],
+            &identity[0..4],
+            &identity[4..8],
         );
-        // let hash_column = meta.instance_column();
-        // meta.enable_equality(hash_column);
-        let sha256_config: Sha256DynamicConfig<F> = Sha256DynamicConfig::configure(
-            meta,
-            vec![Self::MAX_BYTE_SIZE1, Self::MAX_BYTE_SIZE2],
-            range_config,
-            8,
-            2,
-            true,
+        self.constrain_masked_equal(
+            ctx,
+            &qe_report[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 16],
+            &identity[8..24],
+            &identity[24..40],
         );
-
-        let config = Self {
-            encoded_chars,
-            bit_decompositions: bit_decompositions.try_into().unwrap(),
-            decoded_chars,
-            decoded_chars_without_gap,
-            bit_decomposition_table,
-            q_decode_selector,
-            fp_config,
-            sha256_config,
-            _marker: PhantomData,
-        };
-        // Create bit lookup for each 6-bit encoded value
-        for i in 0..ENCODED_LOOKUP_COLS.len() {
-            config.create_bit_lookup(
-                meta,
-                i,
-                true,
-                ENCODED_BIT_LOOKUP_COLS[i].to_vec(),
-                [2, 1, 0].to_vec(),
-                config.q_decode_selector,
+        for (report_byte, identity_byte) in qe_report[MR_SIGNER_OFFSET..MR_SIGNER_OFFSET + 32]
+            .iter()
+            .zip(identity[40..72].iter())
+        {
+            gate.assert_equal(
+                ctx,
+                QuantumCell::Existing(report_byte),
+                QuantumCell::Existing(identity_byte),
             );
         }
-        // Create bit lookup for each 8-bit decoded value
-        for i in 0..DECODED_LOOKUP_COLS.len() {
-            config.create_bit_lookup(
-                meta,
-                i,
-                false,
-                DECODED_BIT_LOOKUP_COLS[i].to_vec(),
-                [3, 2, 1, 0].to_vec(),
-                config.q_decode_selector,
+        let isv_prod_id = self.pack_u16(ctx, &qe_report[ISV_PROD_ID_OFFSET..]);
+        let expected_isv_prod_id = self.pack_bytes(ctx, &identity[72..QE_IDENTITY_LEN]);
+        gate.assert_equal(
+            ctx,
+            QuantumCell::Existing(&isv_prod_id),
+            QuantumCell::Existing(&expected_isv_prod_id[0]),
+        );
+
+        let isv_svn = self.pack_u16(ctx, &qe_report[ISV_SVN_OFFSET..]);
+        let mut matches = vec![];
+        for level in identity[QE_IDENTITY_LEN..].chunks(QE_TCB_LEVEL_LEN) {
+            let level_isv_svn = self.pack_bytes(ctx, &level[..2]);
+            let is_less = range.is_less_than(
+                ctx,
+                QuantumCell::Existing(&isv_svn),
+                QuantumCell::Existing(&level_isv_svn[0]),
+                16,
             );
+            let matched = gate.not(ctx, QuantumCell::Existing(&is_less));
+            matches.push((matched, level[2].clone()));
         }
-        config
+        self.first_matched_status(ctx, &matches)
     }
 
-    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
-        self.bit_decomposition_table.load(layouter)
+    /// Packs a little-endian `u16` into a single field element holding its value
+    pub(crate) fn pack_u16<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        self.fp_config.gate().inner_product(
+            ctx,
+            bytes[..2]
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            vec![
+                QuantumCell::Constant(F::one()),
+                QuantumCell::Constant(F::from(256)),
+            ],
+        )
+    }
+
+    /// Exposes the cells as the public instances, in order
+    pub(crate) fn constrain_instances(
+        &self,
+        layouter: &mut impl Layouter<F>,
+        cells: Vec<Cell>,
+    ) -> Result<(), Error> {
+        for (i, cell) in cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, self.instance, i)?;
+        }
+        Ok(())
+    }
+
+    /// Packs big-endian bytes into field elements of `BYTES_PER_INSTANCE` bytes each, see
+    /// `pack_instances` for the host-side counterpart
+    pub(crate) fn pack_bytes<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) -> Vec<AssignedValue<'v, F>> {
+        bytes
+            .chunks(BYTES_PER_INSTANCE)
+            .map(|chunk| self.pack_be(ctx, chunk))
+            .collect()
+    }
+
+    /// Packs at most 31 big-endian bytes into a single field element holding their value
+    fn pack_be<'v>(
+        &self,
+        ctx: &mut Context<'v, F>,
+        bytes: &[AssignedValue<'v, F>],
+    ) -> AssignedValue<'v, F> {
+        assert!(bytes.len() < 32);
+        self.fp_config.gate().inner_product(
+            ctx,
+            bytes
+                .iter()
+                .map(|x| QuantumCell::Existing(x))
+                .collect::<Vec<QuantumCell<F>>>(),
+            be_bytes_coffes()[32 - bytes.len()..].to_vec(),
+        )
     }
 }
+
+/// Verifies an SGX quote, with the ECDSA chips configured with the parameters of `P`
 #[derive(Default, Clone)]
-pub struct SgxDcapVerifierCircuit<F: PrimeField> {
+pub struct SgxDcapVerifierCircuit<F: PrimeField, P: CircuitParamsSpec = SgxDcapCircuitParams> {
     // Since this is only relevant for the witness, we can opt to make this whatever convenient type we want
-    pub sgx_dcap_verifier_encoded_string: Vec<u8>,
-    _marker: PhantomData<F>,
+    // leaf, intermediate and root certificates in the quote, as PEM and base64 bodies
+    pub pck_cert_chain: PckCertChain,
+    pub quote: Quote,
+    // proves MRENCLAVE or MRSIGNER is allowlisted instead of exposing it
+    pub allowlist: Option<AllowlistPolicy>,
+    // TCB levels of Intel's TCBInfo for the platform, committed to in the public instances
+    pub tcb_levels: TcbLevels,
+    // Intel's QE Identity the QE report must match, committed to in the public instances
+    pub qe_identity: QeIdentity,
+    // proves the PCK leaf certificate is not in the committed PCK CRL
+    pub pck_crl: NonRevocationProof,
+    // Unix timestamp within the validity period of every certificate of the chain
+    pub timestamp: u64,
+    _marker: PhantomData<(F, P)>,
 }
 
-impl<F: PrimeField> SgxDcapVerifierCircuit<F> {
-    pub fn new(sgx_dcap_verifier_encoded_string: Vec<u8>) -> Self {
-        Self {
-            sgx_dcap_verifier_encoded_string,
+impl<F: PrimeField, P: CircuitParamsSpec> SgxDcapVerifierCircuit<F, P> {
+    /// The certificate chain is proven valid at the Unix `timestamp`, which is exposed in the
+    /// public instances. The PCK leaf certificate is checked against an empty PCK CRL until
+    /// `with_pck_crl` is called.
+    pub fn new(quote: Quote, timestamp: u64) -> anyhow::Result<Self> {
+        let circuit = Self {
+            pck_cert_chain: quote.signature.certification_data.pck_cert_chain()?,
+            quote,
+            allowlist: None,
+            tcb_levels: TcbLevels::default(),
+            qe_identity: QeIdentity::default(),
+            pck_crl: NonRevocationProof::default(),
+            timestamp,
             _marker: PhantomData,
-        }
+        };
+        circuit.leaf_sgx_extension()?;
+        circuit.with_pck_crl(&RevokedSerials::new(&[])?)
     }
 
-    // Note that the two types of region.assign_advice calls happen together so that it is the same region
-    fn sgx_dcap_verifier_assign_values(
-        &self,
-        region: &mut Region<F>,
-        characters: &[u8],
-        encoded_chars: Column<Advice>,
-        bit_decompositions: [Column<Advice>; BIT_DECOMPOSITION_ADVICE_COL_COUNT],
-        decoded_chars: Column<Advice>,
-        decoded_chars_without_gap: Column<Advice>,
-        bit_decomposition_table: BitDecompositionTableConfig<F>,
-        q_decode_selector: Selector,
-    ) -> Result<AssignedSgxDcapVerifierResult<F>, Error> {
-        let mut assigned_encoded_values = Vec::new();
-        let mut assigned_decoded_values = Vec::new();
-
-        // Set the decoded values and enable permutation checks with offset
-        let res_decoded_chars: Vec<u8> =
-            general_purpose::STANDARD
-                .decode(characters)
-                .unwrap_or_else(|_| panic!("{:?} is an invalid sgx_dcap_verifier string bytes",
-                    characters));
-        for i in 0..res_decoded_chars.len() {
-            let offset_value = region.assign_advice(
-                || "decoded character".to_string(),
-                decoded_chars_without_gap,
-                i,
-                || Value::known(F::from_u128(res_decoded_chars[i].into())),
-            )?;
-            offset_value.copy_advice(
-                || "copying to add offset",
-                region,
-                decoded_chars,
-                i + (i / 3),
-            )?;
-            assigned_decoded_values.push(offset_value);
-        }
-
-        // Set the character values as encoded chars
-        for i in 0..SHAHASH_BASE64_STRING_LEN {
-            let bit_val: u8 =
-                bit_decomposition_table.map_character_to_encoded_value(characters[i] as char);
-            let assigned_encoded = region.assign_advice(
-                || "encoded character".to_string(),
-                encoded_chars,
-                i,
-                || Value::known(F::from(characters[i] as u64)),
-            )?;
-            assigned_encoded_values.push(assigned_encoded);
-
-            // Set bit values by decomposing the encoded character
-            for j in 0..3 {
-                region.assign_advice(
-                    || "bit assignment".to_string(),
-                    bit_decompositions[(i % 4) * 3 + j],
-                    i - (i % 4),
-                    || Value::known(F::from_u128(((bit_val >> ((2 - j) * 2)) % 4) as u128)),
-                )?;
-            }
-        }
+    /// Serial number of the PCK leaf certificate
+    fn leaf_serial_number(&self) -> anyhow::Result<[u8; SERIAL_NUMBER_LEN]> {
+        let (leaf, layout) = CertLayout::from_base64(&self.pck_cert_chain.leaf)?;
+        layout.serial_number(&leaf)
+    }
+
+    /// The decoded PCK leaf certificate and the layout of its SGX extension
+    fn leaf_sgx_extension(&self) -> anyhow::Result<(Vec<u8>, SgxExtensionLayout)> {
+        let (leaf, _) = CertLayout::from_base64(&self.pck_cert_chain.leaf)?;
+        let layout = SgxExtensionLayout::parse(&leaf)?;
+        Ok((leaf, layout))
+    }
+
+    /// Switches to the allowlist policy: the circuit proves that the quote's MRENCLAVE or
+    /// MRSIGNER is in `allowlist` and exposes the allowlist root in its place
+    pub fn with_allowlist(
+        mut self,
+        target: AllowlistTarget,
+        allowlist: &Allowlist,
+    ) -> anyhow::Result<Self> {
+        let proof = allowlist.proof(&target.measurement(&self.quote.isv_enclave_report))?;
+        self.allowlist = Some(AllowlistPolicy { target, proof });
+        Ok(self)
+    }
+
+    /// Sets the TCB levels the TCB of the PCK certificate is evaluated against
+    pub fn with_tcb_levels(mut self, tcb_levels: TcbLevels) -> Self {
+        self.tcb_levels = tcb_levels;
+        self
+    }
+
+    /// Proves that the PCK leaf certificate is not revoked by `pck_crl`, whose root is exposed in
+    /// the public instances. Fails if it is revoked.
+    pub fn with_pck_crl(mut self, pck_crl: &RevokedSerials) -> anyhow::Result<Self> {
+        self.pck_crl = pck_crl.proof(&self.leaf_serial_number()?)?;
+        Ok(self)
+    }
+
+    /// Sets the QE Identity the QE report is checked against. The proof only verifies if the QE
+    /// report matches it, see `QeIdentity::matches`.
+    pub fn with_qe_identity(mut self, qe_identity: QeIdentity) -> Self {
+        self.qe_identity = qe_identity;
+        self
+    }
+
+    /// Parses a raw SGX quote and takes all the witnesses of the circuit from it, see `new`
+    pub fn from_quote(quote: &[u8], timestamp: u64) -> anyhow::Result<Self> {
+        Self::new(Quote::parse(quote)?, timestamp)
+    }
 
-        // Enable q_decomposed on every 4 rows
-        for i in (0..SHAHASH_BASE64_STRING_LEN).step_by(4) {
-            q_decode_selector.enable(region, i)?;
+    /// Public instances of the circuit, laid out as described at `MR_ENCLAVE_INSTANCE`
+    pub fn instances(&self) -> Vec<F> {
+        let report = &self.quote.isv_enclave_report;
+        let (leaf, sgx_extension) = self
+            .leaf_sgx_extension()
+            .expect("PCK leaf certificate should have an SGX extension");
+        let pck_tcb = sgx_extension
+            .pck_tcb(&leaf)
+            .expect("PCK leaf certificate should have a valid TCB");
+        let mut instances = [
+            pack_instances(&report.mr_enclave),
+            pack_instances(&report.mr_signer),
+            vec![F::from(report.isv_prod_id as u64)],
+            vec![F::from(report.isv_svn as u64)],
+            pack_instances(&report.attributes),
+            pack_instances(&report.report_data),
+            pack_instances(&leaf[sgx_extension.fmspc]),
+            pack_instances(&leaf[sgx_extension.pce_id]),
+            pack_instances(&self.tcb_levels.commitment()),
+            vec![F::from(self.tcb_levels.evaluate(&pck_tcb).code() as u64)],
+            pack_instances(&self.qe_identity.commitment()),
+            vec![F::from(
+                self.qe_identity
+                    .evaluate(self.quote.signature.qe_report.isv_svn)
+                    .code() as u64,
+            )],
+            pack_instances(&self.pck_crl.root()),
+            vec![F::from(self.timestamp)],
+        ]
+        .concat();
+        debug_assert_eq!(instances.len(), SGX_NUM_INSTANCES);
+
+        if let Some(policy) = &self.allowlist {
+            let root = policy.proof.root(&policy.target.measurement(report));
+            let index = policy.target.instance();
+            instances.splice(index..index + 2, pack_instances(&root));
         }
-        // println!("Decoded chars: {:?}", decoded_chars);
-        let result = AssignedSgxDcapVerifierResult {
-            encoded: assigned_encoded_values,
-            decoded: assigned_decoded_values,
-        };
-        Ok(result)
+        instances
     }
 }
 
-impl<F: PrimeField> Circuit<F> for SgxDcapVerifierCircuit<F> {
+impl<F: PrimeField, P: CircuitParamsSpec> Circuit<F> for SgxDcapVerifierCircuit<F, P> {
     type Config = SgxDcapVerifierConfig<F>;
     type FloorPlanner = SimpleFloorPlanner;
 
     // Circuit without witnesses, called only during key generation
     fn without_witnesses(&self) -> Self {
         Self {
-            sgx_dcap_verifier_encoded_string: vec![],
+            pck_cert_chain: PckCertChain::default(),
+            quote: Quote::default(),
+            allowlist: self.allowlist.clone(),
+            tcb_levels: TcbLevels::default(),
+            qe_identity: QeIdentity::default(),
+            pck_crl: NonRevocationProof::default(),
+            timestamp: 0,
             _marker: PhantomData,
         }
     }
 
     fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
-        // let encoded_chars = meta.advice_column();
-        // TODO Set an offset to encoded_chars
-
-        SgxDcapVerifierConfig::configure(meta)
+        SgxDcapVerifierConfig::configure(meta, &P::params())
+            .expect("circuit params should be valid")
+            .with_root_ca_pubkey(P::root_ca_pubkey())
     }
 
     fn synthesize(
@@ -353,142 +2696,53 @@ impl<F: PrimeField> Circuit<F> for SgxDcapVerifierCircuit<F> {
         config: Self::Config,
         mut layouter: impl Layouter<F>,
     ) -> Result<(), Error> {
-        // println!("Assigning table in synthesize...");
-        let fp_chip = config.fp_config;
-        fp_chip.range.load_lookup_table(&mut layouter)?;
-
-        let limb_bits = fp_chip.limb_bits;
-        let num_limbs = fp_chip.num_limbs;
-        let _num_fixed = fp_chip.range.gate.constants.len();
-        let _lookup_bits = fp_chip.range.lookup_bits;
-        let _num_advice = fp_chip.range.gate.num_advice;
-
-        config.bit_decomposition_table.load(&mut layouter)?;
-
-        let mut sha256 = config.sha256_config.clone();
-        sha256.range().load_lookup_table(&mut layouter)?;
-        sha256.load(&mut layouter)?;
-
-        let flex_config = sha256.range().clone().gate;
-
-        // leaf certificate sgx_dcap_verifier decoded result
-        let leaf_cert = layouter.assign_region(
-            || "Assign all values",
-            |mut region| {
-                self.sgx_dcap_verifier_assign_values(
-                    &mut region,
-                    &self.sgx_dcap_verifier_encoded_string,
-                    config.encoded_chars,
-                    config.bit_decompositions,
-                    config.decoded_chars,
-                    config.decoded_chars_without_gap,
-                    config.bit_decomposition_table,
-                    config.q_decode_selector,
-                )
-            },
-        )?;
-
-        let mut first_pass = SKIP_FIRST_PASS;
-        let re = Regex::new(r"inner: Some\(0x(.{64})\)").unwrap();
-        // coffes for converting big-endian bytes to original bigint
-        // load constants from [2^248, 2^240, ..., 2^8, 2^0]
-        let coffes = (0..32)
-            .map(|i| QuantumCell::Constant(biguint_to_fe(&BigUint::from(2u32).pow(248 - 8 * i))))
-            .collect::<Vec<_>>();
-
-        // let mut assigned_hash_cells = vec![];
-        // let mut msghash_mod: Vec<AssignedValue<F>> = vec![];
-        let range = sha256.range().clone();
-        let qe_report: Vec<u8> = vec![
-            8, 9, 14, 13, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 21, 0, 0, 0, 0, 0, 0, 0,
-            231, 0, 0, 0, 0, 0, 0, 0, 206, 29, 168, 154, 193, 245, 74, 128, 114, 87, 196, 229, 124,
-            120, 20, 12, 188, 102, 82, 212, 213, 135, 214, 15, 5, 131, 18, 90, 39, 146, 190, 112,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 140, 79, 87, 117, 215, 150, 80, 62, 150, 19, 127, 119, 198, 138, 130, 154, 0,
-            86, 172, 141, 237, 112, 20, 11, 8, 27, 9, 68, 144, 197, 123, 255, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 9, 188, 124, 79, 211, 205, 227, 97, 238, 49, 224, 32, 91, 56,
-            220, 72, 241, 138, 165, 234, 97, 86, 191, 147, 42, 38, 34, 143, 92, 197, 56, 135, 0, 0,
-            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-            0,
-        ];
-
-        let mut msg_hash_cell = vec![];
-        let mut msg_hash_value = vec![];
-        let mut msg_hash_row_offset = vec![];
-        let mut msg_hash_context_id = vec![];
-        let mut hash_bytes_u8: Vec<u8> = vec![];
-        layouter.assign_region(
-            || "dynamic sha2",
-            |region| {
-                if first_pass {
-                    first_pass = false;
-                    return Ok(());
-                }
-
-                let ctx = &mut sha256.new_context(region);
-                let result0 = sha256.digest(ctx, &qe_report, Some(384))?;
-                let hash_bytes: Vec<QuantumCell<'_, '_, F>> = result0
-                    .output_bytes
-                    .into_iter()
-                    .map(|v| QuantumCell::ExistingOwned(v))
-                    .collect();
-                hash_bytes_u8.extend::<&Vec<u8>>(
-                    &hash_bytes
-                        .clone()
-                        .into_iter()
-                        .rev()
-                        .map(|x| {
-                            u8::from_str_radix(
-                                re.captures(&format!("{:?}", x))
-                                    .unwrap()
-                                    .get(1)
-                                    .unwrap()
-                                    .as_str(),
-                                16,
-                            )
-                            .unwrap()
-                        })
-                        .collect(),
-                );
-                // big-endian
-                let (_, msghash) = flex_config.inner_product_simple_with_assignments(
-                    ctx,
-                    coffes.clone(),
-                    hash_bytes,
-                );
-
-                msg_hash_cell.push(msghash.cell);
-                msg_hash_value.push(msghash.value);
-                msg_hash_row_offset.push(msghash.row_offset);
-                msg_hash_context_id.push(msghash.context_id);
+        config.load(&mut layouter)?;
+
+        // the layouts only give the hashed TBSCertificates and witnesses such as the signatures,
+        // certificates that do not parse, e.g. the default ones of key generation, fail the proof
+        let (leaf_der, leaf_layout) =
+            CertLayout::from_base64(&self.pck_cert_chain.leaf).unwrap_or_default();
+        let (intermediate_der, intermediate_layout) =
+            CertLayout::from_base64(&self.pck_cert_chain.intermediate).unwrap_or_default();
+        let (root_der, root_layout) =
+            CertLayout::from_base64(&self.pck_cert_chain.root).unwrap_or_default();
+        let sgx_extension = SgxExtensionLayout::parse(&leaf_der).unwrap_or_default();
 
-                range.finalize(ctx);
-                // {
-                //     println!("total advice cells: {}", ctx.total_advice);
-                //     let const_rows = ctx.total_fixed + 1;
-                //     println!("maximum rows used by a fixed column: {const_rows}");
-                //     println!("lookup cells used: {}", ctx.cells_to_lookup.len());
-                // }
-                Ok(())
-            },
-        )?;
-        // NOTE (xiaowentao) This value is msghash mod p where p is Fr's modulus 0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001
-        let msghash_mod_by_fr_p = AssignedValue {
-            cell: msg_hash_cell[0],
-            value: msg_hash_value[0],
-            row_offset: msg_hash_row_offset[0],
-            context_id: msg_hash_context_id[0],
-            _marker: PhantomData,
-        };
         // the output of sha256 is big-endian
-        // println!("msghash mod by fr's p: {:?}", msghash_mod_by_fr_p);
-
+        let mut messages = vec![
+            self.quote.signature.qe_report.to_bytes(),
+            leaf_der[leaf_layout.tbs.clone()].to_vec(),
+            intermediate_der[intermediate_layout.tbs.clone()].to_vec(),
+            self.quote.signed_bytes(),
+            [
+                &self.quote.signature.ecdsa_attestation_key[..],
+                &self.quote.signature.qe_auth_data,
+            ]
+            .concat(),
+            self.tcb_levels.to_bytes(),
+            self.qe_identity.to_bytes(),
+            root_der[root_layout.tbs.clone()].to_vec(),
+        ];
+        // the digests of the PCK CRL proof must fill their slots for the allowlist to follow them
+        if self.pck_crl.proof.siblings.len() != CRL_DEPTH {
+            return Err(Error::Synthesis);
+        }
+        messages.extend(self.pck_crl.messages());
+        if let Some(policy) = &self.allowlist {
+            let measurement = policy.target.measurement(&self.quote.isv_enclave_report);
+            messages.extend(policy.proof.messages(&measurement));
+        }
+        let digests = config.digest(&mut layouter, &messages)?;
+        let (qe_report_digest, leaf_tbs_digest, intermediate_tbs_digest, quote_digest) =
+            (&digests[0], &digests[1], &digests[2], &digests[3]);
+        let (attestation_key_digest, tcb_levels_digest, qe_identity_digest) =
+            (&digests[4], &digests[5], &digests[6]);
+        let root_tbs_digest = &digests[7];
+        let crl_digests = &digests[8..9 + CRL_DEPTH];
+        let allowlist_digests = &digests[9 + CRL_DEPTH..];
+
+        let mut outputs = vec![];
+        let mut first_pass = SKIP_FIRST_PASS;
         layouter.assign_region(
             || "ECDSA",
             |region| {
@@ -497,249 +2751,181 @@ impl<F: PrimeField> Circuit<F> for SgxDcapVerifierCircuit<F> {
                     return Ok(());
                 }
 
-                // NOTE (xiaowentao) All the values must be Little-Endian
-                // let pubkey_x_base = Fp::from_bytes(&[25, 122, 102, 10, 107, 161, 208, 37, 40, 103, 230, 212, 217, 201, 219, 37, 243, 21, 148, 231, 81, 156, 37, 255, 173, 53, 17, 65, 57, 1, 131, 41]).unwrap();
-                // let pubkey_y_base = Fp::from_bytes(&[61, 92, 233, 152, 97, 160, 133, 116, 50, 175, 252, 245, 58, 47, 19, 241, 229, 38, 133, 160, 239, 55, 223, 203, 39, 166, 219, 23, 138, 241, 140, 84]).unwrap();
-                // let pubkey_point: Option<Secp256r1Affine> = Secp256r1Affine::from_xy(pubkey_x_base, pubkey_y_base).into();
-                // sha256 result of qeReport (attestation[436+128:436+512])
-                // let msghash: Option<Fq> = <Secp256r1Affine as CurveAffine>::ScalarExt::from_bytes(&[213, 190, 114, 4, 209, 8, 253, 177, 115, 233, 78, 182, 125, 86, 180, 111, 229, 1, 180, 87, 87, 165, 247, 28, 227, 115, 150, 79, 183, 175, 176, 217]).into();
-                let msghash_array: [u8; 32] = hash_bytes_u8
-                    .clone()
-                    .try_into()
-                    .unwrap_or_else(|_| panic!("failed to convert vec to array"));
-                let msghash: Option<Fq> =
-                    <Secp256r1Affine as CurveAffine>::ScalarExt::from_bytes(&msghash_array).into();
-                // qeReportSig (attestation[436+512:436+576])
-                let r_point: Option<Fq> =
-                    <Secp256r1Affine as CurveAffine>::ScalarExt::from_bytes(&[
-                        85, 11, 117, 70, 141, 121, 224, 181, 11, 22, 189, 36, 53, 164, 196, 215,
-                        128, 241, 3, 3, 78, 217, 25, 34, 39, 31, 169, 113, 138, 231, 85, 42,
-                    ])
-                    .into();
-                let s_point: Option<Fq> =
-                    <Secp256r1Affine as CurveAffine>::ScalarExt::from_bytes(&[
-                        41, 142, 197, 233, 154, 110, 18, 217, 14, 60, 22, 79, 26, 131, 37, 102, 35,
-                        30, 143, 208, 8, 164, 25, 160, 36, 86, 192, 101, 211, 255, 243, 6,
-                    ])
-                    .into();
-
-                let mut aux = fp_chip.new_context(region);
+                let mut aux = config.new_context(region);
                 let ctx = &mut aux;
 
-                // println!("leaf cert decoded: {:?}", &leaf_cert.decoded[..3]);
-                let leaf_cert_assigned: Vec<AssignedValue<'_, F>> = leaf_cert
-                    .decoded
-                    .clone()
-                    .iter()
-                    .map(|x| {
-                        fp_chip.gate().mul(
-                            ctx,
-                            QuantumCell::Witness(
-                                Some(F::from_u128(
-                                    u128::from_str_radix(
-                                        &re.captures(&format!("{:?}", x))
-                                            .map_or("1".to_string(), |i| {
-                                                i.get(1).unwrap().as_str().to_string()
-                                            }),
-                                        16,
-                                    )
-                                    .unwrap(),
-                                ))
-                                .map_or(Value::unknown(), Value::known),
-                            ),
-                            QuantumCell::Constant(F::one()),
-                        )
-                    })
-                    .collect();
-                // euality constraints for leaf cert sgx_dcap_verifier decoded bytes
-                for (leaf_cert_byte, leaf_cert_byte_assigned) in leaf_cert
-                    .decoded
-                    .clone()
-                    .iter()
-                    .zip(leaf_cert_assigned.iter())
-                {
-                    ctx.region
-                        .constrain_equal(leaf_cert_byte.cell(), leaf_cert_byte_assigned.cell())
-                        .unwrap();
-                }
+                // leaf, intermediate and root certificates decoded from their PEM in the quote
+                let mut base64 = config.base64_chip();
+                let leaf_cert_assigned =
+                    config.decode_pem_cert(ctx, &mut base64, &self.pck_cert_chain.leaf_pem)?;
+                let intermediate_cert_assigned = config.decode_pem_cert(
+                    ctx,
+                    &mut base64,
+                    &self.pck_cert_chain.intermediate_pem,
+                )?;
+                let root_cert_assigned =
+                    config.decode_pem_cert(ctx, &mut base64, &self.pck_cert_chain.root_pem)?;
 
-                // get pubkey from leaf_cert, starts with [2, 1, 6, 8, 42, 134, 72, 206, 61, 3, 1, 7, 3, 66, 0, 4]
-                // which is oid of secp256r1
-                // NOTE (xiaowentao) here, hard-coded the start position of pubkey to be 335
-                // varirable length is not friendly in halo2
-                // and also note that they are big-endian in cert and after inner_product
-                // they will be mod by Fr's modulus
-                let pubkey_x_mod = fp_chip.gate().inner_product(
+                let leaf = config.parse_cert(ctx, &leaf_cert_assigned);
+                let intermediate = config.parse_cert(ctx, &intermediate_cert_assigned);
+                let root = config.parse_cert(ctx, &root_cert_assigned);
+
+                // Intel's root CA signs its own certificate, which binds its validity period
+                let root_ca_pubkey = config.load_root_ca_pubkey(ctx);
+                config.verify_cert_signature(
                     ctx,
-                    leaf_cert_assigned[335..335 + 32]
-                        .iter()
-                        .map(|x| QuantumCell::Existing(x))
-                        .collect::<Vec<QuantumCell<F>>>(),
-                    coffes.clone(),
+                    &root_cert_assigned,
+                    &root,
+                    &root_layout.signature(&root_der),
+                    &root_ca_pubkey,
+                    root_tbs_digest,
                 );
-                let pubkey_y_mod = fp_chip.gate().inner_product(
+
+                // Intel's root CA signs the intermediate certificate
+                config.verify_cert_signature(
                     ctx,
-                    leaf_cert_assigned[335 + 32..335 + 64]
-                        .iter()
-                        .map(|x| QuantumCell::Existing(x))
-                        .collect::<Vec<QuantumCell<F>>>(),
-                    coffes.clone(),
+                    &intermediate_cert_assigned,
+                    &intermediate,
+                    &intermediate_layout.signature(&intermediate_der),
+                    &root_ca_pubkey,
+                    intermediate_tbs_digest,
                 );
-                // big-endian => little-endian
-                let pubkey_x_bytes: Vec<u8> = if !leaf_cert.decoded.is_empty() {
-                    leaf_cert.decoded[335..335 + 32]
-                        .iter()
-                        .rev()
-                        .map(|x| {
-                            u8::from_str_radix(
-                                &re.captures(&format!("{:?}", x))
-                                    .map_or("1".to_string(), |i| {
-                                        i.get(1).unwrap().as_str().to_string()
-                                    }),
-                                16,
-                            )
-                            .unwrap()
-                        })
-                        .collect()
-                } else {
-                    vec![1; 32]
-                };
-                let pubkey_y_bytes: Vec<u8> = if !leaf_cert.decoded.is_empty() {
-                    leaf_cert.decoded[335 + 32..335 + 64]
-                        .iter()
-                        .rev()
-                        .map(|x| {
-                            u8::from_str_radix(
-                                &re.captures(&format!("{:?}", x))
-                                    .map_or("1".to_string(), |i| {
-                                        i.get(1).unwrap().as_str().to_string()
-                                    }),
-                                16,
-                            )
-                            .unwrap()
-                        })
-                        .collect()
-                } else {
-                    vec![1; 32]
-                };
 
-                let pubkey_x_base = Fp::from_bytes(
-                    &pubkey_x_bytes
-                        .try_into()
-                        .unwrap_or_else(|_| panic!("failed to convert vec to array")),
-                )
-                .unwrap();
-                let pubkey_y_base = Fp::from_bytes(
-                    &pubkey_y_bytes
-                        .try_into()
-                        .unwrap_or_else(|_| panic!("failed to convert vec to array")),
-                )
-                .unwrap();
-                let pubkey_point: Option<Secp256r1Affine> =
-                    Secp256r1Affine::from_xy(pubkey_x_base, pubkey_y_base).into();
-
-                let (r_assigned, s_assigned, m_assigned) = {
-                    let fq_chip = FpConfig::<F, Fq>::construct(
-                        fp_chip.range.clone(),
-                        limb_bits,
-                        num_limbs,
-                        modulus::<Fq>(),
-                    );
-
-                    let m_assigned = fq_chip.load_private(
-                        ctx,
-                        FpConfig::<F, Fq>::fe_to_witness(
-                            &msghash.map_or(Value::unknown(), Value::known),
-                        ),
-                    );
+                // the intermediate CA signs the leaf certificate
+                config.verify_cert_signature(
+                    ctx,
+                    &leaf_cert_assigned,
+                    &leaf,
+                    &leaf_layout.signature(&leaf_der),
+                    &intermediate.pubkey,
+                    leaf_tbs_digest,
+                );
 
-                    let r_assigned = fq_chip.load_private(
-                        ctx,
-                        FpConfig::<F, Fq>::fe_to_witness(
-                            &r_point.map_or(Value::unknown(), Value::known),
-                        ),
-                    );
-                    let s_assigned = fq_chip.load_private(
-                        ctx,
-                        FpConfig::<F, Fq>::fe_to_witness(
-                            &s_point.map_or(Value::unknown(), Value::known),
-                        ),
-                    );
-                    (r_assigned, s_assigned, m_assigned)
-                };
+                // PCK leaf certificate signs the QE report
+                let qe_report_signature =
+                    config.load_bytes(ctx, &self.quote.signature.qe_report_signature);
+                config.ecdsa_verify(ctx, &leaf.pubkey, &qe_report_signature, qe_report_digest);
 
-                // NOTE (xiaowentao) check msghash mod by Fr's p is actually the native value in m_assigned (CRTInteger)
-                // NOTE (xiaowentao) we need to ensure that the m_assigned is indeed the msghash outputed by sha256 circuit
-                fp_chip.gate().assert_equal(
+                // attestation key signs the quote header and ISV enclave report body
+                let attestation_key =
+                    config.load_bytes(ctx, &self.quote.signature.ecdsa_attestation_key);
+                config.constrain_qe_report_data(
                     ctx,
-                    QuantumCell::Existing(m_assigned.native()),
-                    QuantumCell::Existing(&msghash_mod_by_fr_p),
+                    qe_report_digest,
+                    &attestation_key,
+                    attestation_key_digest,
                 );
-
-                let ecc_chip = EccChip::<F, FpChip<F>>::construct(fp_chip.clone());
-                let pk_assigned = ecc_chip.load_private(
+                let isv_enclave_report_signature =
+                    config.load_bytes(ctx, &self.quote.signature.isv_enclave_report_signature);
+                config.ecdsa_verify(
                     ctx,
-                    (
-                        pubkey_point.map_or(Value::unknown(), |pt| Value::known(pt.x)),
-                        pubkey_point.map_or(Value::unknown(), |pt| Value::known(pt.y)),
-                    ),
+                    &attestation_key,
+                    &isv_enclave_report_signature,
+                    quote_digest,
                 );
 
-                // checks like msghash_mod_by_fr_p
-                fp_chip.gate().assert_equal(
+                // the whole quote body is hashed in-circuit, so the enclave identity can be taken
+                // from the hash input
+                config.constrain_digest_len(
                     ctx,
-                    QuantumCell::Existing(pk_assigned.x.native()),
-                    QuantumCell::Existing(&pubkey_x_mod),
+                    quote_digest,
+                    QUOTE_HEADER_LEN + ENCLAVE_REPORT_LEN,
                 );
-                fp_chip.gate().assert_equal(
-                    ctx,
-                    QuantumCell::Existing(pk_assigned.y.native()),
-                    QuantumCell::Existing(&pubkey_y_mod),
+                let report = &quote_digest.input_bytes[QUOTE_HEADER_LEN..];
+                let mut instances = vec![];
+                instances.extend(
+                    config.pack_bytes(ctx, &report[MR_ENCLAVE_OFFSET..MR_ENCLAVE_OFFSET + 32]),
+                );
+                instances.extend(
+                    config.pack_bytes(ctx, &report[MR_SIGNER_OFFSET..MR_SIGNER_OFFSET + 32]),
+                );
+                instances.push(config.pack_u16(ctx, &report[ISV_PROD_ID_OFFSET..]));
+                instances.push(config.pack_u16(ctx, &report[ISV_SVN_OFFSET..]));
+                instances.extend(
+                    config.pack_bytes(ctx, &report[ATTRIBUTES_OFFSET..ATTRIBUTES_OFFSET + 16]),
+                );
+                instances.extend(
+                    config.pack_bytes(ctx, &report[REPORT_DATA_OFFSET..REPORT_DATA_OFFSET + 64]),
                 );
 
-                // test ECDSA
-                let ecdsa = ecdsa_verify_no_pubkey_check::<F, Fp, Fq, Secp256r1Affine>(
-                    &ecc_chip.field_chip,
+                // FMSPC and PCEID from the SGX extension of the PCK leaf certificate
+                let leaf_sgx_extension = config.sgx_extension(
                     ctx,
-                    &pk_assigned,
-                    &r_assigned,
-                    &s_assigned,
-                    &m_assigned,
-                    4,
-                    4,
+                    &leaf_cert_assigned,
+                    &leaf,
+                    sgx_extension.tcb_offset(),
                 );
+                instances.extend(config.pack_bytes(ctx, &leaf_sgx_extension.fmspc));
+                instances.extend(config.pack_bytes(ctx, &leaf_sgx_extension.pce_id));
+
+                // TCB status of the PCK leaf certificate against the committed TCB levels
+                let tcb_levels = config.load_bytes(ctx, &self.tcb_levels.to_bytes());
+                config.constrain_digest_input(ctx, tcb_levels_digest, &tcb_levels);
+                instances.extend(config.pack_bytes(ctx, &tcb_levels_digest.output_bytes));
+                instances.push(config.tcb_status(ctx, &leaf_sgx_extension.tcb, &tcb_levels));
+
+                // the whole QE report is hashed in-circuit and checked against the QE Identity
+                config.constrain_digest_len(ctx, qe_report_digest, ENCLAVE_REPORT_LEN);
+                let qe_identity = config.load_bytes(ctx, &self.qe_identity.to_bytes());
+                config.constrain_digest_input(ctx, qe_identity_digest, &qe_identity);
+                instances.extend(config.pack_bytes(ctx, &qe_identity_digest.output_bytes));
+                instances.push(config.qe_tcb_status(
+                    ctx,
+                    &qe_report_digest.input_bytes,
+                    &qe_identity,
+                ));
 
-                // check the ecdsa signature verification result is ok
-                fp_chip.gate().assert_is_const(ctx, &ecdsa, F::one());
-
-                // IMPORTANT: this copies cells to the lookup advice column to perform range check lookups
-                // This is not optional.
-                fp_chip.finalize(ctx);
-
-                println!("ECDSA res {ecdsa:?}");
-
-                #[cfg(feature = "display")]
-                if self.r.is_some() {
-                    println!("ECDSA res {ecdsa:?}");
+                // the PCK leaf certificate is not revoked by the committed PCK CRL
+                let serial = config.cert_serial(ctx, &leaf_cert_assigned, &leaf);
+                let crl_root = config.non_revocation_root(ctx, &serial, &self.pck_crl, crl_digests);
+                instances.extend(config.pack_bytes(ctx, &crl_root));
+
+                // the timestamp is within the validity period of every certificate
+                let timestamp = config
+                    .gate()
+                    .load_witness(ctx, Value::known(F::from(self.timestamp)));
+                config
+                    .fp_config
+                    .range
+                    .range_check(ctx, &timestamp, TIMESTAMP_BITS);
+                for cert in [&leaf, &intermediate, &root] {
+                    config.check_validity(ctx, cert, &timestamp);
+                }
+                instances.push(timestamp);
 
-                    ctx.print_stats(&["Range"]);
+                // allowlist policy: the measurement is replaced by the root of its Merkle proof
+                if let Some(policy) = &self.allowlist {
+                    let offset = policy.target.report_offset();
+                    let root = config.merkle_root(
+                        ctx,
+                        &report[offset..offset + 32],
+                        &policy.proof,
+                        allowlist_digests,
+                    );
+                    let index = policy.target.instance();
+                    instances.splice(index..index + 2, config.pack_bytes(ctx, &root));
                 }
+                outputs = instances.iter().map(|x| x.cell()).collect();
 
+                config.finalize(ctx);
                 Ok(())
             },
         )?;
-        // println!("Done assigning values in synthesize");
-        Ok(())
+        config.constrain_instances(&mut layouter, outputs)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use ark_std::{end_timer, start_timer};
+    use base64::{engine::general_purpose, Engine};
     use halo2_base::{
         halo2_proofs::{
             dev::MockProver,
-            halo2curves::bn256::{Bn256, Fr, G1Affine},
+            halo2curves::{
+                bn256::{Bn256, Fr, G1Affine},
+                secp256r1::Fq,
+            },
             plonk::{create_proof, keygen_pk, keygen_vk, verify_proof},
             poly::{
                 commitment::ParamsProver,
@@ -759,41 +2945,118 @@ mod tests {
     use rand_chacha::rand_core::OsRng;
 
     use super::*;
+    use crate::collateral::{
+        tests::{
+            der_sequence, qe_identity_body, sign, sign_with, signed_qe_identity_document,
+            test_pck_cert_chain, TestRootCa, TEST_INTERMEDIATE_KEY, TEST_LEAF_KEY,
+        },
+        EnclaveIdentity,
+    };
+    use crate::quote::{tests::build_quote, EnclaveReportBody, CERT_DATA_TYPE_PCK_CERT_CHAIN};
+    use crate::tcb::{QeTcbLevel, TcbLevel};
+    use sha2::{Digest, Sha256};
+
+    // within the validity period of the certificates of `assets/pck_cert_chain.pem`
+    const TIMESTAMP: u64 = 1700000000;
+
+    // verifies the certificates of `test_pck_cert_chain`
+    type TestCircuit = SgxDcapVerifierCircuit<Fr, TestRootCa<SgxDcapCircuitParams>>;
+
+    // QE report taken from a real SGX quote, matching `qe_identity_body`
+    const QE_REPORT: [u8; 384] = [
+        8, 9, 14, 13, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 21, 0, 0, 0, 0, 0, 0, 0, 231,
+        0, 0, 0, 0, 0, 0, 0, 206, 29, 168, 154, 193, 245, 74, 128, 114, 87, 196, 229, 124, 120, 20,
+        12, 188, 102, 82, 212, 213, 135, 214, 15, 5, 131, 18, 90, 39, 146, 190, 112, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 140, 79,
+        87, 117, 215, 150, 80, 62, 150, 19, 127, 119, 198, 138, 130, 154, 0, 86, 172, 141, 237,
+        112, 20, 11, 8, 27, 9, 68, 144, 197, 123, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 9, 188, 124, 79,
+        211, 205, 227, 97, 238, 49, 224, 32, 91, 56, 220, 72, 241, 138, 165, 234, 97, 86, 191, 147,
+        42, 38, 34, 143, 92, 197, 56, 135, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+
+    /// Builds a quote around the real QE report and `test_pck_cert_chain`, signing its ISV
+    /// enclave report with a fresh attestation key and its QE report with the PCK leaf key. With
+    /// `bind_attestation_key`, the report data of the QE report is set to the hash of the fresh
+    /// key, as the QE does.
+    fn build_signed_quote(bind_attestation_key: bool) -> Quote {
+        let mut quote = Quote::parse(&build_quote(
+            &EnclaveReportBody::default(),
+            &EnclaveReportBody::parse(&QE_REPORT).unwrap(),
+            &[0; 64],
+            CERT_DATA_TYPE_PCK_CERT_CHAIN,
+            &test_pck_cert_chain().concat(),
+        ))
+        .unwrap();
+        let (attestation_key, isv_enclave_report_signature) = sign(&quote.signed_bytes());
+        let signature = &mut quote.signature;
+        signature.ecdsa_attestation_key = attestation_key;
+        signature.isv_enclave_report_signature = isv_enclave_report_signature;
+        if bind_attestation_key {
+            let hash = Sha256::digest([&attestation_key[..], &signature.qe_auth_data].concat());
+            signature.qe_report.report_data[..32].copy_from_slice(&hash);
+        }
+        signature.qe_report_signature =
+            sign_with(Fq::from(TEST_LEAF_KEY), &signature.qe_report.to_bytes());
+        quote
+    }
 
     #[test]
     fn test_sgx_dcap_verifier_pass() {
-        let k = 17; // 8, 128, etc
-
-        // Convert query string to u128s
-        // "R0g=""
-        let characters: Vec<u8> = "MIIE8zCCBJmgAwIBAgIVANnqQ+J6On8k9DBBJWcJx3reEJy4MAoGCCqGSM49BAMCMHAxIjAgBgNVBAMMGUludGVsIFNHWCBQQ0sgUGxhdGZvcm0gQ0ExGjAYBgNVBAoMEUludGVsIENvcnBvcmF0aW9uMRQwEgYDVQQHDAtTYW50YSBDbGFyYTELMAkGA1UECAwCQ0ExCzAJBgNVBAYTAlVTMB4XDTIyMTEyODIyMDIxMFoXDTI5MTEyODIyMDIxMFowcDEiMCAGA1UEAwwZSW50ZWwgU0dYIFBDSyBDZXJ0aWZpY2F0ZTEaMBgGA1UECgwRSW50ZWwgQ29ycG9yYXRpb24xFDASBgNVBAcMC1NhbnRhIENsYXJhMQswCQYDVQQIDAJDQTELMAkGA1UEBhMCVVMwWTATBgcqhkjOPQIBBggqhkjOPQMBBwNCAAQpgwE5QRE1rf8lnFHnlBXzJdvJ2dTmZygl0KFrCmZ6GVSM8YoX26Yny98376CFJuXxEy869fyvMnSFoGGY6Vw9o4IDDjCCAwowHwYDVR0jBBgwFoAUlW9dzb0b4elAScnU9DPOAVcL3lQwawYDVR0fBGQwYjBgoF6gXIZaaHR0cHM6Ly9hcGkudHJ1c3RlZHNlcnZpY2VzLmludGVsLmNvbS9zZ3gvY2VydGlmaWNhdGlvbi92My9wY2tjcmw/Y2E9cGxhdGZvcm0mZW5jb2Rpbmc9ZGVyMB0GA1UdDgQWBBQAE57yu4XMyfNOmuKqnPmlWDwjETAOBgNVHQ8BAf8EBAMCBsAwDAYDVR0TAQH/BAIwADCCAjsGCSqGSIb4TQENAQSCAiwwggIoMB4GCiqGSIb4TQENAQEEEEQrDHfHzNZ3gmSih7cpm9swggFlBgoqhkiG+E0BDQECMIIBVTAQBgsqhkiG+E0BDQECAQIBBzAQBgsqhkiG+E0BDQECAgIBCTAQBgsqhkiG+E0BDQECAwIBAzAQBgsqhkiG+E0BDQECBAIBAzARBgsqhkiG+E0BDQECBQICAP8wEQYLKoZIhvhNAQ0BAgYCAgD/MBAGCyqGSIb4TQENAQIHAgEBMBAGCyqGSIb4TQENAQIIAgEAMBAGCyqGSIb4TQENAQIJAgEAMBAGCyqGSIb4TQENAQIKAgEAMBAGCyqGSIb4TQENAQILAgEAMBAGCyqGSIb4TQENAQIMAgEAMBAGCyqGSIb4TQENAQINAgEAMBAGCyqGSIb4TQENAQIOAgEAMBAGCyqGSIb4TQENAQIPAgEAMBAGCyqGSIb4TQENAQIQAgEAMBAGCyqGSIb4TQENAQIRAgENMB8GCyqGSIb4TQENAQISBBAHCQMD//8BAAAAAAAAAAAAMBAGCiqGSIb4TQENAQMEAgAAMBQGCiqGSIb4TQENAQQEBgBgagAAADAPBgoqhkiG+E0BDQEFCgEBMB4GCiqGSIb4TQENAQYEEHGGXU24gBumawNX8L7XcfEwRAYKKoZIhvhNAQ0BBzA2MBAGCyqGSIb4TQENAQcBAQH/MBAGCyqGSIb4TQENAQcCAQEAMBAGCyqGSIb4TQENAQcDAQEAMAoGCCqGSM49BAMCA0gAMEUCIQC5Jc5Gr9eeJKD9ZkN2l/AHeqDKuog01EOSL6obVJTPowIgbJ8WKzefyUxwbaRQVruhFvo6T9TJzwk4JokWgGnDybI="
-            .chars()
-            .map(|c| c as u32 as u8)
-            .collect();
-
-        // Decode characters
-        assert_eq!(characters.len(), SHAHASH_BASE64_STRING_LEN);
-        // #[allow(deprecated)]
-        // let chars: Vec<char> = base64::decode(characters.clone())
-        //     .unwrap()
-        //     .iter()
-        //     .map(|&b| b as char)
-        //     .collect();
-        // print!("Decoded chars: {:?}", chars);
-
-        // Successful cases
-        let circuit = SgxDcapVerifierCircuit::<Fr> {
-            sgx_dcap_verifier_encoded_string: characters,
-            _marker: PhantomData,
-        };
-
-        let prover = match MockProver::run(k, &circuit, vec![]) {
-            Ok(prover) => prover,
-            Err(e) => panic!("Error: {:?}", e),
-        };
-        prover.assert_satisfied();
-        // CircuitCost::<G1, SgxDcapVerifierCircuit<Fr>>::measure((k as u128).try_into().unwrap(), &circuit);
-        // .proof_size(2);
+        let k = SgxDcapCircuitParams::params().degree;
+
+        // the QE Identity, the TCB levels of the platform and a PCK CRL that does not revoke the
+        // PCK certificate, as served by the PCS
+        let qe_identity =
+            signed_qe_identity_document(&qe_identity_body(), Fq::from(TEST_INTERMEDIATE_KEY));
+        let circuit = TestCircuit::new(build_signed_quote(true), TIMESTAMP)
+            .unwrap()
+            .with_qe_identity(
+                EnclaveIdentity::parse(qe_identity.as_bytes())
+                    .unwrap()
+                    .identity,
+            );
+        let (leaf, sgx_extension) = circuit.leaf_sgx_extension().unwrap();
+        let pck_tcb = sgx_extension.pck_tcb(&leaf).unwrap();
+        let tcb_levels = TcbLevels::new(vec![
+            TcbLevel {
+                pce_svn: pck_tcb.pce_svn + 1,
+                status: TcbStatus::UpToDate,
+                ..Default::default()
+            },
+            TcbLevel {
+                sgx_tcb_components: pck_tcb.sgx_tcb_components,
+                pce_svn: pck_tcb.pce_svn,
+                status: TcbStatus::SWHardeningNeeded,
+            },
+        ])
+        .unwrap();
+        let mut revoked = circuit.leaf_serial_number().unwrap();
+        revoked[SERIAL_NUMBER_LEN - 1] ^= 1;
+        let circuit = circuit
+            .with_tcb_levels(tcb_levels)
+            .with_pck_crl(&RevokedSerials::new(&[revoked]).unwrap())
+            .unwrap();
+
+        let instances = circuit.instances();
+        assert_eq!(
+            instances[TCB_STATUS_INSTANCE],
+            Fr::from(TcbStatus::SWHardeningNeeded.code() as u64)
+        );
+        // the QE's ISVSVN 9 is at the UpToDate level
+        assert_eq!(
+            instances[QE_TCB_STATUS_INSTANCE],
+            Fr::from(TcbStatus::UpToDate.code() as u64)
+        );
+        MockProver::run(k, &circuit, vec![instances.clone()])
+            .unwrap()
+            .assert_satisfied();
 
         let params_time = start_timer!(|| "Time elapsed in circuit & params construction");
         let params = gen_srs(k);
@@ -817,8 +3080,15 @@ mod tests {
             Challenge255<G1Affine>,
             _,
             Blake2bWrite<Vec<u8>, G1Affine, Challenge255<G1Affine>>,
-            SgxDcapVerifierCircuit<Fr>,
-        >(&params, &pk, &[circuit], &[&[]], &mut rng, &mut transcript)
+            TestCircuit,
+        >(
+            &params,
+            &pk,
+            &[circuit],
+            &[&[&instances]],
+            &mut rng,
+            &mut transcript,
+        )
         .unwrap();
         let proof = transcript.finalize();
         end_timer!(proof_time);
@@ -837,12 +3107,261 @@ mod tests {
             verifier_params,
             pk.get_vk(),
             strategy,
-            &[&[]],
+            &[&[&instances]],
             &mut transcript
         )
         .is_ok());
         end_timer!(verify_time);
+    }
+
+    #[test]
+    fn test_sgx_dcap_verifier_instances() {
+        let mut quote = Quote::default();
+        quote.isv_enclave_report.mr_signer = [0xff; 32];
+        quote.isv_enclave_report.isv_prod_id = 0x0102;
+        quote.isv_enclave_report.report_data[63] = 7;
+        let circuit = SgxDcapVerifierCircuit::<Fr> {
+            pck_cert_chain: PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem"))
+                .unwrap(),
+            quote,
+            allowlist: None,
+            tcb_levels: TcbLevels::default(),
+            qe_identity: QeIdentity::default(),
+            pck_crl: NonRevocationProof::default(),
+            timestamp: 0,
+            _marker: PhantomData,
+        }
+        .with_pck_crl(&RevokedSerials::new(&[]).unwrap())
+        .unwrap();
+
+        let instances = circuit.instances();
+        assert_eq!(instances.len(), SGX_NUM_INSTANCES);
+        assert_eq!(instances[MR_ENCLAVE_INSTANCE], Fr::zero());
+        assert_eq!(instances[MR_SIGNER_INSTANCE], Fr::from_u128(u128::MAX),);
+        assert_eq!(instances[ISV_PROD_ID_INSTANCE], Fr::from(0x0102));
+        assert_eq!(instances[REPORT_DATA_INSTANCE + 3], Fr::from(7));
+        assert_eq!(instances[FMSPC_INSTANCE], Fr::from(0x00606a000000));
+        assert_eq!(instances[PCE_ID_INSTANCE], Fr::zero());
+        assert_eq!(
+            instances[TCB_STATUS_INSTANCE],
+            Fr::from(TcbStatus::Unrecognized.code() as u64)
+        );
+
+        // the fixture's PCK certificate has PCESVN 13
+        let tcb_levels = TcbLevels::new(vec![
+            TcbLevel {
+                pce_svn: 14,
+                status: TcbStatus::UpToDate,
+                ..Default::default()
+            },
+            TcbLevel {
+                pce_svn: 13,
+                status: TcbStatus::OutOfDate,
+                ..Default::default()
+            },
+        ])
+        .unwrap();
+        let circuit = circuit.with_tcb_levels(tcb_levels.clone());
+        let instances = circuit.instances();
+        assert_eq!(
+            instances[TCB_LEVELS_INSTANCE..TCB_LEVELS_INSTANCE + 2],
+            pack_instances::<Fr>(&tcb_levels.commitment())
+        );
+        assert_eq!(
+            instances[TCB_STATUS_INSTANCE],
+            Fr::from(TcbStatus::OutOfDate.code() as u64)
+        );
+
+        // the QE's ISVSVN is evaluated against the QE Identity
+        let qe_identity = QeIdentity::new(
+            [0; 4],
+            [0; 4],
+            [0; 16],
+            [0; 16],
+            [0; 32],
+            0,
+            vec![QeTcbLevel {
+                isv_svn: 0,
+                status: TcbStatus::SWHardeningNeeded,
+            }],
+        )
+        .unwrap();
+        let circuit = circuit.with_qe_identity(qe_identity.clone());
+        let instances = circuit.instances();
+        assert_eq!(
+            instances[QE_IDENTITY_INSTANCE..QE_IDENTITY_INSTANCE + 2],
+            pack_instances::<Fr>(&qe_identity.commitment())
+        );
+        assert_eq!(
+            instances[QE_TCB_STATUS_INSTANCE],
+            Fr::from(TcbStatus::SWHardeningNeeded.code() as u64)
+        );
+
+        // the root of the PCK CRL is exposed, a revoked PCK certificate has no proof
+        let serial = circuit.leaf_serial_number().unwrap();
+        let mut other_serial = serial;
+        other_serial[SERIAL_NUMBER_LEN - 1] ^= 1;
+        let pck_crl = RevokedSerials::new(&[other_serial]).unwrap();
+        let circuit = circuit.with_pck_crl(&pck_crl).unwrap();
+        let instances = circuit.instances();
+        assert_eq!(
+            instances[PCK_CRL_INSTANCE..PCK_CRL_INSTANCE + 2],
+            pack_instances::<Fr>(&pck_crl.root())
+        );
+        let revoked = RevokedSerials::new(&[other_serial, serial]).unwrap();
+        assert!(circuit.clone().with_pck_crl(&revoked).is_err());
+
+        // the timestamp the certificate chain is valid at is exposed
+        let circuit = SgxDcapVerifierCircuit {
+            timestamp: TIMESTAMP,
+            ..circuit
+        };
+        let instances = circuit.instances();
+        assert_eq!(instances[TIMESTAMP_INSTANCE], Fr::from(TIMESTAMP));
+
+        // the allowlist root takes the place of MRSIGNER
+        let allowlist = Allowlist::new(&[[0x11; 32], [0xff; 32]]).unwrap();
+        let circuit = circuit
+            .with_allowlist(AllowlistTarget::MrSigner, &allowlist)
+            .unwrap();
+        let policy_instances = circuit.instances();
+        assert_eq!(
+            policy_instances[MR_SIGNER_INSTANCE..MR_SIGNER_INSTANCE + 2],
+            pack_instances::<Fr>(&allowlist.root())
+        );
+        assert_eq!(
+            policy_instances[ISV_PROD_ID_INSTANCE..],
+            instances[ISV_PROD_ID_INSTANCE..]
+        );
+        assert!(circuit
+            .with_allowlist(AllowlistTarget::MrEnclave, &allowlist)
+            .is_err());
+    }
 
-        // Assert the 33rd pos is 0
+    #[test]
+    fn test_sgx_dcap_verifier_unbound_attestation_key() {
+        let k = SgxDcapCircuitParams::params().degree;
+
+        // every signature verifies, but the QE report was issued for another attestation key
+        let circuit = TestCircuit::new(build_signed_quote(false), TIMESTAMP).unwrap();
+        let prover = MockProver::run(k, &circuit, vec![circuit.instances()]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sgx_dcap_verifier_wrong_qe_report_signature() {
+        let k = SgxDcapCircuitParams::params().degree;
+
+        // the QE report binds the attestation key, but its signature is for the real report data
+        let mut quote = build_signed_quote(true);
+        quote.signature.qe_report_signature = sign_with(Fq::from(TEST_LEAF_KEY), &QE_REPORT);
+        let circuit = TestCircuit::new(quote, TIMESTAMP).unwrap();
+        let prover = MockProver::run(k, &circuit, vec![circuit.instances()]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn test_sgx_dcap_verifier_vk_independent_of_cert_lengths() {
+        let k = SgxDcapCircuitParams::params().degree;
+        let params = gen_srs(k);
+
+        let circuit = TestCircuit::new(build_signed_quote(true), TIMESTAMP).unwrap();
+        // the intermediate certificate is shorter than the leaf and has other field offsets
+        let chain = &circuit.pck_cert_chain;
+        let shorter = SgxDcapVerifierCircuit {
+            pck_cert_chain: PckCertChain {
+                leaf: chain.intermediate.clone(),
+                leaf_pem: chain.intermediate_pem.clone(),
+                ..chain.clone()
+            },
+            ..circuit.clone()
+        };
+        assert_ne!(
+            CertLayout::from_base64(&chain.leaf).unwrap().1,
+            CertLayout::from_base64(&shorter.pck_cert_chain.leaf).unwrap().1
+        );
+
+        let pinned =
+            |circuit: &TestCircuit| format!("{:?}", keygen_vk(&params, circuit).unwrap().pinned());
+        let vk = pinned(&circuit);
+        assert_eq!(vk, pinned(&shorter));
+        assert_eq!(vk, pinned(&circuit.without_witnesses()));
+    }
+
+    #[test]
+    fn test_sgx_dcap_verifier_oversized_cert() {
+        let k = SgxDcapCircuitParams::params().degree;
+
+        // the leaf TBSCertificate grows by a trailing OCTET STRING past its sha256 input size
+        let circuit = TestCircuit::new(build_signed_quote(true), TIMESTAMP).unwrap();
+        let instances = circuit.instances();
+        let (der, layout) = CertLayout::from_base64(&circuit.pck_cert_chain.leaf).unwrap();
+        let padding = [&[0x04, 0x82, 0x00, 0xc8][..], &[0; 0xc8]].concat();
+        let tbs = der_sequence(&[&der[layout.tbs.start + 4..layout.tbs.end], &padding].concat());
+        let der = der_sequence(&[&tbs, &der[layout.tbs.end..]].concat());
+        assert!(CertLayout::parse(&der).unwrap().tbs.len() > 1280);
+
+        let leaf = general_purpose::STANDARD.encode(&der).into_bytes();
+        let circuit = SgxDcapVerifierCircuit {
+            pck_cert_chain: PckCertChain {
+                leaf_pem: crate::pem::to_pem(&leaf),
+                leaf,
+                ..circuit.pck_cert_chain.clone()
+            },
+            ..circuit
+        };
+        assert!(MockProver::run(k, &circuit, vec![instances]).is_err());
+    }
+
+    #[test]
+    fn test_circuit_params() {
+        let params = CircuitParams::default();
+        assert_eq!((params.limb_bits, params.num_limbs), (88, 3));
+        let json = serde_json::to_vec(&params).unwrap();
+        assert_eq!(
+            CircuitParams::from_json(&json).unwrap().degree,
+            params.degree
+        );
+
+        // 3 limbs of 80 bits do not hold secp256r1 field elements
+        let narrow = CircuitParams {
+            limb_bits: 80,
+            ..params.clone()
+        };
+        assert!(narrow.validate().is_err());
+        // invalid parameters are returned as errors rather than configured
+        assert!(SgxDcapVerifierConfig::<Fr>::configure_with_params(
+            &mut ConstraintSystem::default(),
+            &narrow,
+            vec![64],
+            128
+        )
+        .is_err());
+        // the lookup table takes more rows than the circuit has
+        let small = CircuitParams {
+            degree: params.lookup_bits as u32,
+            ..params
+        };
+        assert!(CircuitParams::from_json(&serde_json::to_vec(&small).unwrap()).is_err());
+
+        // circuits configure their chips with the parameters of their type
+        #[derive(Default, Clone)]
+        struct WideParams;
+        impl CircuitParamsSpec for WideParams {
+            fn params() -> CircuitParams {
+                CircuitParams {
+                    num_advice: 8,
+                    ..CircuitParams::default()
+                }
+            }
+        }
+        let config = <SgxDcapVerifierCircuit<Fr, WideParams> as Circuit<Fr>>::configure(
+            &mut ConstraintSystem::default(),
+        );
+        assert_eq!(config.params(), &WideParams::params());
+        let config = <SgxDcapVerifierCircuit<Fr> as Circuit<Fr>>::configure(
+            &mut ConstraintSystem::default(),
+        );
+        assert_eq!(config.params(), &SgxDcapCircuitParams::params());
     }
 }
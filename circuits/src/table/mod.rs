@@ -4,24 +4,37 @@ use halo2_base::halo2_proofs::{
 };
 use halo2_base::utils::PrimeField;
 
-
 use std::marker::PhantomData;
 
-
 pub const BIT_COLUMN_COUNT: u8 = 4;
 
+/// Alphabet of the base64 strings looked up in a `BitDecompositionTableConfig`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Base64Alphabet {
+    /// '+' and '/' for the values 62 and 63, padded with '=' (RFC 4648 section 4)
+    #[default]
+    Standard,
+    /// '-' and '_' for the values 62 and 63, without padding (RFC 4648 section 5), as in JWTs
+    /// and WebAuthn clientDataJSON
+    UrlSafe,
+}
+
 /// A lookup table of values from 0..RANGE.
 #[derive(Debug, Clone, Copy)]
-pub(super) struct BitDecompositionTableConfig<F: PrimeField> {
-    pub(super) character: TableColumn, // This is the pre-mapped 6 bit character in ASCII (i.e. a-z, A-Z, 0-9, +, /)
-    pub(super) value_encoded: TableColumn, // This is the 6 bit value that each of the above character maps to from 0-64
-    pub(super) value_decoded: TableColumn, // This is the 8 bit value that you get after decoding the base64 values
-    pub(super) bit_decompositions: [TableColumn; BIT_COLUMN_COUNT as usize],
+pub struct BitDecompositionTableConfig<F: PrimeField> {
+    pub(crate) character: TableColumn, // This is the pre-mapped 6 bit character in ASCII (i.e. a-z, A-Z, 0-9, +, /)
+    pub(crate) value_encoded: TableColumn, // This is the 6 bit value that each of the above character maps to from 0-64
+    pub(crate) value_decoded: TableColumn, // This is the 8 bit value that you get after decoding the base64 values
+    pub(crate) bit_decompositions: [TableColumn; BIT_COLUMN_COUNT as usize],
+    pub(crate) alphabet: Base64Alphabet,
     _marker: PhantomData<F>,
 }
 
 impl<F: PrimeField> BitDecompositionTableConfig<F> {
-    pub(super) fn configure(meta: &mut ConstraintSystem<F>) -> Self {
+    /// The table relates the characters of `alphabet` to their 6-bit values, and bytes to their
+    /// 2-bit decompositions, so lookups into it prove both the decoding of a base64 string and
+    /// the encoding of a byte string
+    pub fn configure(meta: &mut ConstraintSystem<F>, alphabet: Base64Alphabet) -> Self {
         let character = meta.lookup_table_column();
         let value_encoded = meta.lookup_table_column();
         let value_decoded = meta.lookup_table_column();
@@ -35,41 +48,54 @@ impl<F: PrimeField> BitDecompositionTableConfig<F> {
             value_encoded,
             value_decoded,
             bit_decompositions: bit_decompositions.try_into().unwrap(),
+            alphabet,
             _marker: PhantomData,
         }
     }
 
-    pub(super) fn map_encoded_value_to_character(&self, value_encoded: u8) -> char {
+    pub fn map_encoded_value_to_character(&self, value_encoded: u8) -> char {
         match value_encoded {
             0..=25 => (value_encoded + 65) as char,
             26..=51 => (value_encoded + 71) as char,
             52..=61 => (value_encoded - 4) as char,
-            62 => '+',
-            63 => '/',
+            62 if self.alphabet == Base64Alphabet::Standard => '+',
+            63 if self.alphabet == Base64Alphabet::Standard => '/',
+            62 => '-',
+            63 => '_',
             _ => panic!("Invalid value_encoded value"),
         }
     }
 
-    pub(super) fn map_character_to_encoded_value(&self, character: char) -> u8 {
-        match character {
-            '=' => 0,
-            'A'..='Z' => character as u8 - 65,
-            'a'..='z' => character as u8 - 71,
-            '0'..='9' => character as u8 + 4,
-            '+' => 62,
-            '/' => 63,
+    // '=' shares the value of 'A', its positions are constrained by `Base64DecodeChip`
+    pub fn map_character_to_encoded_value(&self, character: char) -> u8 {
+        match (self.alphabet, character) {
+            (Base64Alphabet::Standard, '=') => 0,
+            (_, 'A'..='Z') => character as u8 - 65,
+            (_, 'a'..='z') => character as u8 - 71,
+            (_, '0'..='9') => character as u8 + 4,
+            (Base64Alphabet::Standard, '+') | (Base64Alphabet::UrlSafe, '-') => 62,
+            (Base64Alphabet::Standard, '/') | (Base64Alphabet::UrlSafe, '_') => 63,
             _ => panic!("Invalid character"),
         }
     }
 
-    pub(super) fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
+    /// Character of the top row of the character table, which maps to 0 like 'A'. Only the
+    /// standard alphabet has a padding character.
+    fn padding_character(&self) -> char {
+        match self.alphabet {
+            Base64Alphabet::Standard => '=',
+            Base64Alphabet::UrlSafe => 'A',
+        }
+    }
+
+    pub fn load(&self, layouter: &mut impl Layouter<F>) -> Result<(), Error> {
         const OFFSET: usize = 1; // How many special characters we add into rows at the top (just = as 0)
 
         layouter.assign_table(
             || "load 64 bit table",
             |mut table| {
                 // Assign ASCII characters and corresponding value_encoded values
-                // Special case = to be 0
+                // Special case the padding character to be 0
                 table.assign_cell(
                     || "value_encoded",
                     self.value_encoded,
@@ -77,12 +103,12 @@ impl<F: PrimeField> BitDecompositionTableConfig<F> {
                     || Value::known(F::from_u128(0_u128)),
                 )?;
                 // Assign each character value that corresponds to its base64 encoded value
-                const equal: char = '=';
+                let padding = self.padding_character();
                 table.assign_cell(
                     || "character",
                     self.character,
                     0,
-                    || Value::known(F::from_u128(equal as u128)),
+                    || Value::known(F::from_u128(padding as u128)),
                 )?;
 
                 for value_encoded in 0..64 {
@@ -0,0 +1,382 @@
+//! Host-side TCB levels of Intel's TCBInfo and their evaluation against the TCB of a PCK
+//! certificate.
+//!
+//! The levels are a witness of the circuit, committed to by the SHA256 of their fixed-size
+//! encoding: `MAX_TCB_LEVELS` levels of the 16 SGX TCB components, the big-endian PCESVN and the
+//! status code each. Missing levels are all-zero levels with status `Unrecognized`, which match
+//! any TCB, so a TCB below every level of the TCBInfo evaluates to `Unrecognized`.
+//!
+//! The QE Identity is committed to the same way: its masked MISCSELECT and attributes, MRSIGNER,
+//! the big-endian ISVPRODID and `MAX_QE_TCB_LEVELS` levels of the big-endian ISVSVN and status
+//! code, padded with zero levels of status `Unrecognized`.
+use anyhow::{anyhow, ensure, Result};
+use sha2::{Digest, Sha256};
+use std::str::FromStr;
+
+use crate::quote::EnclaveReportBody;
+use crate::x509::{PckTcb, SGX_TCB_COMPONENTS};
+
+pub const MAX_TCB_LEVELS: usize = 32;
+/// Encoded length of a TCB level: SGX TCB components, PCESVN and status code
+pub(crate) const TCB_LEVEL_LEN: usize = SGX_TCB_COMPONENTS + 3;
+/// Max sha256 input size of the encoded TCB levels
+pub(crate) const TCB_LEVELS_MAX_BYTE_SIZE: usize = 704;
+pub const MAX_QE_TCB_LEVELS: usize = 16;
+/// Encoded length of a QE TCB level: ISVSVN and status code
+pub(crate) const QE_TCB_LEVEL_LEN: usize = 3;
+/// Encoded length of the QE Identity without its TCB levels
+pub(crate) const QE_IDENTITY_LEN: usize = 4 + 4 + 16 + 16 + 32 + 2;
+/// Max sha256 input size of the encoded QE Identity
+pub(crate) const QE_IDENTITY_MAX_BYTE_SIZE: usize = 192;
+
+/// Status of a TCB level, the discriminant is the status code exposed by the circuit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum TcbStatus {
+    UpToDate = 0,
+    SWHardeningNeeded = 1,
+    ConfigurationNeeded = 2,
+    ConfigurationAndSWHardeningNeeded = 3,
+    OutOfDate = 4,
+    OutOfDateConfigurationNeeded = 5,
+    Revoked = 6,
+    /// The TCB is below every level of the TCBInfo
+    #[default]
+    Unrecognized = 7,
+}
+
+impl TcbStatus {
+    pub fn code(self) -> u8 {
+        self as u8
+    }
+
+    pub fn from_code(code: u8) -> Result<Self> {
+        Ok(match code {
+            0 => Self::UpToDate,
+            1 => Self::SWHardeningNeeded,
+            2 => Self::ConfigurationNeeded,
+            3 => Self::ConfigurationAndSWHardeningNeeded,
+            4 => Self::OutOfDate,
+            5 => Self::OutOfDateConfigurationNeeded,
+            6 => Self::Revoked,
+            7 => Self::Unrecognized,
+            _ => return Err(anyhow!("invalid TCB status code {}", code)),
+        })
+    }
+
+    /// The `tcbStatus` string of the status in TCBInfo
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::UpToDate => "UpToDate",
+            Self::SWHardeningNeeded => "SWHardeningNeeded",
+            Self::ConfigurationNeeded => "ConfigurationNeeded",
+            Self::ConfigurationAndSWHardeningNeeded => "ConfigurationAndSWHardeningNeeded",
+            Self::OutOfDate => "OutOfDate",
+            Self::OutOfDateConfigurationNeeded => "OutOfDateConfigurationNeeded",
+            Self::Revoked => "Revoked",
+            Self::Unrecognized => "Unrecognized",
+        }
+    }
+}
+
+impl FromStr for TcbStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        (0..=Self::Unrecognized.code())
+            .map(|code| Self::from_code(code).unwrap())
+            .find(|status| status.name() == name)
+            .ok_or_else(|| anyhow!("unknown TCB status {}", name))
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcbLevel {
+    pub sgx_tcb_components: [u8; SGX_TCB_COMPONENTS],
+    pub pce_svn: u16,
+    pub status: TcbStatus,
+}
+
+impl TcbLevel {
+    /// Whether every component of `tcb` is greater than or equal to the level's
+    pub fn matches(&self, tcb: &PckTcb) -> bool {
+        self.sgx_tcb_components
+            .iter()
+            .zip(tcb.sgx_tcb_components.iter())
+            .all(|(level, component)| component >= level)
+            && tcb.pce_svn >= self.pce_svn
+    }
+
+    pub fn to_bytes(&self) -> [u8; TCB_LEVEL_LEN] {
+        let mut bytes = [0; TCB_LEVEL_LEN];
+        bytes[..SGX_TCB_COMPONENTS].copy_from_slice(&self.sgx_tcb_components);
+        bytes[SGX_TCB_COMPONENTS..SGX_TCB_COMPONENTS + 2]
+            .copy_from_slice(&self.pce_svn.to_be_bytes());
+        bytes[SGX_TCB_COMPONENTS + 2] = self.status.code();
+        bytes
+    }
+}
+
+/// The TCB levels of a TCBInfo, in its order, i.e. from the highest to the lowest
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TcbLevels {
+    levels: Vec<TcbLevel>,
+}
+
+impl TcbLevels {
+    pub fn new(levels: Vec<TcbLevel>) -> Result<Self> {
+        ensure!(
+            levels.len() <= MAX_TCB_LEVELS,
+            "TCBInfo holds at most {} TCB levels",
+            MAX_TCB_LEVELS
+        );
+        Ok(Self { levels })
+    }
+
+    pub fn levels(&self) -> &[TcbLevel] {
+        &self.levels
+    }
+
+    /// Fixed-size encoding of the levels, padded to `MAX_TCB_LEVELS`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let padding = vec![TcbLevel::default(); MAX_TCB_LEVELS - self.levels.len()];
+        self.levels
+            .iter()
+            .chain(padding.iter())
+            .flat_map(|level| level.to_bytes())
+            .collect()
+    }
+
+    /// SHA256 of the encoded levels, exposed by the circuit
+    pub fn commitment(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+
+    /// Status of the first level matched by `tcb`
+    pub fn evaluate(&self, tcb: &PckTcb) -> TcbStatus {
+        self.levels
+            .iter()
+            .find(|level| level.matches(tcb))
+            .map_or(TcbStatus::Unrecognized, |level| level.status)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QeTcbLevel {
+    pub isv_svn: u16,
+    pub status: TcbStatus,
+}
+
+impl QeTcbLevel {
+    pub fn to_bytes(&self) -> [u8; QE_TCB_LEVEL_LEN] {
+        let [hi, lo] = self.isv_svn.to_be_bytes();
+        [hi, lo, self.status.code()]
+    }
+}
+
+/// The identity of Intel's Quoting Enclave and its TCB levels, from the highest to the lowest.
+/// MISCSELECT is in the byte order of the report.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QeIdentity {
+    pub misc_select: [u8; 4],
+    pub misc_select_mask: [u8; 4],
+    pub attributes: [u8; 16],
+    pub attributes_mask: [u8; 16],
+    pub mr_signer: [u8; 32],
+    pub isv_prod_id: u16,
+    tcb_levels: Vec<QeTcbLevel>,
+}
+
+impl QeIdentity {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        misc_select: [u8; 4],
+        misc_select_mask: [u8; 4],
+        attributes: [u8; 16],
+        attributes_mask: [u8; 16],
+        mr_signer: [u8; 32],
+        isv_prod_id: u16,
+        tcb_levels: Vec<QeTcbLevel>,
+    ) -> Result<Self> {
+        ensure!(
+            tcb_levels.len() <= MAX_QE_TCB_LEVELS,
+            "QE Identity holds at most {} TCB levels",
+            MAX_QE_TCB_LEVELS
+        );
+        Ok(Self {
+            misc_select,
+            misc_select_mask,
+            attributes,
+            attributes_mask,
+            mr_signer,
+            isv_prod_id,
+            tcb_levels,
+        })
+    }
+
+    pub fn tcb_levels(&self) -> &[QeTcbLevel] {
+        &self.tcb_levels
+    }
+
+    /// Fixed-size encoding of the identity, its levels padded to `MAX_QE_TCB_LEVELS`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        bytes.extend(self.misc_select);
+        bytes.extend(self.misc_select_mask);
+        bytes.extend(self.attributes);
+        bytes.extend(self.attributes_mask);
+        bytes.extend(self.mr_signer);
+        bytes.extend(self.isv_prod_id.to_be_bytes());
+        let padding = vec![QeTcbLevel::default(); MAX_QE_TCB_LEVELS - self.tcb_levels.len()];
+        for level in self.tcb_levels.iter().chain(padding.iter()) {
+            bytes.extend(level.to_bytes());
+        }
+        bytes
+    }
+
+    /// SHA256 of the encoded identity, exposed by the circuit
+    pub fn commitment(&self) -> [u8; 32] {
+        Sha256::digest(self.to_bytes()).into()
+    }
+
+    /// Whether the QE report matches the identity: masked MISCSELECT and attributes, MRSIGNER
+    /// and ISVPRODID
+    pub fn matches(&self, qe_report: &EnclaveReportBody) -> bool {
+        let masked_equal = |value: &[u8], expected: &[u8], mask: &[u8]| {
+            value
+                .iter()
+                .zip(expected.iter())
+                .zip(mask.iter())
+                .all(|((value, expected), mask)| value & mask == expected & mask)
+        };
+        masked_equal(
+            &qe_report.misc_select.to_le_bytes(),
+            &self.misc_select,
+            &self.misc_select_mask,
+        ) && masked_equal(
+            &qe_report.attributes,
+            &self.attributes,
+            &self.attributes_mask,
+        ) && qe_report.mr_signer == self.mr_signer
+            && qe_report.isv_prod_id == self.isv_prod_id
+    }
+
+    /// Status of the first level whose ISVSVN is less than or equal to the QE's
+    pub fn evaluate(&self, isv_svn: u16) -> TcbStatus {
+        self.tcb_levels
+            .iter()
+            .find(|level| isv_svn >= level.isv_svn)
+            .map_or(TcbStatus::Unrecognized, |level| level.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate_tcb_levels() {
+        // TCB of the PCK certificate in assets/pck_cert_chain.pem
+        let tcb = PckTcb {
+            sgx_tcb_components: [7, 9, 3, 3, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+            pce_svn: 13,
+        };
+        let level = |component: u8, pce_svn: u16, status: TcbStatus| {
+            let mut sgx_tcb_components = tcb.sgx_tcb_components;
+            sgx_tcb_components[0] = component;
+            TcbLevel {
+                sgx_tcb_components,
+                pce_svn,
+                status,
+            }
+        };
+
+        let levels = TcbLevels::new(vec![
+            level(8, 13, TcbStatus::UpToDate),
+            level(7, 14, TcbStatus::SWHardeningNeeded),
+            level(7, 13, TcbStatus::OutOfDate),
+            level(0, 0, TcbStatus::Revoked),
+        ])
+        .unwrap();
+        assert_eq!(levels.evaluate(&tcb), TcbStatus::OutOfDate);
+        assert_eq!(levels.to_bytes().len(), MAX_TCB_LEVELS * TCB_LEVEL_LEN);
+        assert_eq!(levels.to_bytes()[TCB_LEVEL_LEN - 1], 0);
+
+        // a TCB below every level
+        let levels = TcbLevels::new(vec![level(8, 13, TcbStatus::UpToDate)]).unwrap();
+        assert_eq!(levels.evaluate(&tcb), TcbStatus::Unrecognized);
+        assert_eq!(TcbLevels::default().evaluate(&tcb), TcbStatus::Unrecognized);
+        assert_ne!(levels.commitment(), TcbLevels::default().commitment());
+
+        assert!(TcbLevels::new(vec![TcbLevel::default(); MAX_TCB_LEVELS + 1]).is_err());
+        assert_eq!(
+            TcbStatus::from_code(TcbStatus::Revoked.code()).unwrap(),
+            TcbStatus::Revoked
+        );
+        assert!(TcbStatus::from_code(8).is_err());
+        assert_eq!(
+            "SWHardeningNeeded".parse::<TcbStatus>().unwrap(),
+            TcbStatus::SWHardeningNeeded
+        );
+        assert!("Unknown".parse::<TcbStatus>().is_err());
+    }
+
+    #[test]
+    fn test_evaluate_qe_identity() {
+        let qe_report = EnclaveReportBody {
+            misc_select: 0x0100,
+            attributes: [0x15; 16],
+            mr_signer: [0x8c; 32],
+            isv_prod_id: 1,
+            isv_svn: 8,
+            ..Default::default()
+        };
+        let identity = QeIdentity::new(
+            [0; 4],
+            [0xff, 0x00, 0xff, 0xff],
+            [0x11; 16],
+            [0xfb; 16],
+            [0x8c; 32],
+            1,
+            vec![
+                QeTcbLevel {
+                    isv_svn: 9,
+                    status: TcbStatus::UpToDate,
+                },
+                QeTcbLevel {
+                    isv_svn: 8,
+                    status: TcbStatus::OutOfDate,
+                },
+            ],
+        )
+        .unwrap();
+        assert!(identity.matches(&qe_report));
+        assert_eq!(identity.evaluate(qe_report.isv_svn), TcbStatus::OutOfDate);
+        assert_eq!(identity.evaluate(7), TcbStatus::Unrecognized);
+        assert_eq!(
+            identity.to_bytes().len(),
+            QE_IDENTITY_LEN + MAX_QE_TCB_LEVELS * QE_TCB_LEVEL_LEN
+        );
+
+        // bits outside of the masks are ignored, MRSIGNER is compared as a whole
+        let mut other = qe_report.clone();
+        other.attributes[0] = 0x11;
+        assert!(identity.matches(&other));
+        other.attributes[0] = 0x10;
+        assert!(!identity.matches(&other));
+        other = qe_report.clone();
+        other.mr_signer[31] = 0;
+        assert!(!identity.matches(&other));
+
+        assert!(QeIdentity::new(
+            [0; 4],
+            [0; 4],
+            [0; 16],
+            [0; 16],
+            [0; 32],
+            0,
+            vec![QeTcbLevel::default(); MAX_QE_TCB_LEVELS + 1],
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,400 @@
+use anyhow::{anyhow, ensure};
+use halo2_base::utils::PrimeField;
+use halo2_base::{
+    gates::GateInstructions,
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner, Value},
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    AssignedValue, Context, SKIP_FIRST_PASS,
+};
+use std::marker::PhantomData;
+
+use crate::collateral::{
+    TcbInfo, TcbInfoLayout, FMSPC_KEY, PCE_ID_KEY, PCE_SVN_KEY, TCB_LEVELS_KEY,
+};
+use crate::quote::pem_certificates;
+use crate::sgx_dcap_verifier::{
+    pack_instances, CircuitParams, CircuitParamsSpec, SgxDcapVerifierConfig,
+};
+use crate::tcb::{TCB_LEVELS_MAX_BYTE_SIZE, TCB_LEVEL_LEN};
+use crate::x509::CertLayout;
+
+/// Max sha256 input size of the signed `tcbInfo` body, which is hashed in-circuit as a whole.
+/// `MAX_TCB_LEVELS` levels shaped like the PCS's v3 ones, with the category and type of each
+/// component and up to 24 advisory IDs, take some 28 KB.
+pub const TCB_INFO_MAX_BYTE_SIZE: usize = 32768;
+
+// parameters of `TcbInfoCircuitParams`
+const TCB_INFO_CIRCUIT_PARAMS: &str = include_str!("configs/tcb_info_circuit.config");
+
+// Public instances of `TcbInfoVerifierCircuit`: FMSPC and PCEID, a single big-endian instance
+// each, then the SHA256 commitment to the TCB levels in 2 instances. They match the FMSPC, PCEID
+// and TCB levels instances of `SgxDcapVerifierCircuit`.
+pub const TCB_INFO_FMSPC_INSTANCE: usize = 0;
+pub const TCB_INFO_PCE_ID_INSTANCE: usize = 1;
+pub const TCB_INFO_TCB_LEVELS_INSTANCE: usize = 2;
+pub const TCB_INFO_NUM_INSTANCES: usize = 4;
+
+/// Takes FMSPC and PCEID from the signed `tcbInfo` body located by `layout`, packed into their
+/// instances, and constrains the TCB levels of the body to be the first `num_levels` of the
+/// encoded `tcb_levels`, see `SgxDcapVerifierConfig::json_tcb_levels`
+fn tcb_info_instances<'v, F: PrimeField>(
+    config: &SgxDcapVerifierConfig<F>,
+    ctx: &mut Context<'v, F>,
+    body: &[AssignedValue<'v, F>],
+    layout: &TcbInfoLayout,
+    tcb_levels: &[AssignedValue<'v, F>],
+    num_levels: usize,
+) -> Vec<AssignedValue<'v, F>> {
+    let mut instances = vec![];
+    for (field, key, len) in [
+        (&layout.fmspc, FMSPC_KEY, 12),
+        (&layout.pce_id, PCE_ID_KEY, 4),
+    ] {
+        let hex = config.json_string_field(ctx, body, field, key, len);
+        let bytes = config.parse_hex(ctx, &hex);
+        instances.extend(config.pack_bytes(ctx, &bytes));
+    }
+
+    let (array_start, bracket) =
+        config.json_field(ctx, body, layout.tcb_levels.offset(), TCB_LEVELS_KEY, 1);
+    config.constrain_bytes(ctx, &bracket, b"[");
+    let num_levels = config
+        .gate()
+        .load_witness(ctx, Value::known(F::from(num_levels as u64)));
+    config.json_tcb_levels(
+        ctx,
+        body,
+        &array_start,
+        PCE_SVN_KEY,
+        tcb_levels,
+        TCB_LEVEL_LEN,
+        &num_levels,
+    );
+    instances
+}
+
+/// The parameters of `src/configs/tcb_info_circuit.config`, the default ones of
+/// `TcbInfoVerifierCircuit`. The walk of the TCB levels takes a few hundred cells per byte of the
+/// body slot, over 13M cells on top of the 2 ECDSA verifications, which 10 advice columns of
+/// 2^21 rows hold.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TcbInfoCircuitParams;
+
+impl CircuitParamsSpec for TcbInfoCircuitParams {
+    fn params() -> CircuitParams {
+        CircuitParams::from_json(TCB_INFO_CIRCUIT_PARAMS.as_bytes())
+            .expect("built-in circuit params should be valid")
+    }
+}
+
+/// Verifies Intel's signature of a TCBInfo and commits to its TCB levels, with the ECDSA chips
+/// configured with the parameters of `P`
+#[derive(Default, Clone)]
+pub struct TcbInfoVerifierCircuit<F: PrimeField, P: CircuitParamsSpec = TcbInfoCircuitParams> {
+    // base64 body of the TCB Signing certificate, issued by Intel's root CA
+    pub tcb_signing_cert: Vec<u8>,
+    pub tcb_info: TcbInfo,
+    _marker: PhantomData<(F, P)>,
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> TcbInfoVerifierCircuit<F, P> {
+    // max sha256 input sizes in order of digest: TCB Signing TBSCertificate, tcbInfo body and
+    // encoded TCB levels
+    const MAX_BYTE_SIZES: [usize; 3] = [704, TCB_INFO_MAX_BYTE_SIZE, TCB_LEVELS_MAX_BYTE_SIZE];
+
+    /// `tcb_info_issuer_chain` is the PEM `SGX-TCB-Info-Issuer-Chain` served with the TCBInfo,
+    /// i.e. the TCB Signing certificate followed by the root CA certificate
+    pub fn new(tcb_info: TcbInfo, tcb_info_issuer_chain: &[u8]) -> anyhow::Result<Self> {
+        let tcb_signing_cert = pem_certificates(tcb_info_issuer_chain)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("TCB Info issuer chain is empty"))?;
+        ensure!(
+            tcb_info.body.len() + 9 <= TCB_INFO_MAX_BYTE_SIZE,
+            "tcbInfo body does not fit in its sha256 slot"
+        );
+        tcb_info.layout()?;
+        Ok(Self {
+            tcb_signing_cert,
+            tcb_info,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Parses a TCBInfo JSON document and the PEM issuer chain served with it
+    pub fn from_collateral(tcb_info: &[u8], tcb_info_issuer_chain: &[u8]) -> anyhow::Result<Self> {
+        Self::new(TcbInfo::parse(tcb_info)?, tcb_info_issuer_chain)
+    }
+
+    /// Public instances of the circuit, laid out as described at `TCB_INFO_FMSPC_INSTANCE`
+    pub fn instances(&self) -> Vec<F> {
+        [
+            pack_instances(&self.tcb_info.fmspc),
+            pack_instances(&self.tcb_info.pce_id),
+            pack_instances(&self.tcb_info.tcb_levels.commitment()),
+        ]
+        .concat()
+    }
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> Circuit<F> for TcbInfoVerifierCircuit<F, P> {
+    type Config = SgxDcapVerifierConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        SgxDcapVerifierConfig::configure_with_max_byte_sizes(
+            meta,
+            &P::params(),
+            Self::MAX_BYTE_SIZES.to_vec(),
+        )
+        .expect("circuit params should be valid")
+        .with_root_ca_pubkey(P::root_ca_pubkey())
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+
+        // see `SgxDcapVerifierCircuit::synthesize`, a certificate that does not parse fails the
+        // proof
+        let (cert_der, cert_layout) =
+            CertLayout::from_base64(&self.tcb_signing_cert).unwrap_or_default();
+        // a body that does not lay out fails the proof, like a certificate
+        let layout = self.tcb_info.layout().unwrap_or_default();
+        let tcb_levels = &self.tcb_info.tcb_levels;
+
+        let digests = config.digest(
+            &mut layouter,
+            &[
+                cert_der[cert_layout.tbs.clone()].to_vec(),
+                self.tcb_info.body.clone(),
+                tcb_levels.to_bytes(),
+            ],
+        )?;
+        let (tbs_digest, body_digest, tcb_levels_digest) = (&digests[0], &digests[1], &digests[2]);
+
+        let mut outputs = vec![];
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "ECDSA",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = config.new_context(region);
+                let ctx = &mut aux;
+
+                // Intel's root CA signs the TCB Signing certificate, which signs the tcbInfo body
+                let body = config.verify_signed_collateral(
+                    ctx,
+                    &mut config.base64_chip(),
+                    &self.tcb_signing_cert,
+                    &cert_layout.signature(&cert_der),
+                    tbs_digest,
+                    &self.tcb_info.body,
+                    &self.tcb_info.signature,
+                    body_digest,
+                )?;
+
+                // the TCB levels of the body, encoded like `TcbLevels::to_bytes`, are committed to
+                let encoded = config.load_bytes(ctx, &tcb_levels.to_bytes());
+                let mut instances = tcb_info_instances(
+                    &config,
+                    ctx,
+                    &body,
+                    &layout,
+                    &encoded,
+                    tcb_levels.levels().len(),
+                );
+                config.constrain_digest_input(ctx, tcb_levels_digest, &encoded);
+                instances.extend(config.pack_bytes(ctx, &tcb_levels_digest.output_bytes));
+                outputs = instances.iter().map(|x| x.cell()).collect();
+
+                config.finalize(ctx);
+                Ok(())
+            },
+        )?;
+
+        config.constrain_instances(&mut layouter, outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::tests::{
+        pcs_tcb_info_body, signed_tcb_info_document, tcb_info_body, tcb_info_document,
+        test_pck_cert_chain, TestRootCa, TEST_INTERMEDIATE_KEY,
+    };
+    use crate::tcb::{TcbLevel, TcbLevels, MAX_TCB_LEVELS};
+    use crate::x509::SGX_TCB_COMPONENTS;
+    use halo2_base::halo2_proofs::{
+        dev::MockProver,
+        halo2curves::{bn256::Fr, secp256r1::Fq},
+    };
+
+    // bytes of the body walked by the test circuit, rather than the whole sha256 slot
+    const TEST_BODY_LEN: usize = 2048;
+
+    // the walk takes more cells than the built-in advice columns hold in 2^17 rows
+    fn test_params() -> CircuitParams {
+        CircuitParams {
+            num_advice: 12,
+            ..CircuitParams::default()
+        }
+    }
+
+    // takes the fields of an unsigned tcbInfo body with the given TCB levels as witness
+    #[derive(Default)]
+    struct TcbInfoFieldsTestCircuit {
+        tcb_info: TcbInfo,
+        tcb_levels: TcbLevels,
+    }
+
+    impl Circuit<Fr> for TcbInfoFieldsTestCircuit {
+        type Config = SgxDcapVerifierConfig<Fr>;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            SgxDcapVerifierConfig::configure_with_params(meta, &test_params(), vec![64], 16)
+                .unwrap()
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load(&mut layouter)?;
+            let layout = self.tcb_info.layout().unwrap_or_default();
+            let mut outputs = vec![];
+            let mut first_pass = SKIP_FIRST_PASS;
+            layouter.assign_region(
+                || "tcbInfo",
+                |region| {
+                    if first_pass {
+                        first_pass = false;
+                        return Ok(());
+                    }
+                    let mut aux = config.new_context(region);
+                    let ctx = &mut aux;
+
+                    let mut body = self.tcb_info.body.clone();
+                    body.resize(TEST_BODY_LEN, 0);
+                    let body = config.load_bytes(ctx, &body);
+                    let encoded = config.load_bytes(ctx, &self.tcb_levels.to_bytes());
+                    let instances = tcb_info_instances(
+                        &config,
+                        ctx,
+                        &body,
+                        &layout,
+                        &encoded,
+                        self.tcb_levels.levels().len(),
+                    );
+                    outputs = instances.iter().map(|x| x.cell()).collect();
+
+                    config.finalize(ctx);
+                    Ok(())
+                },
+            )?;
+            config.constrain_instances(&mut layouter, outputs)
+        }
+    }
+
+    #[test]
+    fn test_tcb_info_fields() {
+        let k = 17;
+        for version in [2, 3] {
+            let (json, _) = tcb_info_document(&tcb_info_body(version));
+            let tcb_info = TcbInfo::parse(json.as_bytes()).unwrap();
+            assert!(tcb_info.body.len() <= TEST_BODY_LEN);
+            let instances = [
+                pack_instances(&tcb_info.fmspc),
+                pack_instances(&tcb_info.pce_id),
+            ]
+            .concat();
+            let circuit = TcbInfoFieldsTestCircuit {
+                tcb_levels: tcb_info.tcb_levels.clone(),
+                tcb_info,
+            };
+            MockProver::run(k, &circuit, vec![instances.clone()])
+                .unwrap()
+                .assert_satisfied();
+
+            let levels = circuit.tcb_info.tcb_levels.levels();
+            // the components of the first level with the status of the second one
+            let spliced = TcbLevel {
+                status: levels[1].status,
+                ..levels[0].clone()
+            };
+            // components of 1 where the body has 14
+            let truncated = TcbLevel {
+                sgx_tcb_components: [1; SGX_TCB_COMPONENTS],
+                ..levels[0].clone()
+            };
+            for tcb_levels in [
+                vec![spliced, levels[1].clone()],
+                vec![truncated, levels[1].clone()],
+                vec![levels[0].clone()],
+                vec![levels[1].clone(), levels[0].clone()],
+            ] {
+                let forged = TcbInfoFieldsTestCircuit {
+                    tcb_info: circuit.tcb_info.clone(),
+                    tcb_levels: TcbLevels::new(tcb_levels).unwrap(),
+                };
+                assert!(MockProver::run(k, &forged, vec![instances.clone()])
+                    .unwrap()
+                    .verify()
+                    .is_err());
+            }
+        }
+    }
+
+    #[test]
+    fn test_tcb_info_verifier() {
+        let params = TcbInfoCircuitParams::params();
+        let chain = test_pck_cert_chain()[1..].concat();
+        let body = pcs_tcb_info_body(16);
+        let json = signed_tcb_info_document(&body, Fq::from(TEST_INTERMEDIATE_KEY));
+        let circuit =
+            TcbInfoVerifierCircuit::<Fr, TestRootCa<TcbInfoCircuitParams>>::from_collateral(
+                json.as_bytes(),
+                &chain,
+            )
+            .unwrap();
+        let instances = circuit.instances();
+        MockProver::run(params.degree, &circuit, vec![instances.clone()])
+            .unwrap()
+            .assert_satisfied();
+
+        // the proof does not hold for other TCB levels
+        let mut tampered = instances;
+        tampered[TCB_INFO_TCB_LEVELS_INSTANCE] += Fr::from(1);
+        assert!(MockProver::run(params.degree, &circuit, vec![tampered])
+            .unwrap()
+            .verify()
+            .is_err());
+
+        // the body slot holds as many TCB levels as the circuit commits to
+        let json = signed_tcb_info_document(
+            &pcs_tcb_info_body(MAX_TCB_LEVELS),
+            Fq::from(TEST_INTERMEDIATE_KEY),
+        );
+        TcbInfoVerifierCircuit::<Fr>::from_collateral(json.as_bytes(), &chain).unwrap();
+    }
+}
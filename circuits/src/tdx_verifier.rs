@@ -0,0 +1,315 @@
+use halo2_base::utils::PrimeField;
+use halo2_base::{
+    halo2_proofs::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{Circuit, ConstraintSystem, Error},
+    },
+    SKIP_FIRST_PASS,
+};
+use std::marker::PhantomData;
+
+use crate::quote::{PckCertChain, TdxQuote, ENCLAVE_REPORT_LEN, QUOTE_HEADER_LEN, TD_REPORT_LEN};
+use crate::sgx_dcap_verifier::{
+    pack_instances, CircuitParamsSpec, SgxDcapCircuitParams, SgxDcapVerifierConfig,
+    BYTES_PER_INSTANCE,
+};
+use crate::x509::CertLayout;
+
+// offsets of the measurements in the signed quote body, i.e. header || TD report
+const MR_TD_OFFSET: usize = QUOTE_HEADER_LEN + 136;
+const RT_MR_OFFSET: usize = QUOTE_HEADER_LEN + 328;
+const MEASUREMENT_LEN: usize = 48;
+const RT_MR_COUNT: usize = 4;
+
+/// Number of public instances: MRTD followed by RTMR0-3, `BYTES_PER_INSTANCE` bytes per instance
+pub const TDX_NUM_INSTANCES: usize = (1 + RT_MR_COUNT) * MEASUREMENT_LEN / BYTES_PER_INSTANCE;
+
+/// Verifies a TDX quote, with the ECDSA chips configured with the parameters of `P`
+#[derive(Default, Clone)]
+pub struct TdxVerifierCircuit<F: PrimeField, P: CircuitParamsSpec = SgxDcapCircuitParams> {
+    // leaf, intermediate and root certificates in the QE report certification data, as PEM and
+    // base64 bodies
+    pub pck_cert_chain: PckCertChain,
+    pub quote: TdxQuote,
+    _marker: PhantomData<(F, P)>,
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> TdxVerifierCircuit<F, P> {
+    pub fn new(quote: TdxQuote) -> anyhow::Result<Self> {
+        Ok(Self {
+            pck_cert_chain: quote
+                .signature
+                .qe_report_certification_data
+                .certification_data
+                .pck_cert_chain()?,
+            quote,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Parses a raw TDX quote and takes all the witnesses of the circuit from it
+    pub fn from_quote(quote: &[u8]) -> anyhow::Result<Self> {
+        Self::new(TdxQuote::parse(quote)?)
+    }
+
+    /// Public instances of the circuit: MRTD followed by RTMR0-3
+    pub fn instances(&self) -> Vec<F> {
+        let td_report = &self.quote.td_report;
+        let mut measurements = td_report.mr_td.to_vec();
+        td_report
+            .rt_mr
+            .iter()
+            .for_each(|rt_mr| measurements.extend(rt_mr));
+        pack_instances(&measurements)
+    }
+}
+
+impl<F: PrimeField, P: CircuitParamsSpec> Circuit<F> for TdxVerifierCircuit<F, P> {
+    type Config = SgxDcapVerifierConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self {
+            pck_cert_chain: PckCertChain::default(),
+            quote: TdxQuote::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        SgxDcapVerifierConfig::configure(meta, &P::params())
+            .expect("circuit params should be valid")
+            .with_root_ca_pubkey(P::root_ca_pubkey())
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        config.load(&mut layouter)?;
+
+        // see `SgxDcapVerifierCircuit::synthesize`, certificates that do not parse fail the proof
+        let (leaf_der, leaf_layout) =
+            CertLayout::from_base64(&self.pck_cert_chain.leaf).unwrap_or_default();
+        let (intermediate_der, intermediate_layout) =
+            CertLayout::from_base64(&self.pck_cert_chain.intermediate).unwrap_or_default();
+
+        // the signed quote body fits in its sha256 slot, so all of its bytes are hashed in-circuit
+        // and the measurements can be taken from the hash input
+        let qe_report_cert_data = &self.quote.signature.qe_report_certification_data;
+        let digests = config.digest(
+            &mut layouter,
+            &[
+                qe_report_cert_data.qe_report.to_bytes(),
+                leaf_der[leaf_layout.tbs.clone()].to_vec(),
+                intermediate_der[intermediate_layout.tbs.clone()].to_vec(),
+                self.quote.signed_bytes(),
+                [
+                    &self.quote.signature.ecdsa_attestation_key[..],
+                    &qe_report_cert_data.qe_auth_data,
+                ]
+                .concat(),
+            ],
+        )?;
+        let (qe_report_digest, leaf_tbs_digest, intermediate_tbs_digest, quote_digest) =
+            (&digests[0], &digests[1], &digests[2], &digests[3]);
+        let attestation_key_digest = &digests[4];
+
+        let mut outputs = vec![];
+        let mut first_pass = SKIP_FIRST_PASS;
+        layouter.assign_region(
+            || "ECDSA",
+            |region| {
+                if first_pass {
+                    first_pass = false;
+                    return Ok(());
+                }
+
+                let mut aux = config.new_context(region);
+                let ctx = &mut aux;
+
+                // Intel's root CA signs the intermediate certificate
+                let mut base64 = config.base64_chip();
+                let intermediate_cert_assigned = config.decode_pem_cert(
+                    ctx,
+                    &mut base64,
+                    &self.pck_cert_chain.intermediate_pem,
+                )?;
+                let intermediate = config.parse_cert(ctx, &intermediate_cert_assigned);
+                let root_ca_pubkey = config.load_root_ca_pubkey(ctx);
+                config.verify_cert_signature(
+                    ctx,
+                    &intermediate_cert_assigned,
+                    &intermediate,
+                    &intermediate_layout.signature(&intermediate_der),
+                    &root_ca_pubkey,
+                    intermediate_tbs_digest,
+                );
+
+                // the intermediate CA signs the PCK leaf certificate
+                let leaf_cert_assigned =
+                    config.decode_pem_cert(ctx, &mut base64, &self.pck_cert_chain.leaf_pem)?;
+                let leaf = config.parse_cert(ctx, &leaf_cert_assigned);
+                config.verify_cert_signature(
+                    ctx,
+                    &leaf_cert_assigned,
+                    &leaf,
+                    &leaf_layout.signature(&leaf_der),
+                    &intermediate.pubkey,
+                    leaf_tbs_digest,
+                );
+
+                // PCK leaf certificate signs the QE report
+                let qe_report_signature =
+                    config.load_bytes(ctx, &qe_report_cert_data.qe_report_signature);
+                config.ecdsa_verify(ctx, &leaf.pubkey, &qe_report_signature, qe_report_digest);
+                config.constrain_digest_len(ctx, qe_report_digest, ENCLAVE_REPORT_LEN);
+
+                // attestation key signs the quote header and TD report body
+                let attestation_key =
+                    config.load_bytes(ctx, &self.quote.signature.ecdsa_attestation_key);
+                config.constrain_qe_report_data(
+                    ctx,
+                    qe_report_digest,
+                    &attestation_key,
+                    attestation_key_digest,
+                );
+                let quote_signature = config.load_bytes(ctx, &self.quote.signature.quote_signature);
+                config.ecdsa_verify(ctx, &attestation_key, &quote_signature, quote_digest);
+
+                config.constrain_digest_len(ctx, quote_digest, QUOTE_HEADER_LEN + TD_REPORT_LEN);
+                let mr_td = config.pack_bytes(
+                    ctx,
+                    &quote_digest.input_bytes[MR_TD_OFFSET..MR_TD_OFFSET + MEASUREMENT_LEN],
+                );
+                let rt_mr = config.pack_bytes(
+                    ctx,
+                    &quote_digest.input_bytes
+                        [RT_MR_OFFSET..RT_MR_OFFSET + RT_MR_COUNT * MEASUREMENT_LEN],
+                );
+                outputs = mr_td.iter().chain(rt_mr.iter()).map(|x| x.cell()).collect();
+
+                config.finalize(ctx);
+                Ok(())
+            },
+        )?;
+
+        config.constrain_instances(&mut layouter, outputs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::collateral::tests::{
+        sign, sign_with, test_pck_cert_chain, TestRootCa, TEST_LEAF_KEY,
+    };
+    use crate::quote::tests::{build_tdx_quote, to_pem};
+    use crate::quote::{EnclaveReportBody, TdReportBody};
+    use halo2_base::halo2_proofs::{
+        dev::MockProver,
+        halo2curves::{bn256::Fr, secp256r1::Fq},
+    };
+    use sha2::{Digest, Sha256};
+
+    // verifies the certificates of `test_pck_cert_chain`
+    type TestCircuit = TdxVerifierCircuit<Fr, TestRootCa<SgxDcapCircuitParams>>;
+
+    fn td_report() -> TdReportBody {
+        TdReportBody {
+            mr_td: [1; 48],
+            rt_mr: [[2; 48], [3; 48], [4; 48], [5; 48]],
+            report_data: [6; 64],
+            ..Default::default()
+        }
+    }
+
+    fn raw_quote(td_report: &TdReportBody) -> Vec<u8> {
+        let pem = [
+            to_pem(b"TGVhZg=="),
+            to_pem(b"SW50ZXJtZWRpYXRl"),
+            to_pem(b"Um9vdA=="),
+        ]
+        .concat();
+        build_tdx_quote(td_report, &EnclaveReportBody::default(), &[7; 64], &pem)
+    }
+
+    /// A v4 quote around `td_report` and `test_pck_cert_chain`, signed with a fresh attestation
+    /// key that its QE report binds, which the PCK leaf key signs
+    fn signed_quote(td_report: &TdReportBody) -> TdxQuote {
+        let mut quote = TdxQuote::parse(&build_tdx_quote(
+            td_report,
+            &EnclaveReportBody::default(),
+            &[0; 64],
+            &test_pck_cert_chain().concat(),
+        ))
+        .unwrap();
+        let (attestation_key, quote_signature) = sign(&quote.signed_bytes());
+        let signature = &mut quote.signature;
+        signature.ecdsa_attestation_key = attestation_key;
+        signature.quote_signature = quote_signature;
+        let qe_data = &mut signature.qe_report_certification_data;
+        let hash = Sha256::digest([&attestation_key[..], &qe_data.qe_auth_data].concat());
+        qe_data.qe_report.report_data[..32].copy_from_slice(&hash);
+        qe_data.qe_report_signature =
+            sign_with(Fq::from(TEST_LEAF_KEY), &qe_data.qe_report.to_bytes());
+        quote
+    }
+
+    #[test]
+    fn test_tdx_verifier() {
+        let k = SgxDcapCircuitParams::params().degree;
+        let circuit = TestCircuit::new(signed_quote(&td_report())).unwrap();
+        MockProver::run(k, &circuit, vec![circuit.instances()])
+            .unwrap()
+            .assert_satisfied();
+    }
+
+    #[test]
+    fn test_tdx_verifier_instances() {
+        let raw = raw_quote(&td_report());
+        let circuit = TdxVerifierCircuit::<Fr>::from_quote(&raw).unwrap();
+        assert_eq!(circuit.pck_cert_chain.leaf, b"TGVhZg==");
+
+        // the measurements are read at their offsets in the signed quote body
+        let signed = circuit.quote.signed_bytes();
+        let measurements = [
+            &signed[MR_TD_OFFSET..MR_TD_OFFSET + MEASUREMENT_LEN],
+            &signed[RT_MR_OFFSET..RT_MR_OFFSET + RT_MR_COUNT * MEASUREMENT_LEN],
+        ]
+        .concat();
+        let instances = circuit.instances();
+        assert_eq!(instances.len(), TDX_NUM_INSTANCES);
+        assert_eq!(instances, pack_instances::<Fr>(&measurements));
+        assert_eq!(instances[0], pack_instances::<Fr>(&[1; 16])[0]);
+        assert_eq!(
+            instances[TDX_NUM_INSTANCES - 1],
+            pack_instances::<Fr>(&[5; 16])[0]
+        );
+    }
+
+    #[test]
+    fn test_tdx_verifier_tampered_rt_mr() {
+        let k = SgxDcapCircuitParams::params().degree;
+        let circuit = TestCircuit::new(signed_quote(&td_report())).unwrap();
+
+        // RTMR2 of the signed quote is changed after the attestation key signed it
+        let mut tampered = circuit.clone();
+        tampered.quote.td_report.rt_mr[2][47] ^= 1;
+
+        // RTMR2 ends the 12th instance, and no other instance changes
+        let instances = circuit.instances();
+        let tampered_instances = tampered.instances();
+        let changed: Vec<usize> = (0..TDX_NUM_INSTANCES)
+            .filter(|i| instances[*i] != tampered_instances[*i])
+            .collect();
+        assert_eq!(changed, vec![11]);
+
+        // the tampered quote does not prove the measurements of the signed one
+        assert!(MockProver::run(k, &tampered, vec![instances])
+            .unwrap()
+            .verify()
+            .is_err());
+    }
+}
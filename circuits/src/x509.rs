@@ -0,0 +1,585 @@
+//! Host-side DER walker locating the fields of an X.509 certificate that the circuits verify.
+//!
+//! Only the handful of fields used by the circuits are located, everything else is skipped
+//! over by its length. All offsets are into the DER encoding, i.e. into the base64-decoded
+//! certificate bytes.
+use anyhow::{anyhow, ensure, Result};
+use base64::{engine::general_purpose, Engine};
+use std::ops::Range;
+
+const TAG_INTEGER: u8 = 0x02;
+const TAG_BIT_STRING: u8 = 0x03;
+const TAG_OCTET_STRING: u8 = 0x04;
+const TAG_SEQUENCE: u8 = 0x30;
+const TAG_UTC_TIME: u8 = 0x17;
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+const TAG_EXPLICIT_VERSION: u8 = 0xa0;
+/// DER bytes in front of a secp256r1 public key: the end of the id-ecPublicKey OID, the
+/// prime256v1 OID, the BIT STRING header with no unused bits and the uncompressed point prefix
+pub const SECP256R1_PUBKEY_PREFIX: [u8; 16] = [
+    0x02, 0x01, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+];
+
+/// DER bytes of a v3 TBSCertificate from its version up to the length of the serial number: the
+/// explicit version and the INTEGER tag
+pub(crate) const TBS_VERSION_HEADER: [u8; 6] = [0xa0, 0x03, 0x02, 0x01, 0x02, TAG_INTEGER];
+/// DER encoded ecdsa-with-SHA256 AlgorithmIdentifier, which follows the serial number
+pub(crate) const ECDSA_WITH_SHA256: [u8; 12] = [
+    0x30, 0x0a, 0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02,
+];
+/// DER bytes of a secp256r1 SubjectPublicKeyInfo in front of the key, ending with
+/// `SECP256R1_PUBKEY_PREFIX`
+pub(crate) const SECP256R1_SPKI_HEADER: [u8; 27] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00, 0x04,
+];
+
+// DER encoded OID 1.2.840.113741.1.13.1 of the SGX extension
+const SGX_EXTENSION_OID: [u8; 11] = [
+    0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01,
+];
+/// DER bytes in front of the PCEID value: OID 1.2.840.113741.1.13.1.3 and the OCTET STRING header
+pub const PCE_ID_HEADER: [u8; 14] = [
+    0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x03, 0x04, 0x02,
+];
+/// DER bytes in front of the FMSPC value: OID 1.2.840.113741.1.13.1.4 and the OCTET STRING header
+pub const FMSPC_HEADER: [u8; 14] = [
+    0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x04, 0x04, 0x06,
+];
+/// DER bytes in front of the TCB components of the SGX extension: OID 1.2.840.113741.1.13.1.2
+/// and the header of the TCB SEQUENCE, up to its 2-byte length
+pub(crate) const TCB_SEQUENCE_HEADER: [u8; 14] = [
+    0x06, 0x0a, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x02, 0x30, 0x82,
+];
+/// DER encoded OID 1.2.840.113741.1.13.1.2 of the TCB sequence, without the final component
+/// number
+pub(crate) const TCB_COMPONENT_OID_PREFIX: [u8; 12] = [
+    0x06, 0x0b, 0x2a, 0x86, 0x48, 0x86, 0xf8, 0x4d, 0x01, 0x0d, 0x01, 0x02,
+];
+/// Number of SGX TCB components, i.e. the CPUSVN components of the TCB sequence
+pub const SGX_TCB_COMPONENTS: usize = 16;
+// component number of PCESVN in the TCB sequence, the SGX TCB components are numbered 1 to 16
+pub(crate) const PCE_SVN_COMPONENT: u8 = 17;
+// component number of CPUSVN, which follows PCESVN as a 16-byte OCTET STRING
+pub(crate) const CPU_SVN_COMPONENT: u8 = 18;
+/// Max length of a certificate serial number (RFC 5280), which serial numbers are left-padded to
+pub const SERIAL_NUMBER_LEN: usize = 20;
+/// Cumulative number of days before each month of a common year
+pub(crate) const DAYS_BEFORE_MONTH: [u64; 12] =
+    [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+/// Offsets of the signed and signature fields of a DER encoded certificate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertLayout {
+    /// The whole TBSCertificate, header included, i.e. the message signed by the issuer
+    pub tbs: Range<usize>,
+    /// INTEGER value of the serial number, sign byte included
+    pub serial: Range<usize>,
+    /// UTCTime or GeneralizedTime value of the start of the validity period, see `unix_time`
+    pub not_before: Range<usize>,
+    /// UTCTime or GeneralizedTime value of the end of the validity period, see `unix_time`
+    pub not_after: Range<usize>,
+    /// Big-endian `x || y` of the subject's secp256r1 public key
+    pub pubkey: Range<usize>,
+    /// Big-endian `r` of the issuer's signature, without the DER sign byte
+    pub signature_r: Range<usize>,
+    /// Big-endian `s` of the issuer's signature, without the DER sign byte
+    pub signature_s: Range<usize>,
+}
+
+/// A DER TLV: the tag, the range of the whole element and the range of its contents
+struct Tlv {
+    tag: u8,
+    all: Range<usize>,
+    contents: Range<usize>,
+}
+
+fn read_tlv(der: &[u8], offset: usize) -> Result<Tlv> {
+    let header = der
+        .get(offset..offset + 2)
+        .ok_or_else(|| anyhow!("truncated DER element at {}", offset))?;
+    let (tag, len_byte) = (header[0], header[1]);
+    let (header_len, len) = if len_byte < 0x80 {
+        (2, len_byte as usize)
+    } else {
+        let num_len_bytes = (len_byte & 0x7f) as usize;
+        ensure!(
+            (1..=4).contains(&num_len_bytes),
+            "unsupported DER length at {}",
+            offset
+        );
+        let len_bytes = der
+            .get(offset + 2..offset + 2 + num_len_bytes)
+            .ok_or_else(|| anyhow!("truncated DER length at {}", offset))?;
+        let len = len_bytes
+            .iter()
+            .fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        (2 + num_len_bytes, len)
+    };
+    let contents = offset + header_len..offset + header_len + len;
+    ensure!(
+        contents.end <= der.len(),
+        "truncated DER element at {}",
+        offset
+    );
+    Ok(Tlv {
+        tag,
+        all: offset..contents.end,
+        contents,
+    })
+}
+
+fn expect_tlv(der: &[u8], offset: usize, tag: u8) -> Result<Tlv> {
+    let tlv = read_tlv(der, offset)?;
+    ensure!(
+        tlv.tag == tag,
+        "expected DER tag {:#04x} at {}, got {:#04x}",
+        tag,
+        offset,
+        tlv.tag
+    );
+    Ok(tlv)
+}
+
+/// Strips the sign byte of a DER integer so that it fits in 32 bytes
+fn unsigned_integer(der: &[u8], integer: &Tlv) -> Result<Range<usize>> {
+    let mut range = integer.contents.clone();
+    while range.len() > 32 && der[range.start] == 0 {
+        range.start += 1;
+    }
+    ensure!(
+        !range.is_empty() && range.len() <= 32,
+        "signature integer does not fit in 32 bytes"
+    );
+    Ok(range)
+}
+
+/// Locates `r` and `s` of the signature following the signature algorithm at `offset`
+fn signature_integers(der: &[u8], offset: usize) -> Result<(Range<usize>, Range<usize>)> {
+    let signature_algorithm = expect_tlv(der, offset, TAG_SEQUENCE)?;
+    let signature_value = expect_tlv(der, signature_algorithm.all.end, TAG_BIT_STRING)?;
+
+    // the BIT STRING wraps a DER `SEQUENCE { r INTEGER, s INTEGER }`
+    ensure!(
+        der.get(signature_value.contents.start) == Some(&0),
+        "invalid signature BIT STRING"
+    );
+    let signature = expect_tlv(der, signature_value.contents.start + 1, TAG_SEQUENCE)?;
+    let r = expect_tlv(der, signature.contents.start, TAG_INTEGER)?;
+    let s = expect_tlv(der, r.all.end, TAG_INTEGER)?;
+    Ok((unsigned_integer(der, &r)?, unsigned_integer(der, &s)?))
+}
+
+/// Reads a UTCTime or GeneralizedTime at `offset`
+fn read_time(der: &[u8], offset: usize) -> Result<Tlv> {
+    let time = read_tlv(der, offset)?;
+    ensure!(
+        [TAG_UTC_TIME, TAG_GENERALIZED_TIME].contains(&time.tag),
+        "expected a time at {}",
+        offset
+    );
+    Ok(time)
+}
+
+/// Decodes a UTCTime (`YYMMDDhhmmssZ`) or GeneralizedTime (`YYYYMMDDhhmmssZ`) value into a Unix
+/// timestamp. UTCTime years from 50 on are in the 1900s. Years from 1970 to 2099 are supported,
+/// in which every 4th year is a leap year, like in-circuit.
+pub fn unix_time(time: &[u8]) -> Result<u64> {
+    ensure!(
+        matches!(time.len(), 13 | 15)
+            && time[time.len() - 1] == b'Z'
+            && time[..time.len() - 1].iter().all(u8::is_ascii_digit),
+        "unsupported time format"
+    );
+    let number = |digits: &[u8]| {
+        digits
+            .iter()
+            .fold(0u64, |acc, digit| acc * 10 + (digit - b'0') as u64)
+    };
+    let (year, rest) = if time.len() == 13 {
+        let year = number(&time[..2]);
+        (if year < 50 { 2000 } else { 1900 } + year, &time[2..])
+    } else {
+        (number(&time[..4]), &time[4..])
+    };
+    let [month, day, hour, minute, second] = [0, 2, 4, 6, 8].map(|i| number(&rest[i..i + 2]));
+    ensure!(
+        (1970..2100).contains(&year)
+            && (1..=12).contains(&month)
+            && (1..=31).contains(&day)
+            && hour < 24
+            && minute < 60
+            && second < 60,
+        "unsupported time"
+    );
+
+    // years since 1969, so that every 4th one is a leap year
+    let years = year - 1969;
+    let leap_day = (years % 4 == 3 && month > 2) as u64;
+    let days =
+        365 * (year - 1970) + years / 4 + DAYS_BEFORE_MONTH[month as usize - 1] + leap_day + day
+            - 1;
+    Ok(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Left-pads the value of a positive DER INTEGER serial number to `SERIAL_NUMBER_LEN` bytes
+pub fn serial_number(integer: &[u8]) -> Result<[u8; SERIAL_NUMBER_LEN]> {
+    let start = integer
+        .iter()
+        .position(|byte| *byte != 0)
+        .ok_or_else(|| anyhow!("serial number is not positive"))?;
+    let value = &integer[start..];
+    ensure!(
+        value.len() <= SERIAL_NUMBER_LEN && integer[0] < 0x80,
+        "invalid serial number"
+    );
+    let mut serial = [0; SERIAL_NUMBER_LEN];
+    serial[SERIAL_NUMBER_LEN - value.len()..].copy_from_slice(value);
+    Ok(serial)
+}
+
+impl CertLayout {
+    /// Locates the fields of a DER encoded certificate
+    pub fn parse(der: &[u8]) -> Result<Self> {
+        let cert = expect_tlv(der, 0, TAG_SEQUENCE)?;
+        let tbs = expect_tlv(der, cert.contents.start, TAG_SEQUENCE)?;
+        let (signature_r, signature_s) = signature_integers(der, tbs.all.end)?;
+
+        // version, serialNumber, signature, issuer, validity, subject, subjectPublicKeyInfo
+        let mut field = read_tlv(der, tbs.contents.start)?;
+        if field.tag == TAG_EXPLICIT_VERSION {
+            field = read_tlv(der, field.all.end)?;
+        }
+        ensure!(field.tag == TAG_INTEGER, "invalid serialNumber");
+        let serial = field.contents.clone();
+        let signature = read_tlv(der, field.all.end)?;
+        let issuer = read_tlv(der, signature.all.end)?;
+        let validity = expect_tlv(der, issuer.all.end, TAG_SEQUENCE)?;
+        let not_before = read_time(der, validity.contents.start)?;
+        let not_after = read_time(der, not_before.all.end)?;
+        let subject = read_tlv(der, validity.all.end)?;
+        let field = read_tlv(der, subject.all.end)?;
+        ensure!(field.tag == TAG_SEQUENCE, "invalid subjectPublicKeyInfo");
+        let algorithm = expect_tlv(der, field.contents.start, TAG_SEQUENCE)?;
+        let subject_public_key = expect_tlv(der, algorithm.all.end, TAG_BIT_STRING)?;
+        // unused bits byte, then the uncompressed point
+        let point = subject_public_key.contents;
+        let pubkey = point.start + 2..point.end;
+        ensure!(
+            point.len() == 66
+                && der[pubkey.start - SECP256R1_PUBKEY_PREFIX.len()..pubkey.start]
+                    == SECP256R1_PUBKEY_PREFIX,
+            "subject public key is not an uncompressed secp256r1 point"
+        );
+
+        Ok(Self {
+            tbs: tbs.all,
+            serial,
+            not_before: not_before.contents,
+            not_after: not_after.contents,
+            pubkey,
+            signature_r,
+            signature_s,
+        })
+    }
+
+    /// The validity period of the certificate as Unix timestamps, see `unix_time`
+    pub fn validity(&self, der: &[u8]) -> Result<(u64, u64)> {
+        Ok((
+            unix_time(&der[self.not_before.clone()])?,
+            unix_time(&der[self.not_after.clone()])?,
+        ))
+    }
+
+    /// Big-endian `r || s` of the issuer's signature, each left-padded to 32 bytes
+    pub fn signature(&self, der: &[u8]) -> [u8; 64] {
+        let mut signature = [0; 64];
+        for (half, integer) in signature
+            .chunks_mut(32)
+            .zip([&self.signature_r, &self.signature_s])
+        {
+            half[32 - integer.len()..].copy_from_slice(&der[integer.clone()]);
+        }
+        signature
+    }
+
+    /// The serial number of the certificate, see `serial_number`
+    pub fn serial_number(&self, der: &[u8]) -> Result<[u8; SERIAL_NUMBER_LEN]> {
+        serial_number(&der[self.serial.clone()])
+    }
+
+    /// Base64-decodes a PEM certificate body and locates its fields
+    pub fn from_base64(encoded: &[u8]) -> Result<(Vec<u8>, Self)> {
+        let der = general_purpose::STANDARD.decode(encoded)?;
+        let layout = Self::parse(&der)?;
+        Ok((der, layout))
+    }
+}
+
+/// Offsets of the signed fields and the revoked serial numbers of a DER encoded CRL
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrlLayout {
+    /// The whole TBSCertList, header included, i.e. the message signed by the issuer
+    pub tbs: Range<usize>,
+    /// INTEGER values of the serial numbers of the revoked certificates
+    pub revoked_serials: Vec<Range<usize>>,
+    /// Big-endian `r` of the issuer's signature, without the DER sign byte
+    pub signature_r: Range<usize>,
+    /// Big-endian `s` of the issuer's signature, without the DER sign byte
+    pub signature_s: Range<usize>,
+}
+
+impl CrlLayout {
+    /// Locates the fields of a DER encoded CRL
+    pub fn parse(der: &[u8]) -> Result<Self> {
+        let crl = expect_tlv(der, 0, TAG_SEQUENCE)?;
+        let tbs = expect_tlv(der, crl.contents.start, TAG_SEQUENCE)?;
+        let (signature_r, signature_s) = signature_integers(der, tbs.all.end)?;
+
+        // version, signature, issuer, thisUpdate, then the optional nextUpdate and
+        // revokedCertificates
+        let mut field = read_tlv(der, tbs.contents.start)?;
+        if field.tag == TAG_INTEGER {
+            field = read_tlv(der, field.all.end)?;
+        }
+        for _ in 0..3 {
+            field = read_tlv(der, field.all.end)?;
+        }
+        let mut revoked_serials = vec![];
+        let mut offset = field.all.end;
+        while offset < tbs.contents.end {
+            let field = read_tlv(der, offset)?;
+            offset = field.all.end;
+            match field.tag {
+                TAG_UTC_TIME | TAG_GENERALIZED_TIME => continue,
+                TAG_SEQUENCE => {
+                    let mut entry_offset = field.contents.start;
+                    while entry_offset < field.contents.end {
+                        let entry = expect_tlv(der, entry_offset, TAG_SEQUENCE)?;
+                        let serial = expect_tlv(der, entry.contents.start, TAG_INTEGER)?;
+                        revoked_serials.push(serial.contents);
+                        entry_offset = entry.all.end;
+                    }
+                }
+                // crlExtensions
+                _ => break,
+            }
+        }
+
+        Ok(Self {
+            tbs: tbs.all,
+            revoked_serials,
+            signature_r,
+            signature_s,
+        })
+    }
+
+    /// The serial numbers of the revoked certificates, see `serial_number`
+    pub fn revoked_serials(&self, der: &[u8]) -> Result<Vec<[u8; SERIAL_NUMBER_LEN]>> {
+        self.revoked_serials
+            .iter()
+            .map(|serial| serial_number(&der[serial.clone()]))
+            .collect()
+    }
+}
+
+/// Offsets of the values in the SGX extension of a PCK certificate
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SgxExtensionLayout {
+    pub pce_id: Range<usize>,
+    pub fmspc: Range<usize>,
+    /// Big-endian INTEGER values of the 16 SGX TCB components, each 1 or 2 bytes long
+    pub sgx_tcb_components: [Range<usize>; SGX_TCB_COMPONENTS],
+    /// Big-endian INTEGER value of PCESVN
+    pub pce_svn: Range<usize>,
+}
+
+/// TCB of a PCK certificate, compared against the TCB levels of Intel's TCBInfo
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PckTcb {
+    pub sgx_tcb_components: [u8; SGX_TCB_COMPONENTS],
+    pub pce_svn: u16,
+}
+
+/// DER bytes in front of the INTEGER value of TCB component `number`: its OID and the INTEGER
+/// header of a `len`-byte value
+pub fn tcb_component_header(number: u8, len: usize) -> Vec<u8> {
+    let mut header = TCB_COMPONENT_OID_PREFIX.to_vec();
+    header.extend([number, TAG_INTEGER, len as u8]);
+    header
+}
+
+/// Finds the value following `header` in `der[range]`
+fn find_value(der: &[u8], range: Range<usize>, header: &[u8], len: usize) -> Result<Range<usize>> {
+    let start = der[range.clone()]
+        .windows(header.len())
+        .position(|window| window == header)
+        .map(|position| range.start + position + header.len())
+        .ok_or_else(|| anyhow!("SGX extension value not found"))?;
+    ensure!(start + len <= range.end, "truncated SGX extension value");
+    Ok(start..start + len)
+}
+
+/// Finds the INTEGER value of TCB component `number` in `der[range]`
+fn find_tcb_component(
+    der: &[u8],
+    range: Range<usize>,
+    number: u8,
+    max_len: usize,
+) -> Result<Range<usize>> {
+    let mut oid = TCB_COMPONENT_OID_PREFIX.to_vec();
+    oid.push(number);
+    let start = find_value(der, range, &oid, 0)?.start;
+    let integer = expect_tlv(der, start, TAG_INTEGER)?;
+    ensure!(
+        (1..=max_len).contains(&integer.contents.len()),
+        "invalid TCB component {}",
+        number
+    );
+    Ok(integer.contents)
+}
+
+fn be_value(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0u64, |acc, byte| (acc << 8) | *byte as u64)
+}
+
+impl SgxExtensionLayout {
+    /// Locates PCEID, FMSPC and the TCB in the SGX extension (OID 1.2.840.113741.1.13.1) of a DER
+    /// encoded PCK certificate
+    pub fn parse(der: &[u8]) -> Result<Self> {
+        let oid = der
+            .windows(SGX_EXTENSION_OID.len())
+            .position(|window| window == SGX_EXTENSION_OID)
+            .ok_or_else(|| anyhow!("certificate has no SGX extension"))?;
+        // the extension value is an OCTET STRING wrapping the sequence of SGX values
+        let extension = expect_tlv(der, oid + SGX_EXTENSION_OID.len(), TAG_OCTET_STRING)?;
+
+        let contents = extension.contents;
+
+        let mut sgx_tcb_components = vec![];
+        for number in 1..=SGX_TCB_COMPONENTS as u8 {
+            // values up to 255, with a sign byte from 128 on
+            sgx_tcb_components.push(find_tcb_component(der, contents.clone(), number, 2)?);
+        }
+        Ok(Self {
+            pce_id: find_value(der, contents.clone(), &PCE_ID_HEADER, 2)?,
+            fmspc: find_value(der, contents.clone(), &FMSPC_HEADER, 6)?,
+            sgx_tcb_components: sgx_tcb_components.try_into().unwrap(),
+            pce_svn: find_tcb_component(der, contents, PCE_SVN_COMPONENT, 3)?,
+        })
+    }
+
+    /// Offset of the TCB sequence, i.e. of its `TCB_SEQUENCE_HEADER`. The value of the first
+    /// component follows the header, the 2-byte length and the 17 bytes of the component's
+    /// SEQUENCE header, OID and INTEGER header.
+    pub fn tcb_offset(&self) -> usize {
+        let first_value = TCB_SEQUENCE_HEADER.len() + 2 + 4 + TCB_COMPONENT_OID_PREFIX.len() + 1;
+        self.sgx_tcb_components[0].start.saturating_sub(first_value)
+    }
+
+    /// Reads the TCB of the PCK certificate
+    pub fn pck_tcb(&self, der: &[u8]) -> Result<PckTcb> {
+        let mut sgx_tcb_components = [0; SGX_TCB_COMPONENTS];
+        for (component, range) in sgx_tcb_components
+            .iter_mut()
+            .zip(self.sgx_tcb_components.iter())
+        {
+            *component = u8::try_from(be_value(&der[range.clone()]))?;
+        }
+        Ok(PckTcb {
+            sgx_tcb_components,
+            pce_svn: u16::try_from(be_value(&der[self.pce_svn.clone()]))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quote::PckCertChain;
+    use crate::sgx_dcap_verifier::{
+        INTEL_SGX_ROOT_CA_PUBKEY, SGX_TCB_SEARCH, SPKI_SEARCH, VALIDITY_SEARCH,
+    };
+
+    #[test]
+    fn test_parse_cert_layout() {
+        let chain = PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem")).unwrap();
+
+        let (leaf, layout) = CertLayout::from_base64(&chain.leaf).unwrap();
+        assert_eq!(leaf.len(), 1271);
+        assert_eq!(layout.tbs, 4..1185);
+        // the serial number has a sign byte
+        assert_eq!(layout.serial, 15..36);
+        assert_eq!(layout.serial_number(&leaf).unwrap()[..2], [0xd9, 0xea]);
+        assert_eq!(&leaf[layout.not_before.clone()], b"221128220210Z");
+        assert_eq!(layout.validity(&leaf).unwrap(), (1669672930, 1890597730));
+        assert_eq!(layout.pubkey, 335..399);
+        // the validity and the public key are within the positions searched in-circuit
+        assert!(VALIDITY_SEARCH.contains(&(layout.not_before.start - 4)));
+        assert!(SPKI_SEARCH.contains(&(layout.pubkey.start - SECP256R1_SPKI_HEADER.len())));
+        // r has a sign byte, s does not
+        assert_eq!(layout.signature_r, 1205..1237);
+        assert_eq!(layout.signature_s, 1239..1271);
+        let signature = layout.signature(&leaf);
+        assert_eq!(signature[..32], leaf[layout.signature_r.clone()]);
+        assert_eq!(signature[32..], leaf[layout.signature_s.clone()]);
+
+        let (_, layout) = CertLayout::from_base64(&chain.intermediate).unwrap();
+        assert_eq!(layout.tbs, 4..581);
+        assert_eq!(layout.pubkey, 327..391);
+
+        let (root, layout) = CertLayout::from_base64(&chain.root).unwrap();
+        assert_eq!(root[layout.pubkey.clone()], INTEL_SGX_ROOT_CA_PUBKEY);
+        assert_eq!(layout.validity(&root).unwrap().1, 2524607999);
+    }
+
+    #[test]
+    fn test_unix_time() {
+        assert_eq!(unix_time(b"700101000000Z").unwrap(), 0);
+        assert_eq!(unix_time(b"20240229120000Z").unwrap(), 1709208000);
+        assert_eq!(unix_time(b"20500101000000Z").unwrap(), 2524608000);
+        assert_eq!(
+            unix_time(b"491231235959Z").unwrap() + 1,
+            unix_time(b"20500101000000Z").unwrap()
+        );
+        assert!(unix_time(b"691231235959Z").is_err());
+        assert!(unix_time(b"21000101000000Z").is_err());
+        assert!(unix_time(b"2401010000Z").is_err());
+        // out of range fields would alias other times
+        assert!(unix_time(b"991231999999Z").is_err());
+        assert!(unix_time(b"991232000000Z").is_err());
+        assert!(unix_time(b"991231240000Z").is_err());
+    }
+
+    #[test]
+    fn test_parse_sgx_extension_layout() {
+        let chain = PckCertChain::parse(include_bytes!("../assets/pck_cert_chain.pem")).unwrap();
+
+        let (leaf, _) = CertLayout::from_base64(&chain.leaf).unwrap();
+        let layout = SgxExtensionLayout::parse(&leaf).unwrap();
+        assert_eq!(leaf[layout.pce_id.clone()], [0x00, 0x00]);
+        assert_eq!(
+            leaf[layout.fmspc.clone()],
+            [0x00, 0x60, 0x6a, 0x00, 0x00, 0x00]
+        );
+
+        // 255 is encoded with a sign byte
+        assert_eq!(layout.sgx_tcb_components[0], 702..703);
+        assert_eq!(layout.sgx_tcb_components[4], 774..776);
+        let tcb = layout.pck_tcb(&leaf).unwrap();
+        assert_eq!(
+            tcb.sgx_tcb_components,
+            [7, 9, 3, 3, 255, 255, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        assert_eq!(tcb.pce_svn, 13);
+        assert_eq!(layout.tcb_offset(), 669);
+        assert_eq!(leaf[669..669 + TCB_SEQUENCE_HEADER.len()], TCB_SEQUENCE_HEADER);
+        assert!(SGX_TCB_SEARCH.contains(&layout.tcb_offset()));
+
+        // CA certificates have no SGX extension
+        let (root, _) = CertLayout::from_base64(&chain.root).unwrap();
+        assert!(SgxExtensionLayout::parse(&root).is_err());
+    }
+}